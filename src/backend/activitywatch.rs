@@ -0,0 +1,431 @@
+use std::fmt;
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::watch;
+use ureq::Agent;
+
+use crate::backend::idle_monitoring::{AbstractIdleChecker, IdleInfo};
+
+const HEARTBEAT_INTERVAL_SECS: u64 = 5;
+// Heartbeats this close together are merged into one event
+const PULSETIME_SECS: u64 = 2 * HEARTBEAT_INTERVAL_SECS;
+// How often the AFK watcher is asked, in between the last answer is reused
+const AFK_POLL_INTERVAL: StdDuration = StdDuration::from_secs(5);
+// The AFK watcher reports every few seconds. Older news means it stopped.
+const AFK_STALE_SECS: i64 = 60;
+
+#[derive(Debug)]
+pub enum ActivityWatchError {
+    Http(ureq::Error),
+    NoAfkWatcher,
+}
+
+impl fmt::Display for ActivityWatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActivityWatchError::Http(error) => write!(f, "{}", error),
+            ActivityWatchError::NoAfkWatcher => write!(f, "aw-watcher-afk is not running"),
+        }
+    }
+}
+
+impl From<ureq::Error> for ActivityWatchError {
+    fn from(error: ureq::Error) -> Self {
+        ActivityWatchError::Http(error)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerInfo {
+    hostname: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Bucket {
+    id: String,
+    #[serde(rename = "type")]
+    bucket_type: String,
+    hostname: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityEvent {
+    timestamp: DateTime<Utc>,
+    // In seconds
+    duration: f64,
+    data: Value,
+}
+
+// Talks to the REST API of a local aw-server.
+pub struct ActivityWatchClient {
+    url: String,
+    agent: Agent,
+}
+
+impl ActivityWatchClient {
+    pub fn new(url: &str) -> Self {
+        let config = Agent::config_builder()
+            .timeout_global(Some(StdDuration::from_secs(2)))
+            .build();
+        ActivityWatchClient {
+            url: url.trim_end_matches('/').to_string(),
+            agent: Agent::new_with_config(config),
+        }
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/api/0/{}", self.url, path)
+    }
+
+    fn hostname(&self) -> Result<String, ActivityWatchError> {
+        let info: ServerInfo = self
+            .agent
+            .get(self.endpoint("info"))
+            .call()?
+            .body_mut()
+            .read_json()?;
+        Ok(info.hostname)
+    }
+
+    // Creates our bucket unless it's there already, and returns its id.
+    pub fn create_bucket(&self) -> Result<String, ActivityWatchError> {
+        let hostname = self.hostname()?;
+        let bucket_id = format!("stretch-break_{}", hostname);
+        let result = self
+            .agent
+            .post(self.endpoint(&format!("buckets/{}", bucket_id)))
+            .send_json(json!({
+                "client": "stretch-break",
+                "type": "stretch-break.mode",
+                "hostname": hostname,
+            }));
+        match result {
+            // 304 means it exists already
+            Ok(_) | Err(ureq::Error::StatusCode(304)) => Ok(bucket_id),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn heartbeat(
+        &self,
+        bucket_id: &str,
+        idle_info: &IdleInfo,
+    ) -> Result<(), ActivityWatchError> {
+        self.agent
+            .post(self.endpoint(&format!("buckets/{}/heartbeat", bucket_id)))
+            .query("pulsetime", PULSETIME_SECS.to_string())
+            .send_json(json!({
+                "timestamp": idle_info.last_checked.to_rfc3339(),
+                "duration": 0,
+                "data": {"status": idle_info.mode_name()},
+            }))?;
+        Ok(())
+    }
+
+    // Prefers the AFK watcher on this computer, as aw-server may also hold synced buckets.
+    fn find_afk_bucket(&self) -> Result<String, ActivityWatchError> {
+        let hostname = self.hostname()?;
+        let buckets: Vec<Bucket> = self
+            .agent
+            .get(self.endpoint("buckets/"))
+            .call()?
+            .body_mut()
+            .read_json::<serde_json::Map<String, Value>>()?
+            .into_values()
+            .filter_map(|bucket| serde_json::from_value(bucket).ok())
+            .filter(|bucket: &Bucket| bucket.bucket_type == "afkstatus")
+            .collect();
+        buckets
+            .iter()
+            .find(|bucket| bucket.hostname == hostname)
+            .or(buckets.first())
+            .map(|bucket| bucket.id.clone())
+            .ok_or(ActivityWatchError::NoAfkWatcher)
+    }
+
+    // When the user went away, or None while they're at the computer
+    fn afk_since(
+        &self,
+        bucket_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, ActivityWatchError> {
+        let events: Vec<ActivityEvent> = self
+            .agent
+            .get(self.endpoint(&format!("buckets/{}/events", bucket_id)))
+            .query("limit", "1")
+            .call()?
+            .body_mut()
+            .read_json()?;
+        let latest = events.first().ok_or(ActivityWatchError::NoAfkWatcher)?;
+        let end = latest.timestamp + Duration::milliseconds((latest.duration * 1000.0) as i64);
+        if now - end > Duration::seconds(AFK_STALE_SECS) {
+            return Err(ActivityWatchError::NoAfkWatcher);
+        }
+        Ok(match latest.data["status"].as_str() {
+            Some("afk") => Some(latest.timestamp),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfkState {
+    AwaySince(DateTime<Utc>),
+    Present,
+    // ActivityWatch didn't answer, so the session's own idle time is used
+    Unknown,
+}
+
+fn fetch_afk_since(
+    client: &ActivityWatchClient,
+    afk_bucket: &mut Option<String>,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, ActivityWatchError> {
+    let bucket_id = match afk_bucket.take() {
+        Some(bucket_id) => bucket_id,
+        None => client.find_afk_bucket()?,
+    };
+    let result = client.afk_since(&bucket_id, now);
+    // Look again next time, in case the watcher moved to another bucket
+    *afk_bucket = result.is_ok().then_some(bucket_id);
+    result
+}
+
+// Asks aw-watcher-afk in the background and publishes its answer, so a slow aw-server never
+// holds up the idle monitor.
+pub fn watch_afk_state(client: ActivityWatchClient) -> watch::Receiver<AfkState> {
+    let (state_sender, state_receiver) = watch::channel(AfkState::Unknown);
+    thread::spawn(move || {
+        let mut afk_bucket = None;
+        let mut reported_failure = false;
+        while !state_sender.is_closed() {
+            let state = match fetch_afk_since(&client, &mut afk_bucket, Utc::now()) {
+                Ok(Some(afk_since)) => AfkState::AwaySince(afk_since),
+                Ok(None) => AfkState::Present,
+                Err(error) => {
+                    // Only worth mentioning when it stops working
+                    if !reported_failure {
+                        println!(
+                            "Using the session's idle time, as ActivityWatch failed: {}",
+                            error
+                        );
+                    }
+                    AfkState::Unknown
+                }
+            };
+            reported_failure = state == AfkState::Unknown;
+            state_sender.send_replace(state);
+            thread::sleep(AFK_POLL_INTERVAL);
+        }
+    });
+    state_receiver
+}
+
+// Takes idle time from aw-watcher-afk. That only notices being away after its timeout, three
+// minutes by default, so breaks only start counting down after that. Falls back to the
+// wrapped checker while ActivityWatch isn't running.
+pub struct ActivityWatchIdleChecker<T: AbstractIdleChecker> {
+    afk_state: watch::Receiver<AfkState>,
+    fallback: T,
+}
+
+impl<T: AbstractIdleChecker> ActivityWatchIdleChecker<T> {
+    pub fn new(afk_state: watch::Receiver<AfkState>, fallback: T) -> Self {
+        ActivityWatchIdleChecker {
+            afk_state,
+            fallback,
+        }
+    }
+
+    fn idle_secs_at(&self, now: DateTime<Utc>) -> u64 {
+        let afk_state = *self.afk_state.borrow();
+        match afk_state {
+            AfkState::AwaySince(afk_since) => (now - afk_since).num_seconds().max(0) as u64,
+            AfkState::Present => 0,
+            AfkState::Unknown => self.fallback.get_idle_time_in_seconds(),
+        }
+    }
+}
+
+impl<T: AbstractIdleChecker> AbstractIdleChecker for ActivityWatchIdleChecker<T> {
+    fn get_idle_time_in_seconds(&self) -> u64 {
+        self.idle_secs_at(Utc::now())
+    }
+}
+
+// Sends the timer mode as heartbeats, so breaks show up in the ActivityWatch timeline.
+pub fn send_heartbeats_forever(
+    client: ActivityWatchClient,
+    idle_info_recv: watch::Receiver<IdleInfo>,
+) {
+    let mut bucket_id: Option<String> = None;
+    let mut reported_failure = false;
+    // Stops along with the idle monitor
+    while idle_info_recv.has_changed().is_ok() {
+        let result = match &bucket_id {
+            Some(bucket_id) => client.heartbeat(bucket_id, &idle_info_recv.borrow()),
+            None => client.create_bucket().map(|id| {
+                println!("Sending heartbeats to ActivityWatch bucket {}", id);
+                bucket_id = Some(id);
+            }),
+        };
+        match result {
+            Ok(()) => reported_failure = false,
+            Err(error) => {
+                if !reported_failure {
+                    println!("Could not send heartbeats to ActivityWatch: {}", error);
+                    reported_failure = true;
+                }
+                bucket_id = None;
+            }
+        }
+        thread::sleep(StdDuration::from_secs(HEARTBEAT_INTERVAL_SECS));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::TimeZone;
+    use tiny_http::{Header, Response, Server};
+
+    use super::*;
+    use crate::backend::idle_monitoring::{DebouncedIdleState, MockIdleChecker, ModeState};
+
+    type ReceivedRequests = Arc<Mutex<Vec<(String, String)>>>;
+
+    // A stand-in for aw-server that answers from a fixed set of responses and remembers what
+    // it was sent.
+    fn start_fake_server(
+        responses: Vec<(&'static str, u16, String)>,
+    ) -> (String, ReceivedRequests) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let received = ReceivedRequests::default();
+        let received_ref = received.clone();
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let path = request.url().to_string();
+                received_ref.lock().unwrap().push((path.clone(), body));
+                let (status, response_body) = responses
+                    .iter()
+                    .find(|(prefix, _, _)| path.starts_with(prefix))
+                    .map(|(_, status, body)| (*status, body.clone()))
+                    .unwrap_or((404, String::new()));
+                let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+                request
+                    .respond(
+                        Response::from_string(response_body)
+                            .with_status_code(status)
+                            .with_header(header),
+                    )
+                    .ok();
+            }
+        });
+        (url, received)
+    }
+
+    fn make_idle_info() -> IdleInfo {
+        let now = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        IdleInfo {
+            last_mode_state: ModeState::Break {
+                progress_towards_finish: Duration::zero(),
+                idle_state: DebouncedIdleState::Idle { idle_since: now },
+            },
+            ..IdleInfo::for_tests(now)
+        }
+    }
+
+    #[test]
+    fn heartbeats_report_the_mode() {
+        let (url, received) = start_fake_server(vec![
+            ("/api/0/info", 200, String::from(r#"{"hostname": "desk"}"#)),
+            (
+                "/api/0/buckets/stretch-break_desk/heartbeat",
+                200,
+                String::from("{}"),
+            ),
+            ("/api/0/buckets/stretch-break_desk", 304, String::new()),
+        ]);
+        let client = ActivityWatchClient::new(&url);
+
+        let bucket_id = client.create_bucket().unwrap();
+        assert_eq!(bucket_id, "stretch-break_desk");
+        client.heartbeat(&bucket_id, &make_idle_info()).unwrap();
+
+        let received = received.lock().unwrap();
+        let (path, body) = received.last().unwrap();
+        assert_eq!(
+            path,
+            "/api/0/buckets/stretch-break_desk/heartbeat?pulsetime=10"
+        );
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["data"]["status"], "break");
+        assert_eq!(body["timestamp"], "2025-02-03T12:00:00+00:00");
+    }
+
+    #[test]
+    fn idle_time_comes_from_the_afk_watcher() {
+        let now = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        let (url, _) = start_fake_server(vec![
+            ("/api/0/info", 200, String::from(r#"{"hostname": "desk"}"#)),
+            (
+                "/api/0/buckets/aw-watcher-afk_desk/events",
+                200,
+                String::from(
+                    r#"[{"timestamp": "2025-02-03T11:56:00Z", "duration": 230.0, "data": {"status": "afk"}}]"#,
+                ),
+            ),
+            (
+                "/api/0/buckets/",
+                200,
+                String::from(
+                    r#"{
+                        "aw-watcher-afk_laptop": {"id": "aw-watcher-afk_laptop", "type": "afkstatus", "hostname": "laptop"},
+                        "aw-watcher-afk_desk": {"id": "aw-watcher-afk_desk", "type": "afkstatus", "hostname": "desk"},
+                        "aw-watcher-window_desk": {"id": "aw-watcher-window_desk", "type": "currentwindow", "hostname": "desk"}
+                    }"#,
+                ),
+            ),
+        ]);
+        let client = ActivityWatchClient::new(&url);
+        let mut afk_bucket = None;
+        let afk_since = fetch_afk_since(&client, &mut afk_bucket, now).unwrap();
+        assert_eq!(afk_bucket.as_deref(), Some("aw-watcher-afk_desk"));
+
+        let mut fallback = MockIdleChecker::new();
+        fallback.expect_get_idle_time_in_seconds().never();
+        let (_afk_state_sender, afk_state) =
+            watch::channel(AfkState::AwaySince(afk_since.unwrap()));
+        let checker = ActivityWatchIdleChecker::new(afk_state, fallback);
+
+        assert_eq!(checker.idle_secs_at(now), 240);
+        // Reuses the answer in between
+        assert_eq!(checker.idle_secs_at(now + Duration::seconds(2)), 242);
+    }
+
+    #[test]
+    fn falls_back_when_activitywatch_is_unavailable() {
+        let (url, _) = start_fake_server(vec![]);
+        let mut afk_bucket = None;
+        assert!(
+            fetch_afk_since(&ActivityWatchClient::new(&url), &mut afk_bucket, Utc::now()).is_err()
+        );
+
+        let mut fallback = MockIdleChecker::new();
+        fallback
+            .expect_get_idle_time_in_seconds()
+            .return_const(7u64);
+        let (_afk_state_sender, afk_state) = watch::channel(AfkState::Unknown);
+        let checker = ActivityWatchIdleChecker::new(afk_state, fallback);
+
+        assert_eq!(checker.idle_secs_at(Utc::now()), 7);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::backend::history::BreakOutcome;
+use crate::backend::idle_monitoring::{IdleInfo, ModeState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakEventKind {
+    PreBreak,
+    BreakStarted,
+    BreakEnded(BreakOutcome),
+    PresenceChanged,
+}
+
+impl BreakEventKind {
+    // As seen by hooks and other integrations
+    pub fn name(&self) -> &'static str {
+        match self {
+            BreakEventKind::PreBreak => "pre_break",
+            BreakEventKind::BreakStarted => "break_start",
+            BreakEventKind::BreakEnded(BreakOutcome::Completed) => "break_end",
+            BreakEventKind::BreakEnded(BreakOutcome::Skipped) => "skip",
+            BreakEventKind::BreakEnded(BreakOutcome::Postponed { .. }) => "postpone",
+            BreakEventKind::BreakEnded(BreakOutcome::PostponedForMeeting { .. }) => {
+                "meeting_postpone"
+            }
+            BreakEventKind::PresenceChanged => "presence_change",
+        }
+    }
+}
+
+// Something worth telling the outside world about, along with the state right after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakEvent {
+    pub kind: BreakEventKind,
+    pub idle_info: IdleInfo,
+}
+
+// Events that can be read from the state alone. How a break ended can't, so that is reported
+// by whoever ends it.
+pub fn state_changes(previous: &IdleInfo, current: &IdleInfo) -> Vec<BreakEventKind> {
+    let mut changes = Vec::new();
+    if previous.presence_mode != current.presence_mode {
+        changes.push(BreakEventKind::PresenceChanged);
+    }
+    match (previous.last_mode_state, current.last_mode_state) {
+        (ModeState::PreBreak { .. }, ModeState::PreBreak { .. }) => {}
+        (ModeState::Break { .. }, ModeState::Break { .. }) => {}
+        (_, ModeState::PreBreak { .. }) => changes.push(BreakEventKind::PreBreak),
+        (_, ModeState::Break { .. }) => changes.push(BreakEventKind::BreakStarted),
+        _ => {}
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::backend::idle_monitoring::{DebouncedIdleState, PresenceMode};
+
+    fn make_idle_info(last_mode_state: ModeState, presence_mode: PresenceMode) -> IdleInfo {
+        IdleInfo {
+            last_mode_state,
+            presence_mode,
+            ..IdleInfo::for_tests(Utc::now())
+        }
+    }
+
+    #[test]
+    fn reports_entering_prebreak_and_break_once() {
+        let now = Utc::now();
+        let normal = make_idle_info(
+            ModeState::Normal {
+                progress_towards_break: Duration::seconds(1200),
+                progress_towards_reset: Duration::zero(),
+                idle_state: DebouncedIdleState::Active { active_since: now },
+            },
+            PresenceMode::Active,
+        );
+        let prebreak = make_idle_info(
+            ModeState::PreBreak { started_at: now },
+            PresenceMode::Active,
+        );
+        let on_break = make_idle_info(
+            ModeState::Break {
+                progress_towards_finish: Duration::zero(),
+                idle_state: DebouncedIdleState::Idle { idle_since: now },
+            },
+            PresenceMode::Active,
+        );
+
+        assert_eq!(
+            state_changes(&normal, &prebreak),
+            vec![BreakEventKind::PreBreak]
+        );
+        assert_eq!(state_changes(&prebreak, &prebreak), Vec::new());
+        assert_eq!(
+            state_changes(&prebreak, &on_break),
+            vec![BreakEventKind::BreakStarted]
+        );
+        assert_eq!(state_changes(&on_break, &on_break), Vec::new());
+        assert_eq!(state_changes(&on_break, &normal), Vec::new());
+    }
+
+    #[test]
+    fn reports_presence_changes() {
+        let now = Utc::now();
+        let normal = |presence_mode| {
+            make_idle_info(
+                ModeState::Normal {
+                    progress_towards_break: Duration::zero(),
+                    progress_towards_reset: Duration::zero(),
+                    idle_state: DebouncedIdleState::Active { active_since: now },
+                },
+                presence_mode,
+            )
+        };
+        let snoozed = normal(PresenceMode::SnoozedUntil(now + Duration::hours(1)));
+
+        assert_eq!(
+            state_changes(&normal(PresenceMode::Active), &snoozed),
+            vec![BreakEventKind::PresenceChanged]
+        );
+        assert_eq!(
+            state_changes(
+                &snoozed,
+                &normal(PresenceMode::SnoozedUntil(now + Duration::hours(2)))
+            ),
+            vec![BreakEventKind::PresenceChanged]
+        );
+        assert_eq!(state_changes(&snoozed, &snoozed), Vec::new());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::backend::idle_monitoring::{IdleInfo, ModeState, PresenceMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakWarning {
+    pub secs_until_break: i64,
+}
+
+// Watches the countdown to the next break and warns as it passes each configured lead time.
+#[derive(Debug, Default)]
+pub struct BreakWarner {
+    last_secs_until_break: Option<i64>,
+}
+
+impl BreakWarner {
+    pub fn check(&mut self, idle_info: &IdleInfo, warning_secs: &[i64]) -> Option<BreakWarning> {
+        let (secs_until_break, user_is_active) = match idle_info.last_mode_state {
+            ModeState::Normal {
+                progress_towards_break,
+                idle_state,
+                ..
+            } if idle_info.presence_mode == PresenceMode::Active => (
+                idle_info.time_to_break_secs - progress_towards_break.num_seconds(),
+                idle_state.is_user_active(),
+            ),
+            _ => {
+                self.last_secs_until_break = None;
+                return None;
+            }
+        };
+        // Nothing is crossed on the first look, so starting up close to a break stays quiet.
+        let last_secs_until_break = self.last_secs_until_break.replace(secs_until_break)?;
+        // Someone who has walked away won't see the warning, and may not need the break.
+        if !user_is_active {
+            return None;
+        }
+        warning_secs
+            .iter()
+            .filter(|&&warning_secs| {
+                last_secs_until_break > warning_secs && secs_until_break <= warning_secs
+            })
+            .min()
+            .map(|&warning_secs| BreakWarning {
+                secs_until_break: warning_secs,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::backend::idle_monitoring::DebouncedIdleState;
+
+    fn make_idle_info(secs_until_break: i64, user_is_active: bool) -> IdleInfo {
+        let now = Utc::now();
+        IdleInfo {
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(1200 - secs_until_break),
+                progress_towards_reset: Duration::zero(),
+                idle_state: match user_is_active {
+                    true => DebouncedIdleState::Active { active_since: now },
+                    false => DebouncedIdleState::Idle { idle_since: now },
+                },
+            },
+            ..IdleInfo::for_tests(now)
+        }
+    }
+
+    #[test]
+    fn warns_once_at_each_lead_time() {
+        let mut warner = BreakWarner::default();
+        let warnings: Vec<Option<i64>> = [302, 301, 300, 299, 61, 60, 59, 0]
+            .into_iter()
+            .map(|secs_until_break| {
+                warner
+                    .check(&make_idle_info(secs_until_break, true), &[300, 60])
+                    .map(|warning| warning.secs_until_break)
+            })
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![None, None, Some(300), None, None, Some(60), None, None]
+        );
+    }
+
+    #[test]
+    fn idle_user_is_not_warned() {
+        let mut warner = BreakWarner::default();
+        assert_eq!(warner.check(&make_idle_info(301, true), &[300]), None);
+        assert_eq!(warner.check(&make_idle_info(300, false), &[300]), None);
+        assert_eq!(warner.check(&make_idle_info(299, true), &[300]), None);
+    }
+
+    #[test]
+    fn postponing_warns_again() {
+        let mut warner = BreakWarner::default();
+        warner.check(&make_idle_info(61, true), &[60]);
+        assert!(warner.check(&make_idle_info(60, true), &[60]).is_some());
+        warner.check(&make_idle_info(300, true), &[60]);
+        assert!(warner.check(&make_idle_info(59, true), &[60]).is_some());
+    }
+
+    #[test]
+    fn muted_user_is_not_warned() {
+        let mut warner = BreakWarner::default();
+        let mut idle_info = make_idle_info(61, true);
+        idle_info.presence_mode = PresenceMode::Muted;
+        warner.check(&idle_info, &[60]);
+        idle_info = make_idle_info(60, true);
+        idle_info.presence_mode = PresenceMode::Muted;
+        assert_eq!(warner.check(&idle_info, &[60]), None);
+    }
+}
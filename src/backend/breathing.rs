@@ -0,0 +1,206 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::backend::idle_monitoring::{DebouncedIdleState, IdleInfo, ModeState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreathingPhase {
+    BreatheIn,
+    HoldIn,
+    BreatheOut,
+    HoldOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreathingPattern {
+    // Each phase with its length in seconds, for one breath
+    pub phases: &'static [(BreathingPhase, i64)],
+}
+
+pub const BOX_BREATHING: BreathingPattern = BreathingPattern {
+    phases: &[
+        (BreathingPhase::BreatheIn, 4),
+        (BreathingPhase::HoldIn, 4),
+        (BreathingPhase::BreatheOut, 4),
+        (BreathingPhase::HoldOut, 4),
+    ],
+};
+
+pub const FOUR_SEVEN_EIGHT_BREATHING: BreathingPattern = BreathingPattern {
+    phases: &[
+        (BreathingPhase::BreatheIn, 4),
+        (BreathingPhase::HoldIn, 7),
+        (BreathingPhase::BreatheOut, 8),
+    ],
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacerState {
+    pub phase: BreathingPhase,
+    // How full the lungs are, from 0 when empty to 1 when full
+    pub fullness: f64,
+}
+
+impl BreathingPattern {
+    fn breath_length_ms(&self) -> i64 {
+        self.phases.iter().map(|(_, secs)| secs * 1000).sum()
+    }
+
+    // Breaths are stretched or squeezed a little, so the last one ends exactly with the break.
+    pub fn pacer_state(&self, elapsed: Duration, break_length: Duration) -> PacerState {
+        let breath_length_ms = self.breath_length_ms();
+        let break_length_ms = break_length.num_milliseconds().max(1);
+        let breath_count = (break_length_ms as f64 / breath_length_ms as f64)
+            .round()
+            .max(1.0);
+        let scaled_elapsed_ms = elapsed.num_milliseconds().clamp(0, break_length_ms) as f64
+            * (breath_length_ms as f64 * breath_count)
+            / break_length_ms as f64;
+
+        // Once the break is over the lungs are empty, rather than starting a new breath
+        let mut position_ms = match scaled_elapsed_ms >= breath_length_ms as f64 * breath_count {
+            true => breath_length_ms as f64,
+            false => scaled_elapsed_ms % breath_length_ms as f64,
+        };
+        for (position, &(phase, secs)) in self.phases.iter().enumerate() {
+            let phase_length_ms = (secs * 1000) as f64;
+            if position_ms < phase_length_ms || position == self.phases.len() - 1 {
+                let progress = (position_ms / phase_length_ms).min(1.0);
+                let fullness = match phase {
+                    BreathingPhase::BreatheIn => progress,
+                    BreathingPhase::HoldIn => 1.0,
+                    BreathingPhase::BreatheOut => 1.0 - progress,
+                    BreathingPhase::HoldOut => 0.0,
+                };
+                return PacerState { phase, fullness };
+            }
+            position_ms -= phase_length_ms;
+        }
+        unreachable!("breathing patterns have at least one phase")
+    }
+}
+
+// Break progress only advances while the user is away. Between refreshes it is extrapolated,
+// but only while the user is settled into being idle, so the pacer pauses as soon as they move.
+pub fn pacer_elapsed(idle_info: &IdleInfo, now: DateTime<Utc>) -> Duration {
+    match idle_info.last_mode_state {
+        ModeState::Break {
+            progress_towards_finish,
+            idle_state: DebouncedIdleState::Idle { .. },
+        } => (progress_towards_finish + (now - idle_info.last_checked).max(Duration::zero()))
+            .min(Duration::seconds(idle_info.break_length_secs)),
+        ModeState::Break {
+            progress_towards_finish,
+            ..
+        } => progress_towards_finish,
+        _ => Duration::zero(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn assert_pacer_state(
+        pattern: &BreathingPattern,
+        elapsed_ms: i64,
+        break_length_secs: i64,
+        expected_phase: BreathingPhase,
+        expected_fullness: f64,
+    ) {
+        let state = pattern.pacer_state(
+            Duration::milliseconds(elapsed_ms),
+            Duration::seconds(break_length_secs),
+        );
+        assert_eq!(state.phase, expected_phase, "at {} ms", elapsed_ms);
+        assert!(
+            (state.fullness - expected_fullness).abs() < 0.001,
+            "at {} ms: {} instead of {}",
+            elapsed_ms,
+            state.fullness,
+            expected_fullness
+        );
+    }
+
+    #[test]
+    fn box_breathing_goes_through_every_phase() {
+        assert_pacer_state(&BOX_BREATHING, 0, 64, BreathingPhase::BreatheIn, 0.0);
+        assert_pacer_state(&BOX_BREATHING, 2000, 64, BreathingPhase::BreatheIn, 0.5);
+        assert_pacer_state(&BOX_BREATHING, 5000, 64, BreathingPhase::HoldIn, 1.0);
+        assert_pacer_state(&BOX_BREATHING, 9000, 64, BreathingPhase::BreatheOut, 0.75);
+        assert_pacer_state(&BOX_BREATHING, 13000, 64, BreathingPhase::HoldOut, 0.0);
+        assert_pacer_state(&BOX_BREATHING, 17000, 64, BreathingPhase::BreatheIn, 0.25);
+    }
+
+    #[test]
+    fn breaths_are_stretched_to_fill_the_break() {
+        // Three breaths of 19 seconds, stretched to a minute
+        assert_pacer_state(
+            &FOUR_SEVEN_EIGHT_BREATHING,
+            20_000,
+            60,
+            BreathingPhase::BreatheIn,
+            0.0,
+        );
+        assert_pacer_state(
+            &FOUR_SEVEN_EIGHT_BREATHING,
+            59_999,
+            60,
+            BreathingPhase::BreatheOut,
+            0.0,
+        );
+        assert_pacer_state(
+            &FOUR_SEVEN_EIGHT_BREATHING,
+            60_000,
+            60,
+            BreathingPhase::BreatheOut,
+            0.0,
+        );
+        // Short breaks still get one breath
+        assert_pacer_state(&BOX_BREATHING, 5000, 10, BreathingPhase::BreatheOut, 1.0);
+    }
+
+    #[test]
+    fn pacer_pauses_while_user_is_active() {
+        let last_checked = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        let make_idle_info = |idle_state| IdleInfo {
+            last_mode_state: ModeState::Break {
+                progress_towards_finish: Duration::seconds(10),
+                idle_state,
+            },
+            break_length_secs: 11,
+            ..IdleInfo::for_tests(last_checked)
+        };
+        let now = last_checked + Duration::milliseconds(500);
+
+        assert_eq!(
+            pacer_elapsed(
+                &make_idle_info(DebouncedIdleState::Idle {
+                    idle_since: last_checked
+                }),
+                now
+            ),
+            Duration::milliseconds(10_500)
+        );
+        assert_eq!(
+            pacer_elapsed(
+                &make_idle_info(DebouncedIdleState::Idle {
+                    idle_since: last_checked
+                }),
+                now + Duration::seconds(5)
+            ),
+            Duration::seconds(11)
+        );
+        assert_eq!(
+            pacer_elapsed(
+                &make_idle_info(DebouncedIdleState::IdleGoingToActive {
+                    idle_since: last_checked,
+                    transitioning_since: last_checked,
+                }),
+                now
+            ),
+            Duration::seconds(10)
+        );
+    }
+}
@@ -0,0 +1,598 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use dirs::home_dir;
+use rrule::{RRuleSet, Tz};
+
+// Occurrences of recurring meetings are only expanded this far ahead.
+const LOOKAHEAD_HOURS: i64 = 12;
+const MAX_OCCURRENCES_PER_EVENT: u16 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Meeting {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CalendarError {
+    path: PathBuf,
+    source: io::Error,
+}
+
+impl fmt::Display for CalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "could not read calendar {}: {}",
+            self.path.display(),
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for CalendarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+struct Event {
+    start: DateTime<Tz>,
+    duration: Duration,
+    recurrence: Option<RRuleSet>,
+}
+
+impl Event {
+    fn meetings_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Meeting> {
+        let starts = match self.recurrence {
+            Some(ref recurrence) => {
+                recurrence
+                    .clone()
+                    .after((from - self.duration).with_timezone(&Tz::UTC))
+                    .before(to.with_timezone(&Tz::UTC))
+                    .all(MAX_OCCURRENCES_PER_EVENT)
+                    .dates
+            }
+            None => vec![self.start],
+        };
+        starts
+            .into_iter()
+            .map(|start| start.with_timezone(&Utc))
+            .filter(|start| *start + self.duration > from && *start <= to)
+            .map(|start| Meeting {
+                start,
+                end: start + self.duration,
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Calendar {
+    events: Vec<Event>,
+}
+
+// Properties of a single VEVENT, before we know whether it's worth keeping.
+#[derive(Default)]
+struct RawEvent {
+    uid: Option<String>,
+    start: Option<DateTime<Tz>>,
+    end: Option<DateTime<Tz>>,
+    duration: Option<Duration>,
+    recurrence_id: Option<DateTime<Tz>>,
+    rules: Vec<String>,
+    extra_dates: Vec<DateTime<Tz>>,
+    excluded_dates: Vec<DateTime<Tz>>,
+    all_day: bool,
+    skipped: bool,
+}
+
+impl Calendar {
+    // Deliberately forgiving: events we can't make sense of are left out rather than failing
+    // the whole file, because calendar exports are rarely spotless.
+    pub fn parse(contents: &str) -> Self {
+        let mut raw_events = Vec::new();
+        let mut current_event: Option<RawEvent> = None;
+        // Alarms nested inside an event have properties of their own.
+        let mut nesting_depth = 0;
+
+        for line in unfold_lines(contents) {
+            let Some((name, parameters, value)) = split_content_line(&line) else {
+                continue;
+            };
+            match (name.as_str(), value) {
+                ("BEGIN", "VEVENT") if current_event.is_none() => {
+                    current_event = Some(RawEvent::default());
+                    nesting_depth = 0;
+                }
+                ("END", "VEVENT") if nesting_depth == 0 => {
+                    raw_events.extend(current_event.take());
+                }
+                ("BEGIN", _) if current_event.is_some() => nesting_depth += 1,
+                ("END", _) if current_event.is_some() => nesting_depth -= 1,
+                _ => {}
+            }
+            let Some(ref mut event) = current_event else {
+                continue;
+            };
+            if nesting_depth != 0 {
+                continue;
+            }
+
+            match name.as_str() {
+                "UID" => event.uid = Some(value.to_string()),
+                "DTSTART" => {
+                    event.all_day = is_date_only(parameters, value);
+                    event.start = parse_date_time(parameters, value);
+                }
+                "DTEND" => event.end = parse_date_time(parameters, value),
+                "DURATION" => event.duration = parse_duration(value),
+                "RECURRENCE-ID" => event.recurrence_id = parse_date_time(parameters, value),
+                "RRULE" => event.rules.push(line.clone()),
+                "RDATE" => event
+                    .extra_dates
+                    .extend(parse_date_time_list(parameters, value)),
+                "EXDATE" => event
+                    .excluded_dates
+                    .extend(parse_date_time_list(parameters, value)),
+                "STATUS" if value.eq_ignore_ascii_case("CANCELLED") => event.skipped = true,
+                "TRANSP" if value.eq_ignore_ascii_case("TRANSPARENT") => event.skipped = true,
+                _ => {}
+            }
+        }
+
+        // Moved or cancelled instances of a recurring meeting are separate events that
+        // replace the original occurrence.
+        let mut overridden_occurrences: HashMap<String, Vec<DateTime<Tz>>> = HashMap::new();
+        for raw_event in &raw_events {
+            if let (Some(uid), Some(recurrence_id)) = (&raw_event.uid, raw_event.recurrence_id) {
+                overridden_occurrences
+                    .entry(uid.clone())
+                    .or_default()
+                    .push(recurrence_id);
+            }
+        }
+
+        let events = raw_events
+            .into_iter()
+            .filter(|raw_event| !raw_event.skipped && !raw_event.all_day)
+            .filter_map(|raw_event| {
+                let start = raw_event.start?;
+                let duration = match (raw_event.end, raw_event.duration) {
+                    (Some(end), _) => end.signed_duration_since(start),
+                    (None, Some(duration)) => duration,
+                    (None, None) => return None,
+                };
+                if duration <= Duration::zero() {
+                    return None;
+                }
+                let mut raw_event = raw_event;
+                if raw_event.recurrence_id.is_none()
+                    && let Some(overrides) = raw_event
+                        .uid
+                        .as_ref()
+                        .and_then(|uid| overridden_occurrences.get(uid))
+                {
+                    raw_event.excluded_dates.extend(overrides);
+                }
+                Some(Event {
+                    start,
+                    duration,
+                    recurrence: make_recurrence(start, &raw_event),
+                })
+            })
+            .collect();
+        Calendar { events }
+    }
+
+    pub fn load_from_path(path: &Path) -> Result<Self, CalendarError> {
+        fs::read_to_string(path)
+            .map(|contents| Self::parse(&contents))
+            .map_err(|source| CalendarError {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    pub fn meetings_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Meeting> {
+        let mut meetings: Vec<Meeting> = self
+            .events
+            .iter()
+            .flat_map(|event| event.meetings_between(from, to))
+            .collect();
+        meetings.sort_by_key(|meeting| meeting.start);
+        meetings
+    }
+}
+
+fn make_recurrence(start: DateTime<Tz>, raw_event: &RawEvent) -> Option<RRuleSet> {
+    if raw_event.rules.is_empty() && raw_event.extra_dates.is_empty() {
+        return None;
+    }
+    let mut recurrence = RRuleSet::new(start);
+    for rule in &raw_event.rules {
+        // A rule we can't parse shouldn't take the rest of the event down with it.
+        match recurrence.clone().set_from_string(rule) {
+            Ok(updated_recurrence) => recurrence = updated_recurrence,
+            Err(error) => println!("Ignoring calendar rule {:?}: {}", rule, error),
+        }
+    }
+    if recurrence.get_rrule().is_empty() {
+        // Without a rule, only the explicitly listed dates would be returned.
+        recurrence = recurrence.rdate(start);
+    }
+    for extra_date in &raw_event.extra_dates {
+        recurrence = recurrence.rdate(*extra_date);
+    }
+    for excluded_date in &raw_event.excluded_dates {
+        recurrence = recurrence.exdate(*excluded_date);
+    }
+    Some(recurrence)
+}
+
+fn unfold_lines(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(previous)) => previous.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+// Splits `NAME;PARAM=x:VALUE` into its parts. Parameter values may be quoted and contain colons.
+fn split_content_line(line: &str) -> Option<(String, &str, &str)> {
+    let mut in_quotes = false;
+    let separator = line.char_indices().find_map(|(index, character)| {
+        match character {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => return Some(index),
+            _ => {}
+        }
+        None
+    })?;
+    let (head, value) = (&line[..separator], &line[separator + 1..]);
+    let (name, parameters) = head.split_once(';').unwrap_or((head, ""));
+    Some((name.to_ascii_uppercase(), parameters, value))
+}
+
+fn get_parameter<'a>(parameters: &'a str, name: &str) -> Option<&'a str> {
+    parameters.split(';').find_map(|parameter| {
+        let (key, value) = parameter.split_once('=')?;
+        key.eq_ignore_ascii_case(name)
+            .then(|| value.trim_matches('"'))
+    })
+}
+
+fn is_date_only(parameters: &str, value: &str) -> bool {
+    get_parameter(parameters, "VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("DATE"))
+        || !value.contains('T')
+}
+
+fn parse_date_time(parameters: &str, value: &str) -> Option<DateTime<Tz>> {
+    if let Some(utc_value) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc_value, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&naive).with_timezone(&Tz::UTC));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    // Floating times and time zones we don't recognise, such as the Windows names used by
+    // some Outlook exports, are interpreted as local time.
+    let timezone = get_parameter(parameters, "TZID")
+        .and_then(|name| name.parse::<chrono_tz::Tz>().ok())
+        .map_or(Tz::LOCAL, Tz::from);
+    timezone.from_local_datetime(&naive).earliest()
+}
+
+fn parse_date_time_list(parameters: &str, value: &str) -> Vec<DateTime<Tz>> {
+    value
+        .split(',')
+        .filter_map(|value| parse_date_time(parameters, value))
+        .collect()
+}
+
+// Parses RFC 5545 durations such as `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut remainder = value.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut in_time_part = false;
+    while !remainder.is_empty() {
+        if let Some(rest) = remainder.strip_prefix('T') {
+            in_time_part = true;
+            remainder = rest;
+            continue;
+        }
+        let digits_end = remainder.find(|character: char| !character.is_ascii_digit())?;
+        let (digits, rest) = remainder.split_at(digits_end);
+        let amount: i64 = digits.parse().ok()?;
+        let mut rest = rest.chars();
+        // Out-of-range amounts come out as None instead of panicking.
+        let part = match (rest.next()?, in_time_part) {
+            ('W', false) => Duration::try_weeks(amount),
+            ('D', false) => Duration::try_days(amount),
+            ('H', true) => Duration::try_hours(amount),
+            ('M', true) => Duration::try_minutes(amount),
+            ('S', true) => Duration::try_seconds(amount),
+            _ => None,
+        }?;
+        total = total.checked_add(&part)?;
+        remainder = rest.as_str();
+    }
+    Some(total * sign)
+}
+
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(relative_path), Some(home)) => home.join(relative_path),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn get_modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn load_or_log(path: &Path) -> Calendar {
+    Calendar::load_from_path(path).unwrap_or_else(|error| {
+        println!("{}", error);
+        Calendar::default()
+    })
+}
+
+// Keeps the configured calendars loaded and answers whether a meeting is going on. Files are
+// re-read when they change, since sync tools rewrite them in the background.
+pub struct CalendarWatcher {
+    paths: Vec<PathBuf>,
+    last_modified: Vec<Option<SystemTime>>,
+    calendars: Vec<Calendar>,
+    upcoming_meetings: Vec<Meeting>,
+    expanded_until: Option<DateTime<Utc>>,
+}
+
+impl CalendarWatcher {
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let paths: Vec<PathBuf> = paths.iter().map(|path| expand_home(path)).collect();
+        CalendarWatcher {
+            last_modified: paths.iter().map(|path| get_modified_time(path)).collect(),
+            calendars: paths.iter().map(|path| load_or_log(path)).collect(),
+            paths,
+            upcoming_meetings: Vec::new(),
+            expanded_until: None,
+        }
+    }
+
+    fn reload_changed_calendars(&mut self) {
+        for (index, path) in self.paths.iter().enumerate() {
+            let modified = get_modified_time(path);
+            if modified != self.last_modified[index] {
+                self.last_modified[index] = modified;
+                self.calendars[index] = load_or_log(path);
+                self.expanded_until = None;
+            }
+        }
+    }
+
+    // Back-to-back and overlapping meetings are merged, so the snooze lasts until the
+    // last one ends.
+    pub fn current_meeting(&mut self, now: DateTime<Utc>) -> Option<Meeting> {
+        self.reload_changed_calendars();
+        let lookahead = Duration::hours(LOOKAHEAD_HOURS);
+        if self
+            .expanded_until
+            .is_none_or(|expanded_until| now + lookahead / 2 > expanded_until)
+        {
+            self.upcoming_meetings = self
+                .calendars
+                .iter()
+                .flat_map(|calendar| calendar.meetings_between(now, now + lookahead))
+                .collect();
+            self.upcoming_meetings.sort_by_key(|meeting| meeting.start);
+            self.expanded_until = Some(now + lookahead);
+        }
+
+        let mut current_meeting: Option<Meeting> = None;
+        for meeting in &self.upcoming_meetings {
+            match current_meeting {
+                None if meeting.start <= now && now < meeting.end => {
+                    current_meeting = Some(*meeting);
+                }
+                Some(ref mut current) if meeting.start <= current.end => {
+                    current.end = current.end.max(meeting.end);
+                }
+                _ => {}
+            }
+        }
+        current_meeting
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().to_utc()
+    }
+
+    fn wrap_events(events: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}END:VCALENDAR\r\n",
+            events.replace('\n', "\r\n")
+        )
+    }
+
+    #[test]
+    fn parses_single_events() {
+        let calendar = Calendar::parse(&wrap_events(
+            "BEGIN:VEVENT\n\
+             UID:standup\n\
+             DTSTART:20250203T090000Z\n\
+             DTEND:20250203T091500Z\n\
+             BEGIN:VALARM\n\
+             TRIGGER:-PT10M\n\
+             DTSTART:20250101T000000Z\n\
+             END:VALARM\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             UID:review\n\
+             DTSTART;TZID=Europe/Brussels:20250203T140000\n\
+             DURATION:PT1H30M\n\
+             END:VEVENT\n",
+        ));
+
+        assert_eq!(
+            calendar.meetings_between(utc("2025-02-03T00:00:00Z"), utc("2025-02-04T00:00:00Z")),
+            vec![
+                Meeting {
+                    start: utc("2025-02-03T09:00:00Z"),
+                    end: utc("2025-02-03T09:15:00Z"),
+                },
+                Meeting {
+                    start: utc("2025-02-03T13:00:00Z"),
+                    end: utc("2025-02-03T14:30:00Z"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_events_that_are_not_meetings() {
+        let calendar = Calendar::parse(&wrap_events(
+            "BEGIN:VEVENT\n\
+             DTSTART;VALUE=DATE:20250203\n\
+             DTEND;VALUE=DATE:20250204\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             DTSTART:20250203T090000Z\n\
+             DTEND:20250203T100000Z\n\
+             STATUS:CANCELLED\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             DTSTART:20250203T110000Z\n\
+             DTEND:20250203T120000Z\n\
+             TRANSP:TRANSPARENT\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             DTSTART:not a date\n\
+             DTEND:20250203T120000Z\n\
+             END:VEVENT\n",
+        ));
+
+        assert!(
+            calendar
+                .meetings_between(utc("2025-02-01T00:00:00Z"), utc("2025-02-05T00:00:00Z"))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn expands_recurring_meetings() {
+        let calendar = Calendar::parse(&wrap_events(
+            "BEGIN:VEVENT\n\
+             UID:weekly\n\
+             DTSTART:20250106T100000Z\n\
+             DTEND:20250106T103000Z\n\
+             RRULE:FREQ=WEEKLY;BYDAY=MO,TH\n\
+             EXDATE:20250113T100000Z\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             UID:weekly\n\
+             RECURRENCE-ID:20250116T100000Z\n\
+             DTSTART:20250116T150000Z\n\
+             DTEND:20250116T153000Z\n\
+             END:VEVENT\n",
+        ));
+
+        let starts: Vec<DateTime<Utc>> = calendar
+            .meetings_between(utc("2025-01-10T00:00:00Z"), utc("2025-01-21T00:00:00Z"))
+            .into_iter()
+            .map(|meeting| meeting.start)
+            .collect();
+        assert_eq!(
+            starts,
+            vec![utc("2025-01-16T15:00:00Z"), utc("2025-01-20T10:00:00Z"),]
+        );
+    }
+
+    #[test]
+    fn unfolds_long_lines() {
+        let calendar = Calendar::parse(&wrap_events(
+            "BEGIN:VEVENT\n\
+             DTSTART;TZID=\"Europe/\n Brussels\":20250203T140000\n\
+             DTEND;TZID=\"Europe/Brussels\":20250203T150000\n\
+             END:VEVENT\n",
+        ));
+
+        assert_eq!(
+            calendar.meetings_between(utc("2025-02-03T00:00:00Z"), utc("2025-02-04T00:00:00Z")),
+            vec![Meeting {
+                start: utc("2025-02-03T13:00:00Z"),
+                end: utc("2025-02-03T14:00:00Z"),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1DT2S"), Some(Duration::seconds(86_402)));
+        assert_eq!(parse_duration("-P1W"), Some(Duration::weeks(-1)));
+        assert_eq!(parse_duration("PT5X"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_durations_without_panicking() {
+        assert_eq!(parse_duration("PT5é"), None);
+        assert_eq!(parse_duration("P99999999999999W"), None);
+        assert_eq!(parse_duration("P100000000000DT200000000000H"), None);
+    }
+
+    #[test]
+    fn watcher_merges_back_to_back_meetings() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "stretch-break-calendar-test-{}-{}.ics",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::write(
+            &path,
+            wrap_events(
+                "BEGIN:VEVENT\n\
+                 DTSTART:20250203T090000Z\n\
+                 DTEND:20250203T100000Z\n\
+                 END:VEVENT\n\
+                 BEGIN:VEVENT\n\
+                 DTSTART:20250203T100000Z\n\
+                 DTEND:20250203T103000Z\n\
+                 END:VEVENT\n",
+            ),
+        )
+        .unwrap();
+        let mut watcher = CalendarWatcher::new(&[path]);
+
+        assert_eq!(watcher.current_meeting(utc("2025-02-03T08:59:59Z")), None);
+        assert_eq!(
+            watcher.current_meeting(utc("2025-02-03T09:30:00Z")),
+            Some(Meeting {
+                start: utc("2025-02-03T09:00:00Z"),
+                end: utc("2025-02-03T10:30:00Z"),
+            })
+        );
+        assert_eq!(watcher.current_meeting(utc("2025-02-03T10:30:00Z")), None);
+    }
+}
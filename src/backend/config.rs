@@ -0,0 +1,838 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::Duration;
+use dirs::config_dir;
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+use crate::backend::breathing::{BOX_BREATHING, BreathingPattern, FOUR_SEVEN_EIGHT_BREATHING};
+use crate::backend::exercises::ExerciseCategory;
+use crate::backend::file_io::write_atomically;
+use crate::backend::idle_monitoring::{
+    DEFAULT_BREAK_LENGTH_SECS, DEFAULT_TIME_TO_BREAK_SECS, REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+};
+use crate::backend::sounds::SoundEvent;
+
+const CONFIG_HEADER: &str = "\
+# Stretch Break settings. This file may be edited by hand while the app is running;
+# changes are picked up automatically.
+";
+
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+// Typed to leave strict mode, so turning it off takes a deliberate decision.
+pub const STRICT_MODE_EXIT_PHRASE: &str = "I will take my breaks later";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    NoConfigDir,
+    NotFound(PathBuf),
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::NoConfigDir => write!(f, "could not determine the config directory"),
+            ConfigError::NotFound(path) => write!(f, "{} does not exist", path.display()),
+            ConfigError::Io { path, source } => {
+                write!(f, "could not access {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { path, source } => {
+                write!(
+                    f,
+                    "{} is not a valid config file: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ConfigError::Serialize(source) => write!(f, "could not serialize config: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::Serialize(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl ConfigError {
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        match source.kind() {
+            io::ErrorKind::NotFound => ConfigError::NotFound(path.to_path_buf()),
+            _ => ConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationStyle {
+    Urgent,
+    Normal,
+    Silent,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakStyle {
+    #[default]
+    Window,
+    // Dims every monitor. Only the primary one offers postponing and skipping.
+    Fullscreen,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakContent {
+    #[default]
+    Exercise,
+    BoxBreathing,
+    FourSevenEightBreathing,
+}
+
+impl BreakContent {
+    pub fn breathing_pattern(&self) -> Option<BreathingPattern> {
+        match self {
+            BreakContent::Exercise => None,
+            BreakContent::BoxBreathing => Some(BOX_BREATHING),
+            BreakContent::FourSevenEightBreathing => Some(FOUR_SEVEN_EIGHT_BREATHING),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub time_to_break_secs: i64,
+    pub break_length_secs: i64,
+    pub reading_mode: bool,
+    pub prebreak_idle_secs: u64,
+    pub notification_style: NotificationStyle,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            reading_mode: false,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            notification_style: NotificationStyle::Urgent,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrictMode {
+    pub enabled: bool,
+    pub max_postpones: u32,
+}
+
+impl Default for StrictMode {
+    fn default() -> Self {
+        StrictMode {
+            enabled: false,
+            max_postpones: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sounds {
+    pub events: Vec<SoundEvent>,
+    pub volume_percent: u32,
+}
+
+// Only the break end makes a sound out of the box, as it did before the other cues existed.
+impl Default for Sounds {
+    fn default() -> Self {
+        Sounds {
+            events: vec![SoundEvent::BreakEnd],
+            volume_percent: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    // Shell commands, run next to any executables in the hooks folder
+    pub commands: Vec<String>,
+    // Hooks still running after this long are stopped
+    pub timeout_secs: u64,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Hooks {
+            commands: Vec::new(),
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mqtt {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Stretch Break's own topics start with this. Give each computer its own.
+    pub topic_prefix: String,
+    pub discovery_prefix: String,
+}
+
+impl Default for Mqtt {
+    fn default() -> Self {
+        Mqtt {
+            enabled: false,
+            host: String::from("localhost"),
+            port: 1883,
+            username: None,
+            password: None,
+            topic_prefix: String::from("stretch-break"),
+            discovery_prefix: String::from("homeassistant"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Http {
+    pub enabled: bool,
+    // Only ever bound to localhost
+    pub port: u16,
+    // Listens here instead of on the port when set
+    pub unix_socket: Option<PathBuf>,
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Http {
+            enabled: false,
+            port: 7207,
+            unix_socket: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metrics {
+    // For node_exporter's textfile collector. Metrics are also served at /metrics when the
+    // HTTP endpoint is on.
+    pub textfile: Option<PathBuf>,
+    pub interval_secs: u64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics {
+            textfile: None,
+            interval_secs: 15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityWatch {
+    // Sends heartbeats, so breaks show up in the timeline
+    pub enabled: bool,
+    pub url: String,
+    // Takes idle time from aw-watcher-afk instead of the session
+    pub use_afk_watcher: bool,
+}
+
+impl Default for ActivityWatch {
+    fn default() -> Self {
+        ActivityWatch {
+            enabled: false,
+            url: String::from("http://localhost:5600"),
+            use_afk_watcher: false,
+        }
+    }
+}
+
+impl StrictMode {
+    // Each postpone is at most half as long as the one before. None once all postpones are
+    // used up.
+    pub fn allowed_postpone(
+        &self,
+        requested: Duration,
+        postpone_count: u32,
+        last_postpone: Option<Duration>,
+    ) -> Option<Duration> {
+        if !self.enabled {
+            return Some(requested);
+        }
+        (postpone_count < self.max_postpones).then(|| match last_postpone {
+            Some(last_postpone) => requested.min(last_postpone / 2),
+            None => requested,
+        })
+    }
+
+    pub fn postpones_left(&self, postpone_count: u32) -> Option<u32> {
+        self.enabled
+            .then(|| self.max_postpones.saturating_sub(postpone_count))
+    }
+}
+
+pub(crate) fn get_config_folder() -> Result<PathBuf, ConfigError> {
+    let folder = config_dir()
+        .ok_or(ConfigError::NoConfigDir)?
+        .join("stretch-break");
+    fs::create_dir_all(&folder).map_err(|error| ConfigError::io(&folder, error))?;
+    Ok(folder)
+}
+
+pub fn is_strict_mode_exit_phrase(text: &str) -> bool {
+    text.trim().eq_ignore_ascii_case(STRICT_MODE_EXIT_PHRASE)
+}
+
+fn default_postpone_minutes() -> Vec<i64> {
+    vec![1, 5, 15]
+}
+
+fn default_break_warning_minutes() -> Vec<i64> {
+    vec![5, 1]
+}
+
+// Accepts a list like "1, 5, 15". None when anything other than positive numbers was typed.
+pub fn parse_minutes_list(text: &str) -> Option<Vec<i64>> {
+    let mut minutes_list = text
+        .split([',', ' '])
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok().filter(|minutes| *minutes > 0))
+        .collect::<Option<Vec<i64>>>()?;
+    minutes_list.sort_unstable();
+    minutes_list.dedup();
+    Some(minutes_list)
+}
+
+// Hand-edited lists get the same check as typed ones.
+fn deserialize_minutes_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<i64>, D::Error> {
+    let minutes_list = Vec::<i64>::deserialize(deserializer)?;
+    if minutes_list.iter().any(|minutes| *minutes <= 0) {
+        return Err(de::Error::custom("minutes must be more than zero"));
+    }
+    Ok(minutes_list)
+}
+
+pub fn format_minutes_list(minutes_list: &[i64]) -> String {
+    minutes_list
+        .iter()
+        .map(i64::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub active_profile: String,
+    // Paths to .ics files. Breaks are snoozed while a meeting in any of them is going on.
+    #[serde(default)]
+    pub calendars: Vec<PathBuf>,
+    #[serde(default)]
+    pub break_style: BreakStyle,
+    #[serde(default)]
+    pub break_content: BreakContent,
+    #[serde(default)]
+    pub pause_media_during_breaks: bool,
+    #[serde(default)]
+    pub lock_screen_during_breaks: bool,
+    // Offered when postponing a break. The first one is the default.
+    #[serde(
+        default = "default_postpone_minutes",
+        deserialize_with = "deserialize_minutes_list"
+    )]
+    pub postpone_minutes: Vec<i64>,
+    // Normal notifications this many minutes before a break comes due
+    #[serde(default = "default_break_warning_minutes")]
+    pub break_warning_minutes: Vec<i64>,
+    // Idle time counts as activity while an app such as a video player inhibits idling.
+    #[serde(default = "default_respect_idle_inhibitors")]
+    pub respect_idle_inhibitors: bool,
+    #[serde(default)]
+    pub strict_mode: StrictMode,
+    #[serde(default)]
+    pub sounds: Sounds,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub mqtt: Mqtt,
+    #[serde(default)]
+    pub http: Http,
+    #[serde(default)]
+    pub metrics: Metrics,
+    #[serde(default)]
+    pub activitywatch: ActivityWatch,
+    // Break windows suggest exercises from these categories. Empty turns suggestions off.
+    #[serde(default = "default_exercise_categories")]
+    pub exercise_categories: Vec<ExerciseCategory>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+fn default_respect_idle_inhibitors() -> bool {
+    true
+}
+
+fn default_exercise_categories() -> Vec<ExerciseCategory> {
+    ExerciseCategory::ALL.to_vec()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let profiles = BTreeMap::from([
+            (String::from(DEFAULT_PROFILE_NAME), Profile::default()),
+            (
+                String::from("coding"),
+                Profile {
+                    time_to_break_secs: 50 * 60,
+                    break_length_secs: 5 * 60,
+                    reading_mode: true,
+                    notification_style: NotificationStyle::Normal,
+                    ..Profile::default()
+                },
+            ),
+            (
+                String::from("gaming"),
+                Profile {
+                    time_to_break_secs: 60 * 60,
+                    break_length_secs: 5 * 60,
+                    prebreak_idle_secs: 10,
+                    notification_style: NotificationStyle::Normal,
+                    ..Profile::default()
+                },
+            ),
+            (
+                String::from("presentation"),
+                Profile {
+                    time_to_break_secs: 90 * 60,
+                    break_length_secs: 2 * 60,
+                    reading_mode: true,
+                    notification_style: NotificationStyle::Silent,
+                    ..Profile::default()
+                },
+            ),
+        ]);
+        Config {
+            active_profile: String::from(DEFAULT_PROFILE_NAME),
+            calendars: Vec::new(),
+            break_style: BreakStyle::Window,
+            break_content: BreakContent::Exercise,
+            pause_media_during_breaks: false,
+            lock_screen_during_breaks: false,
+            postpone_minutes: default_postpone_minutes(),
+            break_warning_minutes: default_break_warning_minutes(),
+            respect_idle_inhibitors: true,
+            strict_mode: StrictMode::default(),
+            sounds: Sounds::default(),
+            hooks: Hooks::default(),
+            mqtt: Mqtt::default(),
+            http: Http::default(),
+            metrics: Metrics::default(),
+            activitywatch: ActivityWatch::default(),
+            exercise_categories: default_exercise_categories(),
+            profiles,
+        }
+    }
+}
+
+impl Config {
+    // Settings written before profiles existed become the default profile.
+    pub fn from_legacy_profile(profile: Profile) -> Self {
+        let mut config = Config::default();
+        config
+            .profiles
+            .insert(String::from(DEFAULT_PROFILE_NAME), profile);
+        config
+    }
+
+    // Falls back to the built-in defaults when the active profile was removed from the file.
+    pub fn active_profile(&self) -> Profile {
+        self.profiles
+            .get(&self.active_profile)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn active_profile_mut(&mut self) -> &mut Profile {
+        self.profiles
+            .entry(self.active_profile.clone())
+            .or_default()
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    // Names typed on the command line don't have to match the case used in the file.
+    pub fn find_profile_name(&self, name: &str) -> Option<&str> {
+        self.profiles
+            .keys()
+            .find(|profile_name| profile_name.eq_ignore_ascii_case(name))
+            .map(String::as_str)
+    }
+
+    pub fn get_config_filename() -> Result<PathBuf, ConfigError> {
+        Ok(get_config_folder()?.join("config.toml"))
+    }
+
+    pub fn load_from_disk() -> Result<Self, ConfigError> {
+        Self::load_from_path(&Self::get_config_filename()?)
+    }
+
+    pub fn save_to_disk(&self) -> Result<(), ConfigError> {
+        self.save_to_path(&Self::get_config_filename()?)
+    }
+
+    fn load_from_path(path: &Path) -> Result<Self, ConfigError> {
+        let raw_contents =
+            fs::read_to_string(path).map_err(|error| ConfigError::io(path, error))?;
+        let parse_error = |source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        };
+        let raw_config: toml::Table = toml::from_str(&raw_contents).map_err(parse_error)?;
+        if raw_config.contains_key("profiles") {
+            toml::Value::Table(raw_config)
+                .try_into()
+                .map_err(parse_error)
+        } else {
+            toml::Value::Table(raw_config)
+                .try_into()
+                .map(Config::from_legacy_profile)
+                .map_err(parse_error)
+        }
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> Result<(), ConfigError> {
+        let raw_contents = toml::to_string_pretty(self).map_err(ConfigError::Serialize)?;
+        write_atomically(
+            path,
+            format!("{}\n{}", CONFIG_HEADER, raw_contents).as_bytes(),
+        )
+        .map_err(|error| ConfigError::io(path, error))
+    }
+}
+
+// Polled from the idle monitoring loop. Cheaper and more portable than inotify for a file
+// that changes a handful of times per day.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let last_modified = Self::get_modified_time(&path);
+        ConfigWatcher {
+            path,
+            last_modified,
+        }
+    }
+
+    fn get_modified_time(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    // Returns the freshly loaded config when the file changed since the last call.
+    pub fn poll(&mut self) -> Option<Result<Config, ConfigError>> {
+        let modified = Self::get_modified_time(&self.path);
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+        modified.map(|_| Config::load_from_path(&self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    fn make_config_path() -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let folder = std::env::temp_dir().join(format!(
+            "stretch-break-config-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&folder).unwrap();
+        folder.join("config.toml")
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = make_config_path();
+        let mut config = Config {
+            active_profile: String::from("coding"),
+            calendars: vec![PathBuf::from("~/calendars/work.ics")],
+            break_style: BreakStyle::Fullscreen,
+            break_content: BreakContent::FourSevenEightBreathing,
+            pause_media_during_breaks: true,
+            lock_screen_during_breaks: true,
+            postpone_minutes: vec![2, 10],
+            break_warning_minutes: Vec::new(),
+            respect_idle_inhibitors: false,
+            strict_mode: StrictMode {
+                enabled: true,
+                max_postpones: 3,
+            },
+            sounds: Sounds {
+                events: vec![SoundEvent::PreBreak, SoundEvent::Halfway],
+                volume_percent: 60,
+            },
+            hooks: Hooks {
+                commands: vec![String::from("notify-send \"$STRETCH_BREAK_EVENT\"")],
+                timeout_secs: 5,
+            },
+            mqtt: Mqtt {
+                enabled: true,
+                host: String::from("broker.lan"),
+                username: Some(String::from("desk")),
+                topic_prefix: String::from("stretch-break/desk"),
+                ..Mqtt::default()
+            },
+            http: Http {
+                enabled: true,
+                port: 8000,
+                unix_socket: Some(PathBuf::from("/run/user/1000/stretch-break.sock")),
+            },
+            metrics: Metrics {
+                textfile: Some(PathBuf::from(
+                    "/var/lib/node_exporter/textfile_collector/stretch_break.prom",
+                )),
+                interval_secs: 60,
+            },
+            activitywatch: ActivityWatch {
+                enabled: true,
+                url: String::from("http://localhost:5666"),
+                use_afk_watcher: true,
+            },
+            exercise_categories: vec![ExerciseCategory::Eyes],
+            ..Config::default()
+        };
+        config.profiles.insert(
+            String::from("office"),
+            Profile {
+                time_to_break_secs: 1500,
+                break_length_secs: 120,
+                reading_mode: true,
+                prebreak_idle_secs: 3,
+                notification_style: NotificationStyle::Silent,
+            },
+        );
+        config.save_to_path(&path).unwrap();
+
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("# Stretch Break")
+        );
+        assert_eq!(Config::load_from_path(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_defaults() {
+        let path = make_config_path();
+        fs::write(
+            &path,
+            "active_profile = \"short\"\n[profiles.short]\nbreak_length_secs = 30\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert_eq!(
+            config.active_profile(),
+            Profile {
+                break_length_secs: 30,
+                ..Profile::default()
+            }
+        );
+        assert_eq!(config.profile_names(), vec![String::from("short")]);
+        assert!(config.respect_idle_inhibitors);
+    }
+
+    #[test]
+    fn rejects_postpones_that_are_not_positive() {
+        let path = make_config_path();
+        for minutes in ["[0]", "[5, -5]"] {
+            fs::write(
+                &path,
+                format!(
+                    "active_profile = \"default\"\npostpone_minutes = {}\n[profiles.default]\n",
+                    minutes
+                ),
+            )
+            .unwrap();
+            assert!(matches!(
+                Config::load_from_path(&path),
+                Err(ConfigError::Parse { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn flat_config_becomes_default_profile() {
+        let path = make_config_path();
+        fs::write(&path, "time_to_break_secs = 600\nreading_mode = true\n").unwrap();
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert_eq!(config.active_profile, DEFAULT_PROFILE_NAME);
+        assert_eq!(
+            config.active_profile(),
+            Profile {
+                time_to_break_secs: 600,
+                reading_mode: true,
+                ..Profile::default()
+            }
+        );
+        assert_eq!(
+            config.profiles.get("coding"),
+            Config::default().profiles.get("coding")
+        );
+    }
+
+    #[test]
+    fn finds_profiles_regardless_of_case() {
+        let config = Config::default();
+        assert_eq!(config.find_profile_name("Coding"), Some("coding"));
+        assert_eq!(config.find_profile_name("office"), None);
+    }
+
+    #[test]
+    fn strict_mode_shortens_and_limits_postpones() {
+        let strict_mode = StrictMode {
+            enabled: true,
+            max_postpones: 2,
+        };
+        let requested = chrono::Duration::minutes(4);
+        assert_eq!(
+            strict_mode.allowed_postpone(requested, 0, None),
+            Some(requested)
+        );
+        assert_eq!(
+            strict_mode.allowed_postpone(requested, 1, Some(requested)),
+            Some(chrono::Duration::minutes(2))
+        );
+        assert_eq!(
+            strict_mode.allowed_postpone(requested, 2, Some(chrono::Duration::minutes(2))),
+            None
+        );
+        assert_eq!(strict_mode.postpones_left(1), Some(1));
+
+        let relaxed_mode = StrictMode::default();
+        assert_eq!(
+            relaxed_mode.allowed_postpone(requested, 5, Some(chrono::Duration::minutes(1))),
+            Some(requested)
+        );
+        assert_eq!(relaxed_mode.postpones_left(5), None);
+    }
+
+    #[test]
+    fn strict_mode_postpones_never_grow() {
+        let strict_mode = StrictMode {
+            enabled: true,
+            max_postpones: 3,
+        };
+        let first = chrono::Duration::minutes(1);
+        assert_eq!(strict_mode.allowed_postpone(first, 0, None), Some(first));
+        assert_eq!(
+            strict_mode.allowed_postpone(chrono::Duration::minutes(15), 1, Some(first)),
+            Some(chrono::Duration::seconds(30))
+        );
+        assert_eq!(
+            strict_mode.allowed_postpone(
+                chrono::Duration::seconds(10),
+                2,
+                Some(chrono::Duration::seconds(30))
+            ),
+            Some(chrono::Duration::seconds(10))
+        );
+    }
+
+    #[test]
+    fn parses_minutes_list() {
+        assert_eq!(parse_minutes_list("15, 1,5 5"), Some(vec![1, 5, 15]));
+        assert_eq!(parse_minutes_list("1, soon"), None);
+        assert_eq!(parse_minutes_list("0"), None);
+        assert_eq!(parse_minutes_list(""), Some(Vec::new()));
+        assert_eq!(format_minutes_list(&[1, 5, 15]), "1, 5, 15");
+    }
+
+    #[test]
+    fn accepts_exit_phrase_regardless_of_case() {
+        assert!(is_strict_mode_exit_phrase(" i will take my breaks later "));
+        assert!(!is_strict_mode_exit_phrase("later"));
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let path = make_config_path();
+        fs::write(&path, "break_length_secs = \"long\"\n").unwrap();
+
+        assert!(matches!(
+            Config::load_from_path(&path),
+            Err(ConfigError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn watcher_reports_changes_once() {
+        let path = make_config_path();
+        Config::default().save_to_path(&path).unwrap();
+        let mut watcher = ConfigWatcher::new(path.clone());
+        assert!(watcher.poll().is_none());
+
+        // Make sure the modification time moves even on coarse-grained filesystems.
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(&path, "time_to_break_secs = 600\n").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+
+        assert_eq!(
+            watcher.poll().unwrap().unwrap().active_profile(),
+            Profile {
+                time_to_break_secs: 600,
+                ..Profile::default()
+            }
+        );
+        assert!(watcher.poll().is_none());
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::config::{ConfigError, get_config_folder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExerciseCategory {
+    Stretch,
+    Eyes,
+    Breathing,
+}
+
+impl ExerciseCategory {
+    pub const ALL: [ExerciseCategory; 3] = [
+        ExerciseCategory::Stretch,
+        ExerciseCategory::Eyes,
+        ExerciseCategory::Breathing,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Illustration {
+    // SVG source compiled into the app
+    Builtin(&'static str),
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exercise {
+    pub title: String,
+    pub instructions: String,
+    pub category: ExerciseCategory,
+    pub illustration: Option<Illustration>,
+}
+
+// How exercises are written in exercises.toml, as a list of [[exercise]] tables.
+#[derive(Debug, Deserialize)]
+struct UserExercise {
+    title: String,
+    instructions: String,
+    category: ExerciseCategory,
+    // Relative paths start from the folder that holds exercises.toml.
+    illustration: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserExerciseFile {
+    #[serde(default)]
+    exercise: Vec<UserExercise>,
+}
+
+fn make_builtin(
+    title: &str,
+    instructions: &str,
+    category: ExerciseCategory,
+    svg: &'static str,
+) -> Exercise {
+    Exercise {
+        title: title.to_string(),
+        instructions: instructions.to_string(),
+        category,
+        illustration: Some(Illustration::Builtin(svg)),
+    }
+}
+
+// Categories take turns, so rotating through the list doesn't give three stretches in a row.
+pub fn builtin_exercises() -> Vec<Exercise> {
+    vec![
+        make_builtin(
+            "Neck tilt",
+            "Slowly tilt your head towards your right shoulder and hold it there for 15 seconds. Repeat on the left side.",
+            ExerciseCategory::Stretch,
+            include_str!("../../meta/exercises/neck-tilt.svg"),
+        ),
+        make_builtin(
+            "Look into the distance",
+            "Look at something at least 6 meters away for 20 seconds, for example through a window. Blink a few times while you're at it.",
+            ExerciseCategory::Eyes,
+            include_str!("../../meta/exercises/look-away.svg"),
+        ),
+        make_builtin(
+            "Box breathing",
+            "Breathe in for 4 seconds, hold for 4, breathe out for 4 and hold again for 4. Repeat a few times.",
+            ExerciseCategory::Breathing,
+            include_str!("../../meta/exercises/box-breathing.svg"),
+        ),
+        make_builtin(
+            "Shoulder rolls",
+            "Roll your shoulders backwards in big, slow circles ten times, then ten times forwards.",
+            ExerciseCategory::Stretch,
+            include_str!("../../meta/exercises/shoulder-rolls.svg"),
+        ),
+        make_builtin(
+            "Palming",
+            "Rub your hands together until they're warm and cup them over your closed eyes. Relax for a few slow breaths.",
+            ExerciseCategory::Eyes,
+            include_str!("../../meta/exercises/palming.svg"),
+        ),
+        make_builtin(
+            "Reach for the ceiling",
+            "Stand up, interlace your fingers and stretch your arms above your head. Hold for 10 seconds, then lean gently to each side.",
+            ExerciseCategory::Stretch,
+            include_str!("../../meta/exercises/reach-up.svg"),
+        ),
+        make_builtin(
+            "Slow exhale",
+            "Breathe in through your nose for 4 seconds and out through your mouth for 6 to 8 seconds. Repeat five times.",
+            ExerciseCategory::Breathing,
+            include_str!("../../meta/exercises/slow-exhale.svg"),
+        ),
+        make_builtin(
+            "Wrist stretch",
+            "Hold one arm out with the palm up and gently pull the fingers back with your other hand for 15 seconds. Switch hands.",
+            ExerciseCategory::Stretch,
+            include_str!("../../meta/exercises/wrist-stretch.svg"),
+        ),
+    ]
+}
+
+pub struct ExerciseLibrary {
+    exercises: Vec<Exercise>,
+    next_position: usize,
+}
+
+impl ExerciseLibrary {
+    pub fn new(user_exercises: Vec<Exercise>) -> Self {
+        let mut exercises = builtin_exercises();
+        exercises.extend(user_exercises);
+        ExerciseLibrary {
+            exercises,
+            next_position: 0,
+        }
+    }
+
+    pub fn get_exercises_filename() -> Result<PathBuf, ConfigError> {
+        Ok(get_config_folder()?.join("exercises.toml"))
+    }
+
+    // A missing file just means there are no exercises of the user's own.
+    pub fn load_user_exercises(path: &Path) -> Result<Vec<Exercise>, ConfigError> {
+        let raw_contents = match fs::read_to_string(path) {
+            Ok(raw_contents) => raw_contents,
+            Err(error) => {
+                return match ConfigError::io(path, error) {
+                    ConfigError::NotFound(_) => Ok(Vec::new()),
+                    error => Err(error),
+                };
+            }
+        };
+        let user_exercise_file: UserExerciseFile =
+            toml::from_str(&raw_contents).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        let folder = path.parent().unwrap_or(Path::new(""));
+        Ok(user_exercise_file
+            .exercise
+            .into_iter()
+            .map(|user_exercise| Exercise {
+                title: user_exercise.title,
+                instructions: user_exercise.instructions,
+                category: user_exercise.category,
+                illustration: user_exercise
+                    .illustration
+                    .map(|illustration| Illustration::File(folder.join(illustration))),
+            })
+            .collect())
+    }
+
+    // Picks up where the previous break left off, skipping categories that are turned off.
+    pub fn next(&mut self, categories: &[ExerciseCategory]) -> Option<Exercise> {
+        let exercise_count = self.exercises.len();
+        let position = (0..exercise_count)
+            .map(|offset| (self.next_position + offset) % exercise_count)
+            .find(|position| categories.contains(&self.exercises[*position].category))?;
+        self.next_position = position + 1;
+        Some(self.exercises[position].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_exercises_path(contents: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!(
+            "stretch-break-exercises-test-{}-{}",
+            std::process::id(),
+            contents.len()
+        ));
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("exercises.toml");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn builtin_exercises_cover_every_category() {
+        let exercises = builtin_exercises();
+        for category in ExerciseCategory::ALL {
+            assert!(
+                exercises
+                    .iter()
+                    .any(|exercise| exercise.category == category)
+            );
+        }
+        for exercise in exercises {
+            assert!(matches!(
+                exercise.illustration,
+                Some(Illustration::Builtin(svg)) if svg.starts_with("<svg")
+            ));
+        }
+    }
+
+    #[test]
+    fn rotates_through_enabled_categories() {
+        let mut library = ExerciseLibrary::new(Vec::new());
+        let breathing_exercises: Vec<String> = builtin_exercises()
+            .into_iter()
+            .filter(|exercise| exercise.category == ExerciseCategory::Breathing)
+            .map(|exercise| exercise.title)
+            .collect();
+
+        let picked: Vec<String> = (0..breathing_exercises.len() + 1)
+            .map(|_| library.next(&[ExerciseCategory::Breathing]).unwrap().title)
+            .collect();
+        assert_eq!(picked[..breathing_exercises.len()], breathing_exercises);
+        assert_eq!(picked.last(), breathing_exercises.first());
+
+        assert_eq!(library.next(&[]), None);
+    }
+
+    #[test]
+    fn loads_user_exercises_next_to_the_builtin_ones() {
+        let path = make_exercises_path(
+            "[[exercise]]\n\
+             title = \"Squats\"\n\
+             instructions = \"Ten slow squats.\"\n\
+             category = \"stretch\"\n\
+             illustration = \"squats.svg\"\n",
+        );
+
+        let user_exercises = ExerciseLibrary::load_user_exercises(&path).unwrap();
+        assert_eq!(
+            user_exercises,
+            vec![Exercise {
+                title: String::from("Squats"),
+                instructions: String::from("Ten slow squats."),
+                category: ExerciseCategory::Stretch,
+                illustration: Some(Illustration::File(path.with_file_name("squats.svg"))),
+            }]
+        );
+
+        let mut library = ExerciseLibrary::new(user_exercises);
+        let titles: Vec<String> = (0..library.exercises.len())
+            .map(|_| library.next(&ExerciseCategory::ALL).unwrap().title)
+            .collect();
+        assert_eq!(titles.last().unwrap(), "Squats");
+    }
+
+    #[test]
+    fn missing_user_exercises_are_not_an_error() {
+        let path = std::env::temp_dir().join("stretch-break-no-such-exercises.toml");
+        assert_eq!(
+            ExerciseLibrary::load_user_exercises(&path).unwrap(),
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn reports_invalid_user_exercises() {
+        let path = make_exercises_path("[[exercise]]\ntitle = \"Incomplete\"\n");
+        assert!(matches!(
+            ExerciseLibrary::load_user_exercises(&path),
+            Err(ConfigError::Parse { .. })
+        ));
+    }
+}
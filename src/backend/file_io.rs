@@ -1,57 +1,464 @@
 use std::{
+    error::Error,
+    fmt,
     fs::{self, File},
-    io::Write,
-    path::PathBuf,
+    io::{self, Write},
+    path::{Path, PathBuf},
 };
 
 use chrono::{DateTime, Duration, Utc};
-use dirs::config_dir;
+use dirs::{config_dir, data_local_dir, state_dir};
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
-use crate::backend::idle_monitoring::{
-    DEFAULT_BREAK_LENGTH_SECS, DEFAULT_TIME_TO_BREAK_SECS, PresenceMode,
-};
+use crate::backend::config::{Config, Profile};
+use crate::backend::idle_monitoring::{ModeState, PresenceMode};
+
+pub const STATE_SCHEMA_VERSION: u32 = 3;
+
+// Files written before versioning was introduced don't have a schema_version field.
+const UNVERSIONED_SCHEMA_VERSION: u32 = 1;
+
+// MIGRATIONS[i] upgrades a file from version i + 1 to version i + 2.
+const MIGRATIONS: [fn(Value) -> Result<Value, String>; (STATE_SCHEMA_VERSION - 1) as usize] =
+    [migrate_v1_to_v2, migrate_v2_to_v3];
+
+// Settings that lived in the state file until they moved to config.toml.
+const LEGACY_SETTINGS_KEYS: [&str; 3] = ["time_to_break_secs", "break_length_secs", "reading_mode"];
+
+#[derive(Debug)]
+pub enum StateFileError {
+    NoStateDir,
+    NotFound(PathBuf),
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Corrupt {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    UnsupportedVersion {
+        path: PathBuf,
+        version: u32,
+    },
+    Migration {
+        path: PathBuf,
+        from_version: u32,
+        reason: String,
+    },
+}
+
+impl fmt::Display for StateFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateFileError::NoStateDir => write!(f, "could not determine where to store state"),
+            StateFileError::NotFound(path) => write!(f, "{} does not exist", path.display()),
+            StateFileError::Io { path, source } => {
+                write!(f, "could not access {}: {}", path.display(), source)
+            }
+            StateFileError::Corrupt { path, source } => {
+                write!(
+                    f,
+                    "{} is not a valid state file: {}",
+                    path.display(),
+                    source
+                )
+            }
+            StateFileError::UnsupportedVersion { path, version } => write!(
+                f,
+                "{} has schema version {}, but this version of Stretch Break only understands up to {}",
+                path.display(),
+                version,
+                STATE_SCHEMA_VERSION
+            ),
+            StateFileError::Migration {
+                path,
+                from_version,
+                reason,
+            } => write!(
+                f,
+                "could not migrate {} from schema version {}: {}",
+                path.display(),
+                from_version,
+                reason
+            ),
+        }
+    }
+}
 
-fn time_to_break_secs() -> i64 {
-    DEFAULT_TIME_TO_BREAK_SECS
+impl std::error::Error for StateFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StateFileError::Io { source, .. } => Some(source),
+            StateFileError::Corrupt { source, .. } => Some(source),
+            _ => None,
+        }
+    }
 }
 
-fn break_length_secs() -> i64 {
-    DEFAULT_BREAK_LENGTH_SECS
+impl StateFileError {
+    fn io(path: &Path, source: io::Error) -> Self {
+        match source.kind() {
+            io::ErrorKind::NotFound => StateFileError::NotFound(path.to_path_buf()),
+            _ => StateFileError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct PersistableState {
-    #[serde(default = "time_to_break_secs")]
-    pub time_to_break_secs: i64,
-    #[serde(default = "break_length_secs")]
-    pub break_length_secs: i64,
-    pub progress_towards_break: Duration,
-    pub progress_towards_reset: Duration,
+    pub mode_state: ModeState,
+    #[serde(default)]
+    pub overrun: Duration,
     pub last_checked: DateTime<Utc>,
     pub presence_mode: PresenceMode,
-    pub reading_mode: bool,
+    // Kept across restarts so quitting the app doesn't hand out fresh postpones in strict mode.
+    #[serde(default)]
+    pub postpone_count: u32,
+    #[serde(default)]
+    pub last_postpone: Option<Duration>,
+}
+
+#[derive(Serialize)]
+struct VersionedState<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    state: &'a PersistableState,
+}
+
+// Version 1 only knew about normal mode timers and didn't persist overrun.
+fn migrate_v1_to_v2(mut value: Value) -> Result<Value, String> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| "expected a JSON object".to_string())?;
+    let mut take = |key: &str| {
+        object
+            .remove(key)
+            .ok_or_else(|| format!("missing field {}", key))
+    };
+    let progress_towards_break = take("progress_towards_break")?;
+    let progress_towards_reset = take("progress_towards_reset")?;
+    let last_checked = object
+        .get("last_checked")
+        .cloned()
+        .ok_or_else(|| "missing field last_checked".to_string())?;
+    object.insert(
+        "mode_state".to_string(),
+        json!({
+            "state": "Normal",
+            "progress_towards_break": progress_towards_break,
+            "progress_towards_reset": progress_towards_reset,
+            "idle_state": { "state": "Idle", "idle_since": last_checked },
+        }),
+    );
+    Ok(value)
+}
+
+// Settings moved to config.toml, see migrate_legacy_state_file().
+fn migrate_v2_to_v3(mut value: Value) -> Result<Value, String> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| "expected a JSON object".to_string())?;
+    for key in LEGACY_SETTINGS_KEYS {
+        object.remove(key);
+    }
+    Ok(value)
+}
+
+// Versions up to 0.1.9 kept settings and timer state together in the config directory. Splits
+// that file into config.toml and a state file in the state directory.
+pub fn migrate_legacy_state_file() -> Result<(), Box<dyn Error>> {
+    let legacy_path = config_dir()
+        .ok_or(StateFileError::NoStateDir)?
+        .join("stretch-break")
+        .join("state.json");
+    if !legacy_path.exists() {
+        return Ok(());
+    }
+
+    let config_path = Config::get_config_filename()?;
+    if !config_path.exists() {
+        let legacy_value: Value = serde_json::from_str(&fs::read_to_string(&legacy_path)?)?;
+        let mut legacy_settings = serde_json::Map::new();
+        for key in LEGACY_SETTINGS_KEYS {
+            if let Some(setting) = legacy_value.get(key) {
+                legacy_settings.insert(key.to_string(), setting.clone());
+            }
+        }
+        let profile: Profile = serde_json::from_value(Value::Object(legacy_settings))?;
+        Config::from_legacy_profile(profile).save_to_disk()?;
+    }
+
+    let state_path = PersistableState::get_state_filename()?;
+    if state_path != legacy_path && !state_path.exists() {
+        fs::copy(&legacy_path, &state_path)?;
+        fs::remove_file(&legacy_path)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary_path = temporary_path(path);
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, path)
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.bak")
+}
+
+// Settings and metrics go through here too, so the name can't assume a .json file.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
+pub(crate) fn get_state_folder() -> Result<PathBuf, StateFileError> {
+    // There's no state directory convention outside of Linux.
+    let parent_folder = state_dir()
+        .or_else(data_local_dir)
+        .ok_or(StateFileError::NoStateDir)?
+        .join("stretch-break");
+    std::fs::create_dir_all(&parent_folder)
+        .map_err(|error| StateFileError::io(&parent_folder, error))?;
+    Ok(parent_folder)
 }
 
 impl PersistableState {
-    fn get_state_filename() -> Result<PathBuf, ()> {
-        let parent_folder = config_dir()
-            .expect("Could not construct config dir path")
-            .join("stretch-break");
-        std::fs::create_dir_all(&parent_folder).map_err(|_| ())?;
-        Ok(parent_folder.join("state.json"))
+    fn get_state_filename() -> Result<PathBuf, StateFileError> {
+        Ok(get_state_folder()?.join("state.json"))
+    }
+
+    pub fn load_from_disk() -> Result<Self, StateFileError> {
+        Self::load_from_path_or_backup(&Self::get_state_filename()?)
     }
 
-    pub fn load_from_disk() -> Result<Self, ()> {
-        let file_from_disk = fs::read_to_string(Self::get_state_filename()?).map_err(|_| ())?;
-        let persistable_state: PersistableState =
-            serde_json::from_str(&file_from_disk).map_err(|_| ())?;
-        Ok(persistable_state)
+    pub fn save_to_disk(&self) -> Result<(), StateFileError> {
+        self.save_to_path(&Self::get_state_filename()?)
+    }
+
+    fn load_from_path_or_backup(path: &Path) -> Result<Self, StateFileError> {
+        match Self::load_from_path(path) {
+            Ok(persistable_state) => Ok(persistable_state),
+            Err(error) => match Self::load_from_path(&backup_path(path)) {
+                Ok(persistable_state) => {
+                    println!("Restored timer state from backup because {}", error);
+                    Ok(persistable_state)
+                }
+                Err(_) => Err(error),
+            },
+        }
+    }
+
+    fn load_from_path(path: &Path) -> Result<Self, StateFileError> {
+        let file_from_disk =
+            fs::read_to_string(path).map_err(|error| StateFileError::io(path, error))?;
+        let corrupt = |source| StateFileError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut value: Value = serde_json::from_str(&file_from_disk).map_err(corrupt)?;
+
+        let version = match value.get("schema_version") {
+            None => UNVERSIONED_SCHEMA_VERSION,
+            Some(raw_version) => {
+                serde_json::from_value::<u32>(raw_version.clone()).map_err(corrupt)?
+            }
+        };
+        if !(UNVERSIONED_SCHEMA_VERSION..=STATE_SCHEMA_VERSION).contains(&version) {
+            return Err(StateFileError::UnsupportedVersion {
+                path: path.to_path_buf(),
+                version,
+            });
+        }
+        for from_version in version..STATE_SCHEMA_VERSION {
+            let migration = MIGRATIONS[(from_version - 1) as usize];
+            value = migration(value).map_err(|reason| StateFileError::Migration {
+                path: path.to_path_buf(),
+                from_version,
+                reason,
+            })?;
+        }
+
+        serde_json::from_value(value).map_err(corrupt)
+    }
+
+    // Writes to a temporary file first and renames it over the real one, so a crash or a
+    // full disk never leaves a half-written state file behind.
+    fn save_to_path(&self, path: &Path) -> Result<(), StateFileError> {
+        let raw_contents = serde_json::to_string(&VersionedState {
+            schema_version: STATE_SCHEMA_VERSION,
+            state: self,
+        })
+        .map_err(|source| StateFileError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        // Every file we wrote went through write_atomically, so the one being replaced is
+        // complete. Moving it aside keeps the backup complete too, even if we crash mid-way.
+        let backup_path = backup_path(path);
+        match fs::rename(path, &backup_path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                return Err(StateFileError::io(&backup_path, error));
+            }
+            _ => {}
+        }
+        write_atomically(path, raw_contents.as_bytes())
+            .map_err(|error| StateFileError::io(path, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::backend::idle_monitoring::DebouncedIdleState;
+
+    fn make_state_path() -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let folder = std::env::temp_dir().join(format!(
+            "stretch-break-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&folder).unwrap();
+        folder.join("state.json")
+    }
+
+    fn make_state(progress_towards_break_secs: i64) -> PersistableState {
+        let last_checked = Utc.with_ymd_and_hms(2025, 2, 3, 12, 34, 11).unwrap();
+        PersistableState {
+            mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(progress_towards_break_secs),
+                progress_towards_reset: Duration::seconds(3),
+                idle_state: DebouncedIdleState::Idle {
+                    idle_since: last_checked,
+                },
+            },
+            overrun: Duration::seconds(0),
+            last_checked,
+            presence_mode: PresenceMode::Active,
+            postpone_count: 0,
+            last_postpone: None,
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = make_state_path();
+        make_state(60).save_to_path(&path).unwrap();
+
+        let raw_contents: Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(raw_contents["schema_version"], json!(STATE_SCHEMA_VERSION));
+        assert!(!temporary_path(&path).exists());
+        assert_eq!(
+            PersistableState::load_from_path(&path).unwrap(),
+            make_state(60)
+        );
+    }
+
+    #[test]
+    fn migrates_unversioned_file() {
+        let path = make_state_path();
+        fs::write(
+            &path,
+            r#"{
+                "progress_towards_break": [60, 0],
+                "progress_towards_reset": [3, 0],
+                "last_checked": "2025-02-03T12:34:11Z",
+                "presence_mode": {"type": "active"},
+                "reading_mode": true
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            PersistableState::load_from_path(&path).unwrap(),
+            make_state(60)
+        );
+    }
+
+    #[test]
+    fn rejects_newer_schema_version() {
+        let path = make_state_path();
+        fs::write(&path, r#"{"schema_version": 99}"#).unwrap();
+
+        assert!(matches!(
+            PersistableState::load_from_path(&path),
+            Err(StateFileError::UnsupportedVersion { version: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn reports_missing_file() {
+        let path = make_state_path();
+
+        assert!(matches!(
+            PersistableState::load_from_path_or_backup(&path),
+            Err(StateFileError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_last_good_backup() {
+        let path = make_state_path();
+        make_state(60).save_to_path(&path).unwrap();
+        make_state(120).save_to_path(&path).unwrap();
+        fs::write(&path, "{\"schema_version\": 3, \"mode_sta").unwrap();
+
+        assert!(matches!(
+            PersistableState::load_from_path(&path),
+            Err(StateFileError::Corrupt { .. })
+        ));
+        assert_eq!(
+            PersistableState::load_from_path_or_backup(&path).unwrap(),
+            make_state(60)
+        );
+    }
+
+    #[test]
+    fn backup_holds_the_previous_save() {
+        let path = make_state_path();
+        make_state(60).save_to_path(&path).unwrap();
+        assert!(!backup_path(&path).exists());
+        make_state(120).save_to_path(&path).unwrap();
+        make_state(180).save_to_path(&path).unwrap();
+
+        assert_eq!(
+            PersistableState::load_from_path(&backup_path(&path)).unwrap(),
+            make_state(120)
+        );
+        assert_eq!(
+            PersistableState::load_from_path(&path).unwrap(),
+            make_state(180)
+        );
     }
 
-    pub fn save_to_disk(&self) -> Result<(), ()> {
-        let raw_contents = serde_json::to_string(self).map_err(|_| ())?;
-        let mut file = File::create(Self::get_state_filename()?).map_err(|_| ())?;
-        file.write_all(raw_contents.as_bytes()).map_err(|_| ())
+    #[test]
+    fn temporary_file_sits_next_to_any_kind_of_file() {
+        assert_eq!(
+            temporary_path(Path::new("/state/state.json")),
+            Path::new("/state/state.json.tmp")
+        );
+        assert_eq!(
+            temporary_path(Path::new("/config/config.toml")),
+            Path::new("/config/config.toml.tmp")
+        );
+        assert_eq!(
+            temporary_path(Path::new("/metrics/stretch_break.prom")),
+            Path::new("/metrics/stretch_break.prom.tmp")
+        );
     }
 }
@@ -0,0 +1,190 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::file_io::{StateFileError, get_state_folder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum BreakOutcome {
+    Completed,
+    Skipped,
+    Postponed { postponed_for_secs: i64 },
+    // Cut short or put off by a meeting in one of the calendars, not by the user
+    PostponedForMeeting { postponed_for_secs: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub outcome: BreakOutcome,
+}
+
+// How many of the latest entries are kept at hand for break policies
+const RECENT_ENTRY_COUNT: usize = 100;
+
+// An append-only log with one JSON object per line, so it can be inspected with standard tools.
+pub struct BreakHistory {
+    // None keeps no history at all.
+    path: Option<PathBuf>,
+    recent_entries: Vec<HistoryEntry>,
+}
+
+impl BreakHistory {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let recent_entries = path
+            .as_deref()
+            .map(Self::load_recent_from_path)
+            .unwrap_or_default();
+        BreakHistory {
+            path,
+            recent_entries,
+        }
+    }
+
+    // Lines that can't be read, for example from a newer version, are passed over.
+    fn load_all_from_path(path: &Path) -> Vec<HistoryEntry> {
+        let Ok(raw_contents) = fs::read_to_string(path) else {
+            return Vec::new();
+        };
+        raw_contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    fn load_recent_from_path(path: &Path) -> Vec<HistoryEntry> {
+        let entries = Self::load_all_from_path(path);
+        entries[entries.len().saturating_sub(RECENT_ENTRY_COUNT)..].to_vec()
+    }
+
+    // Everything from the given time on, however many entries that is. Oldest first.
+    pub fn load_since(path: &Path, since: DateTime<Utc>) -> Vec<HistoryEntry> {
+        Self::load_all_from_path(path)
+            .into_iter()
+            .filter(|entry| entry.timestamp >= since)
+            .collect()
+    }
+
+    // Oldest first
+    pub fn recent_entries(&self) -> &[HistoryEntry] {
+        &self.recent_entries
+    }
+
+    pub fn get_history_filename() -> Result<PathBuf, StateFileError> {
+        Ok(get_state_folder()?.join("history.jsonl"))
+    }
+
+    pub fn record(&mut self, timestamp: DateTime<Utc>, outcome: BreakOutcome) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        let entry = HistoryEntry { timestamp, outcome };
+        if self.recent_entries.len() >= RECENT_ENTRY_COUNT {
+            self.recent_entries.remove(0);
+        }
+        self.recent_entries.push(entry);
+        if let Err(error) = Self::append_to_path(path, &entry) {
+            println!("Could not add to break history: {}", error);
+        }
+    }
+
+    fn append_to_path(path: &Path, entry: &HistoryEntry) -> io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(line.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn load_from_path(path: &Path) -> Vec<HistoryEntry> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn records_outcomes_in_order() {
+        let path = std::env::temp_dir().join(format!(
+            "stretch-break-history-test-{}.jsonl",
+            std::process::id()
+        ));
+        fs::remove_file(&path).ok();
+        let mut history = BreakHistory::new(Some(path.clone()));
+        let timestamp = Utc.with_ymd_and_hms(2025, 2, 3, 12, 34, 11).unwrap();
+
+        history.record(
+            timestamp,
+            BreakOutcome::Postponed {
+                postponed_for_secs: 300,
+            },
+        );
+        history.record(timestamp, BreakOutcome::Completed);
+
+        assert_eq!(
+            load_from_path(&path),
+            vec![
+                HistoryEntry {
+                    timestamp,
+                    outcome: BreakOutcome::Postponed {
+                        postponed_for_secs: 300
+                    },
+                },
+                HistoryEntry {
+                    timestamp,
+                    outcome: BreakOutcome::Completed,
+                },
+            ]
+        );
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with(r#"{"timestamp":"2025-02-03T12:34:11Z","outcome":"postponed","#)
+        );
+        assert_eq!(
+            BreakHistory::new(Some(path.clone())).recent_entries(),
+            history.recent_entries()
+        );
+    }
+
+    #[test]
+    fn loads_every_entry_since_a_time() {
+        let path = std::env::temp_dir().join(format!(
+            "stretch-break-history-since-test-{}.jsonl",
+            std::process::id()
+        ));
+        fs::remove_file(&path).ok();
+        let mut history = BreakHistory::new(Some(path.clone()));
+        let start = Utc.with_ymd_and_hms(2025, 2, 3, 8, 0, 0).unwrap();
+        // More than the recent entries hold, all on the same day
+        for minutes in 0..150 {
+            history.record(start + Duration::minutes(minutes), BreakOutcome::Completed);
+        }
+
+        assert_eq!(history.recent_entries().len(), RECENT_ENTRY_COUNT);
+        assert_eq!(BreakHistory::load_since(&path, start).len(), 150);
+        assert_eq!(
+            BreakHistory::load_since(&path, start + Duration::minutes(140)),
+            BreakHistory::load_all_from_path(&path)[140..]
+        );
+    }
+}
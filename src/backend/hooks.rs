@@ -0,0 +1,308 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+
+use crate::backend::break_events::{BreakEvent, BreakEventKind};
+use crate::backend::config::{Config, ConfigError, get_config_folder};
+use crate::backend::history::BreakOutcome;
+use crate::backend::idle_monitoring::PresenceMode;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hook {
+    Executable(PathBuf),
+    // Run through sh -c
+    Command(String),
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hook::Executable(path) => write!(f, "{}", path.display()),
+            Hook::Command(command) => write!(f, "{}", command),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HookError {
+    Spawn(io::Error),
+    TimedOut,
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookError::Spawn(error) => write!(f, "could not start: {}", error),
+            HookError::TimedOut => write!(f, "took too long and was stopped"),
+        }
+    }
+}
+
+// Everything a hook gets to know, as STRETCH_BREAK_* environment variables.
+pub fn hook_environment(event: &BreakEvent) -> Vec<(&'static str, String)> {
+    let idle_info = &event.idle_info;
+    let snoozed_until = match idle_info.presence_mode {
+        PresenceMode::SnoozedUntil(timestamp) => timestamp.to_rfc3339(),
+        PresenceMode::Active | PresenceMode::Muted => String::new(),
+    };
+    let postponed_for_secs = match event.kind {
+        BreakEventKind::BreakEnded(
+            BreakOutcome::Postponed { postponed_for_secs }
+            | BreakOutcome::PostponedForMeeting { postponed_for_secs },
+        ) => postponed_for_secs.to_string(),
+        _ => String::new(),
+    };
+    vec![
+        ("STRETCH_BREAK_EVENT", event.kind.name().to_string()),
+        ("STRETCH_BREAK_MODE", idle_info.mode_name().to_string()),
+        (
+            "STRETCH_BREAK_PRESENCE",
+            idle_info.presence_name().to_string(),
+        ),
+        ("STRETCH_BREAK_SNOOZED_UNTIL", snoozed_until),
+        (
+            "STRETCH_BREAK_SECS_UNTIL_BREAK",
+            idle_info.secs_until_break().to_string(),
+        ),
+        (
+            "STRETCH_BREAK_TIME_TO_BREAK_SECS",
+            idle_info.time_to_break_secs.to_string(),
+        ),
+        (
+            "STRETCH_BREAK_BREAK_LENGTH_SECS",
+            idle_info.break_length_secs.to_string(),
+        ),
+        (
+            "STRETCH_BREAK_IDLE_SECS",
+            idle_info.idle_since_seconds.to_string(),
+        ),
+        (
+            "STRETCH_BREAK_READING_MODE",
+            idle_info.reading_mode.to_string(),
+        ),
+        (
+            "STRETCH_BREAK_POSTPONE_COUNT",
+            idle_info.postpone_count.to_string(),
+        ),
+        ("STRETCH_BREAK_POSTPONED_FOR_SECS", postponed_for_secs),
+        (
+            "STRETCH_BREAK_TIMESTAMP",
+            idle_info.last_checked.to_rfc3339(),
+        ),
+    ]
+}
+
+pub fn get_hooks_folder() -> Result<PathBuf, ConfigError> {
+    Ok(get_config_folder()?.join("hooks"))
+}
+
+// Executable files, in alphabetical order so users can control the order with prefixes.
+// Anything else in the folder, such as a README, is left alone.
+fn find_hook_executables(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut executables: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.metadata().is_ok_and(|metadata| {
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            })
+        })
+        .map(|entry| entry.path())
+        .collect();
+    executables.sort();
+    executables
+}
+
+pub fn run_hook(
+    hook: &Hook,
+    environment: &[(&'static str, String)],
+    timeout: StdDuration,
+) -> Result<ExitStatus, HookError> {
+    let mut command = match hook {
+        Hook::Executable(path) => Command::new(path),
+        Hook::Command(shell_command) => {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(shell_command);
+            command
+        }
+    };
+    let mut child = command
+        .envs(environment.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(HookError::Spawn)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().map_err(HookError::Spawn)? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(HookError::TimedOut);
+        }
+        thread::sleep(StdDuration::from_millis(50));
+    }
+}
+
+// Runs hooks for every event the actor reports, one after the other and in the order the
+// events happened, so a quick break end hook can't finish before a slow break start hook.
+// The hook timeout keeps a stuck hook from holding up the rest for long.
+pub fn run_hooks_forever(
+    mut break_events: broadcast::Receiver<BreakEvent>,
+    config: watch::Receiver<Config>,
+    hooks_folder: Option<PathBuf>,
+) {
+    loop {
+        let event = match break_events.blocking_recv() {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                println!("Hooks missed {} events", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let hook_settings = config.borrow().hooks.clone();
+        let hooks = hooks_folder
+            .as_deref()
+            .map(find_hook_executables)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Hook::Executable)
+            .chain(hook_settings.commands.into_iter().map(Hook::Command));
+        let environment = hook_environment(&event);
+        let timeout = StdDuration::from_secs(hook_settings.timeout_secs);
+        let name = event.kind.name();
+        for hook in hooks {
+            match run_hook(&hook, &environment, timeout) {
+                Ok(status) if status.success() => println!("Hook {} ran for {}", hook, name),
+                Ok(status) => println!("Hook {} failed with {}", hook, status),
+                Err(error) => println!("Hook {} {}", hook, error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::backend::idle_monitoring::IdleInfo;
+
+    fn make_event(kind: BreakEventKind) -> BreakEvent {
+        let now = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        BreakEvent {
+            kind,
+            idle_info: IdleInfo {
+                idle_since_seconds: 3,
+                presence_mode: PresenceMode::SnoozedUntil(now + Duration::hours(1)),
+                postpone_count: 1,
+                last_postpone: Some(Duration::minutes(5)),
+                ..IdleInfo::for_tests(now)
+            },
+        }
+    }
+
+    fn make_hooks_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!(
+            "stretch-break-hooks-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn environment_describes_the_event() {
+        let environment = hook_environment(&make_event(BreakEventKind::BreakEnded(
+            BreakOutcome::Postponed {
+                postponed_for_secs: 300,
+            },
+        )));
+        let value = |key| {
+            environment
+                .iter()
+                .find(|(other_key, _)| *other_key == key)
+                .map(|(_, value)| value.as_str())
+                .unwrap()
+        };
+        assert_eq!(value("STRETCH_BREAK_EVENT"), "postpone");
+        assert_eq!(value("STRETCH_BREAK_MODE"), "normal");
+        assert_eq!(value("STRETCH_BREAK_PRESENCE"), "snoozed");
+        assert_eq!(
+            value("STRETCH_BREAK_SNOOZED_UNTIL"),
+            "2025-02-03T13:00:00+00:00"
+        );
+        assert_eq!(value("STRETCH_BREAK_SECS_UNTIL_BREAK"), "300");
+        assert_eq!(value("STRETCH_BREAK_POSTPONED_FOR_SECS"), "300");
+        assert_eq!(value("STRETCH_BREAK_POSTPONE_COUNT"), "1");
+    }
+
+    #[test]
+    fn meetings_postpone_under_their_own_name() {
+        let environment = hook_environment(&make_event(BreakEventKind::BreakEnded(
+            BreakOutcome::PostponedForMeeting {
+                postponed_for_secs: 1800,
+            },
+        )));
+        assert!(environment.contains(&("STRETCH_BREAK_EVENT", String::from("meeting_postpone"))));
+        assert!(environment.contains(&("STRETCH_BREAK_POSTPONED_FOR_SECS", String::from("1800"))));
+    }
+
+    #[test]
+    fn only_executables_are_hooks() {
+        let folder = make_hooks_folder("executables");
+        for (name, mode) in [("20-lights", 0o755), ("10-slack", 0o700), ("README", 0o644)] {
+            let path = folder.join(name);
+            fs::write(&path, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        assert_eq!(
+            find_hook_executables(&folder),
+            vec![folder.join("10-slack"), folder.join("20-lights")]
+        );
+        assert_eq!(
+            find_hook_executables(&folder.join("missing")),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn hooks_see_the_environment() {
+        let output = make_hooks_folder("environment").join("output");
+        let hook = Hook::Command(format!(
+            "echo \"$STRETCH_BREAK_EVENT\" > {}",
+            output.display()
+        ));
+        let environment = hook_environment(&make_event(BreakEventKind::BreakStarted));
+
+        let status = run_hook(&hook, &environment, StdDuration::from_secs(5)).unwrap();
+        assert!(status.success());
+        assert_eq!(fs::read_to_string(&output).unwrap(), "break_start\n");
+    }
+
+    #[test]
+    fn slow_hooks_are_stopped() {
+        let started = Instant::now();
+        let result = run_hook(
+            &Hook::Command(String::from("sleep 10")),
+            &[],
+            StdDuration::from_millis(200),
+        );
+        assert!(matches!(result, Err(HookError::TimedOut)));
+        assert!(started.elapsed() < StdDuration::from_secs(5));
+    }
+}
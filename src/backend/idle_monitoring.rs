@@ -7,6 +7,7 @@ use user_idle2::UserIdle;
 #[cfg(test)]
 use mockall::automock;
 
+use crate::backend::config::Profile;
 use crate::backend::file_io::PersistableState;
 
 pub const DEFAULT_TIME_TO_BREAK_SECS: i64 = 20 * 60;
@@ -24,6 +25,13 @@ pub trait AbstractIdleChecker {
     fn get_idle_time_in_seconds(&self) -> u64;
 }
 
+// Lets the idle source be picked at startup
+impl<T: AbstractIdleChecker + ?Sized> AbstractIdleChecker for Box<T> {
+    fn get_idle_time_in_seconds(&self) -> u64 {
+        (**self).get_idle_time_in_seconds()
+    }
+}
+
 pub struct IdleChecker;
 #[cfg_attr(test, automock)]
 impl AbstractIdleChecker for IdleChecker {
@@ -120,6 +128,11 @@ pub struct IdleInfo {
     pub presence_mode: PresenceMode,
     pub time_to_break_secs: i64,
     pub break_length_secs: i64,
+    pub prebreak_idle_secs: u64,
+    // Postpones since the last completed break. Strict mode limits these.
+    pub postpone_count: u32,
+    // How long the latest of those postpones was, so strict mode can make the next one shorter
+    pub last_postpone: Option<Duration>,
     pub overrun: Duration,
 }
 
@@ -131,30 +144,111 @@ impl IdleInfo {
             PresenceMode::Muted => true,
         }
     }
+
+    // Names for the mode and presence as hooks, scripts and other integrations see them
+    pub fn mode_name(&self) -> &'static str {
+        match self.last_mode_state {
+            ModeState::Normal { .. } => "normal",
+            ModeState::PreBreak { .. } => "pre_break",
+            ModeState::Break { .. } => "break",
+        }
+    }
+
+    pub fn presence_name(&self) -> &'static str {
+        match self.presence_mode {
+            PresenceMode::Active => "active",
+            PresenceMode::SnoozedUntil(_) => "snoozed",
+            PresenceMode::Muted => "muted",
+        }
+    }
+
+    // Zero once the break is due
+    pub fn secs_until_break(&self) -> i64 {
+        match self.last_mode_state {
+            ModeState::Normal {
+                progress_towards_break,
+                ..
+            } => (self.time_to_break_secs - progress_towards_break.num_seconds()).max(0),
+            ModeState::PreBreak { .. } | ModeState::Break { .. } => 0,
+        }
+    }
+}
+
+// Shared starting point for tests elsewhere: an unmuted user busy at the computer, 15 minutes
+// into a 20 minute interval. Tests change what they care about with struct update syntax.
+#[cfg(test)]
+impl IdleInfo {
+    pub fn for_tests(last_checked: DateTime<Utc>) -> IdleInfo {
+        IdleInfo {
+            idle_since_seconds: 0,
+            last_checked,
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(900),
+                progress_towards_reset: Duration::zero(),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: last_checked,
+                },
+            },
+            reading_mode: false,
+            presence_mode: PresenceMode::Active,
+            time_to_break_secs: 1200,
+            break_length_secs: 60,
+            prebreak_idle_secs: 2,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::zero(),
+        }
+    }
+}
+
+fn make_user_active(
+    debounced_idle_state: DebouncedIdleState,
+    check_time: DateTime<Utc>,
+) -> DebouncedIdleState {
+    match debounced_idle_state {
+        DebouncedIdleState::Active { active_since } => DebouncedIdleState::Active { active_since },
+        DebouncedIdleState::ActiveGoingToIdle { active_since, .. } => {
+            DebouncedIdleState::Active { active_since }
+        }
+        DebouncedIdleState::IdleGoingToActive { .. } | DebouncedIdleState::Idle { .. } => {
+            DebouncedIdleState::Active {
+                active_since: check_time,
+            }
+        }
+    }
 }
 
 pub struct IdleMonitor<T: AbstractIdleChecker, U: AbstractClock> {
     idle_checker: T,
     clock: U,
+    // Another app, such as a video player, is keeping the session from going idle.
+    idle_inhibited: bool,
     last_idle_info: IdleInfo,
 }
 
 impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
-    pub fn new(idle_checker: T, clock: U, restored_state: Option<PersistableState>) -> Self {
+    pub fn new(
+        idle_checker: T,
+        clock: U,
+        profile: &Profile,
+        restored_state: Option<PersistableState>,
+    ) -> Self {
         let time = clock.get_time();
-        let use_restored_timers = match restored_state {
-            Some(ref state) => {
-                let time_since_last_check = time.signed_duration_since(state.last_checked);
-                let sum_of_check_delta_and_reset_progress = time_since_last_check
-                    .checked_add(&state.progress_towards_reset)
-                    .unwrap();
-                sum_of_check_delta_and_reset_progress < Duration::seconds(DEFAULT_BREAK_LENGTH_SECS)
-            }
-            None => false,
-        };
+        let restored_timers = restored_state
+            .as_ref()
+            .and_then(|state| Self::_restore_timers(state, profile.break_length_secs, time));
+        let (last_mode_state, overrun) = restored_timers.unwrap_or((
+            ModeState::Normal {
+                progress_towards_break: Duration::seconds(0),
+                progress_towards_reset: Duration::seconds(0),
+                idle_state: DebouncedIdleState::Active { active_since: time },
+            },
+            Duration::seconds(0),
+        ));
         Self {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 presence_mode: match restored_state {
                     Some(ref state) => state.presence_mode,
@@ -162,66 +256,71 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 },
                 idle_since_seconds: 0,
                 last_checked: time,
-                last_mode_state: ModeState::Normal {
-                    idle_state: match use_restored_timers {
-                        true => DebouncedIdleState::Idle {
-                            idle_since: match restored_state {
-                                Some(ref state) => state.last_checked,
-                                None => time,
-                            },
-                        },
-                        false => DebouncedIdleState::Active { active_since: time },
-                    },
-                    progress_towards_break: match restored_state {
-                        Some(ref state) => {
-                            if use_restored_timers {
-                                state.progress_towards_break
-                            } else {
-                                Duration::seconds(0)
-                            }
-                        }
-                        None => Duration::seconds(0),
-                    },
-                    progress_towards_reset: match restored_state {
-                        Some(ref state) => {
-                            if use_restored_timers {
-                                let time_since_last_checked: Duration =
-                                    time.signed_duration_since(state.last_checked);
-                                let unconstrained_duration = state
-                                    .progress_towards_reset
-                                    .checked_add(&time_since_last_checked)
-                                    .unwrap();
-                                if unconstrained_duration
-                                    > Duration::seconds(DEFAULT_BREAK_LENGTH_SECS)
-                                {
-                                    Duration::seconds(DEFAULT_BREAK_LENGTH_SECS)
-                                } else {
-                                    unconstrained_duration
-                                }
-                            } else {
-                                Duration::seconds(0)
-                            }
-                        }
-                        None => Duration::seconds(0),
-                    },
+                last_mode_state,
+                reading_mode: profile.reading_mode,
+                time_to_break_secs: profile.time_to_break_secs,
+                break_length_secs: profile.break_length_secs,
+                prebreak_idle_secs: profile.prebreak_idle_secs,
+                postpone_count: match (restored_timers, restored_state.as_ref()) {
+                    (Some(_), Some(state)) => state.postpone_count,
+                    _ => 0,
                 },
-                reading_mode: match restored_state {
-                    Some(ref state) => state.reading_mode,
-                    None => false,
+                last_postpone: match (restored_timers, restored_state.as_ref()) {
+                    (Some(_), Some(state)) => state.last_postpone,
+                    _ => None,
                 },
-                time_to_break_secs: match restored_state {
-                    Some(ref state) => state.time_to_break_secs,
-                    None => DEFAULT_TIME_TO_BREAK_SECS,
-                },
-                break_length_secs: match restored_state {
-                    Some(ref state) => state.break_length_secs,
-                    None => DEFAULT_BREAK_LENGTH_SECS,
-                },
-                overrun: Duration::seconds(0),
+                overrun,
             },
         }
     }
 
+    // The app wasn't running between the last check and now, so we treat that gap as idle
+    // time. Returns None when that gap alone was long enough to count as a break.
+    fn _restore_timers(
+        state: &PersistableState,
+        break_length_secs: i64,
+        time: DateTime<Utc>,
+    ) -> Option<(ModeState, Duration)> {
+        let time_since_last_check = time.signed_duration_since(state.last_checked);
+        let break_length = Duration::seconds(break_length_secs);
+        let idle_state = DebouncedIdleState::Idle {
+            idle_since: state.last_checked,
+        };
+
+        match state.mode_state {
+            ModeState::Normal {
+                progress_towards_break,
+                progress_towards_reset,
+                ..
+            } => {
+                let progress_towards_reset = progress_towards_reset + time_since_last_check;
+                (progress_towards_reset < break_length).then_some((
+                    ModeState::Normal {
+                        progress_towards_break,
+                        progress_towards_reset,
+                        idle_state,
+                    },
+                    state.overrun,
+                ))
+            }
+            ModeState::PreBreak { started_at } => (time_since_last_check < break_length)
+                .then_some((ModeState::PreBreak { started_at }, state.overrun)),
+            ModeState::Break {
+                progress_towards_finish,
+                ..
+            } => {
+                let progress_towards_finish = progress_towards_finish + time_since_last_check;
+                (progress_towards_finish < break_length).then_some((
+                    ModeState::Break {
+                        progress_towards_finish,
+                        idle_state,
+                    },
+                    state.overrun,
+                ))
+            }
+        }
+    }
+
     fn _make_debounced_idle_state(
         &self,
         idle_since_seconds: u64,
@@ -229,7 +328,7 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
         time_since_last_check: Duration,
         check_time: DateTime<Utc>,
         start_of_transition_period: DateTime<Utc>,
-        in_break: bool,
+        idle_counts_as_activity: bool,
     ) -> DebouncedIdleState {
         match last_idle_state {
             DebouncedIdleState::Active { active_since: _ }
@@ -243,7 +342,7 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
             }
             DebouncedIdleState::Active { active_since } => match idle_since_seconds {
                 0 => DebouncedIdleState::Active { active_since },
-                _ => match self.last_idle_info.reading_mode && !in_break {
+                _ => match idle_counts_as_activity {
                     true => DebouncedIdleState::Active { active_since },
                     false => DebouncedIdleState::ActiveGoingToIdle {
                         active_since,
@@ -359,6 +458,9 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
             reading_mode,
             break_length_secs,
             time_to_break_secs,
+            prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+            postpone_count: self.last_idle_info.postpone_count,
+            last_postpone: self.last_idle_info.last_postpone,
             overrun,
         }
     }
@@ -384,6 +486,9 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
             reading_mode,
             break_length_secs,
             time_to_break_secs,
+            prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+            postpone_count: self.last_idle_info.postpone_count,
+            last_postpone: self.last_idle_info.last_postpone,
             overrun,
         }
     }
@@ -420,56 +525,85 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
             reading_mode,
             time_to_break_secs,
             break_length_secs,
+            prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+            postpone_count: self.last_idle_info.postpone_count,
+            last_postpone: self.last_idle_info.last_postpone,
             overrun,
         }
     }
 
     pub fn snooze(&mut self, timestamp: DateTime<Utc>) -> IdleInfo {
         self.last_idle_info.presence_mode = PresenceMode::SnoozedUntil(timestamp);
-        self.persist_settings_to_disk();
+        self.persist_state_to_disk();
+        self.last_idle_info
+    }
+
+    // A meeting takes priority over a break that's underway; the break is offered again
+    // once the meeting is over. Not persisted, the calendar is consulted again on startup.
+    pub fn snooze_until_meeting_ends(&mut self, meeting_end: DateTime<Utc>) -> IdleInfo {
+        if let ModeState::Break { .. } = self.last_idle_info.last_mode_state {
+            self.last_idle_info.last_mode_state = ModeState::Normal {
+                progress_towards_break: Duration::seconds(self.last_idle_info.time_to_break_secs),
+                progress_towards_reset: Duration::seconds(0),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: self.clock.get_time(),
+                },
+            };
+        }
+        self.last_idle_info.presence_mode = PresenceMode::SnoozedUntil(meeting_end);
         self.last_idle_info
     }
 
-    fn persist_settings_to_disk(&self) {
-        if self.export_persistable_state().save_to_disk().is_err() {
-            println!("Could not save settings and timer state to disk");
+    // Sitting still through a meeting isn't a break, so a break that came due during one
+    // is offered even if the idle time would otherwise have reset the timer.
+    pub fn make_break_due(&mut self) -> IdleInfo {
+        if let ModeState::Normal { idle_state, .. } = self.last_idle_info.last_mode_state {
+            self.last_idle_info.last_mode_state = ModeState::Normal {
+                progress_towards_break: Duration::seconds(self.last_idle_info.time_to_break_secs),
+                progress_towards_reset: Duration::seconds(0),
+                idle_state,
+            };
+        }
+        self.last_idle_info
+    }
+
+    fn persist_state_to_disk(&self) {
+        if let Err(error) = self.export_persistable_state().save_to_disk() {
+            println!("Could not save timer state to disk: {}", error);
         }
     }
 
     pub fn mute(&mut self) -> IdleInfo {
         self.last_idle_info.presence_mode = PresenceMode::Muted;
-        self.persist_settings_to_disk();
+        self.persist_state_to_disk();
         self.last_idle_info
     }
 
     pub fn unmute(&mut self) -> IdleInfo {
         self.last_idle_info.presence_mode = PresenceMode::Active;
-        self.persist_settings_to_disk();
+        self.persist_state_to_disk();
+        self.last_idle_info
+    }
+
+    // Only touches the settings that differ, so switching to a profile with the same timings
+    // doesn't disturb a running countdown.
+    pub fn apply_profile(&mut self, profile: &Profile) -> IdleInfo {
+        if profile.reading_mode != self.last_idle_info.reading_mode {
+            self.set_reading_mode(profile.reading_mode);
+        }
+        if profile.time_to_break_secs != self.last_idle_info.time_to_break_secs {
+            self.set_time_to_break(profile.time_to_break_secs);
+        }
+        if profile.break_length_secs != self.last_idle_info.break_length_secs {
+            self.set_break_length(profile.break_length_secs);
+        }
+        self.last_idle_info.prebreak_idle_secs = profile.prebreak_idle_secs;
         self.last_idle_info
     }
 
     pub fn set_reading_mode(&mut self, reading_mode: bool) {
         let check_time = self.clock.get_time();
 
-        fn map_debounced_idle_state(
-            debounced_idle_state: DebouncedIdleState,
-            check_time: DateTime<Utc>,
-        ) -> DebouncedIdleState {
-            match debounced_idle_state {
-                DebouncedIdleState::Active { active_since } => {
-                    DebouncedIdleState::Active { active_since }
-                }
-                DebouncedIdleState::ActiveGoingToIdle { active_since, .. } => {
-                    DebouncedIdleState::Active { active_since }
-                }
-                DebouncedIdleState::IdleGoingToActive { .. } | DebouncedIdleState::Idle { .. } => {
-                    DebouncedIdleState::Active {
-                        active_since: check_time,
-                    }
-                }
-            }
-        }
-
         self.last_idle_info = IdleInfo {
             idle_since_seconds: self.last_idle_info.idle_since_seconds,
             last_checked: self.last_idle_info.last_checked,
@@ -481,7 +615,7 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 } => ModeState::Normal {
                     progress_towards_break,
                     progress_towards_reset,
-                    idle_state: map_debounced_idle_state(idle_state, check_time),
+                    idle_state: make_user_active(idle_state, check_time),
                 },
                 ModeState::PreBreak { started_at } => ModeState::PreBreak { started_at },
                 ModeState::Break {
@@ -489,17 +623,41 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                     idle_state,
                 } => ModeState::Break {
                     progress_towards_finish,
-                    idle_state: map_debounced_idle_state(idle_state, check_time),
+                    idle_state: make_user_active(idle_state, check_time),
                 },
             },
             reading_mode,
             presence_mode: self.last_idle_info.presence_mode,
             time_to_break_secs: self.last_idle_info.time_to_break_secs,
             break_length_secs: self.last_idle_info.break_length_secs,
+            prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+            postpone_count: self.last_idle_info.postpone_count,
+            last_postpone: self.last_idle_info.last_postpone,
             overrun: self.last_idle_info.overrun,
         };
+    }
 
-        self.persist_settings_to_disk();
+    // Sitting still while an inhibitor is held counts as watching rather than being away.
+    // Breaks themselves still need the user to step away.
+    pub fn set_idle_inhibited(&mut self, idle_inhibited: bool) {
+        if idle_inhibited
+            && !self.idle_inhibited
+            && let ModeState::Normal {
+                progress_towards_break,
+                progress_towards_reset,
+                idle_state,
+            } = self.last_idle_info.last_mode_state
+        {
+            self.last_idle_info.last_mode_state = ModeState::Normal {
+                progress_towards_break,
+                progress_towards_reset: match idle_state.is_user_active() {
+                    true => progress_towards_reset,
+                    false => Duration::seconds(0),
+                },
+                idle_state: make_user_active(idle_state, self.clock.get_time()),
+            };
+        }
+        self.idle_inhibited = idle_inhibited;
     }
 
     pub fn get_last_idle_info(&self) -> IdleInfo {
@@ -507,8 +665,26 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
     }
 
     pub fn refresh_idle_info(&mut self) -> IdleInfo {
+        self.refresh_idle_info_with_policy(|_| true)
+    }
+
+    // The policy sees this tick's idle time. Saying the user isn't idle keeps them active like
+    // an inhibitor does, without resetting an idle streak that already started.
+    pub fn refresh_idle_info_with_policy(
+        &mut self,
+        is_user_idle: impl FnOnce(&IdleInfo) -> bool,
+    ) -> IdleInfo {
         let idle_since_seconds = self.idle_checker.get_idle_time_in_seconds();
         let check_time = self.clock.get_time();
+        // Outside breaks, sitting still in reading mode, under an inhibitor or when the policy
+        // says so isn't idleness.
+        let idle_counts_as_activity = self.last_idle_info.reading_mode
+            || self.idle_inhibited
+            || !is_user_idle(&IdleInfo {
+                idle_since_seconds,
+                last_checked: check_time,
+                ..self.last_idle_info
+            });
         let start_of_transition_period = self.clock.get_time()
             - Duration::seconds(TRANSITION_THRESHOLD_SECS.try_into().unwrap());
         let time_since_last_check =
@@ -565,7 +741,7 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                     time_since_last_check,
                     check_time,
                     start_of_transition_period,
-                    false,
+                    idle_counts_as_activity,
                 ),
                 time_since_last_check,
                 self.last_idle_info.reading_mode,
@@ -614,7 +790,7 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                         time_since_last_check,
                         check_time,
                         start_of_transition_period,
-                        false,
+                        idle_counts_as_activity,
                     ),
                     time_since_last_check,
                     self.last_idle_info.reading_mode,
@@ -623,7 +799,7 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                     self.last_idle_info.overrun,
                 ),
             ModeState::PreBreak { .. }
-                if idle_since_seconds >= REQUIRED_PREBREAK_IDLE_STREAK_SECONDS =>
+                if idle_since_seconds >= self.last_idle_info.prebreak_idle_secs =>
             {
                 IdleInfo {
                     idle_since_seconds,
@@ -641,6 +817,9 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                     reading_mode: self.last_idle_info.reading_mode,
                     break_length_secs: self.last_idle_info.break_length_secs,
                     time_to_break_secs: self.last_idle_info.time_to_break_secs,
+                    prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+                    postpone_count: self.last_idle_info.postpone_count,
+                    last_postpone: self.last_idle_info.last_postpone,
                     overrun: self.last_idle_info.overrun
                         + (check_time - self.last_idle_info.last_checked),
                 }
@@ -677,13 +856,16 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                             time_since_last_check,
                             check_time,
                             start_of_transition_period,
-                            true,
+                            false,
                         ),
                     },
                     presence_mode: new_presence_mode,
                     reading_mode: self.last_idle_info.reading_mode,
                     time_to_break_secs: self.last_idle_info.time_to_break_secs,
                     break_length_secs: self.last_idle_info.break_length_secs,
+                    prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+                    postpone_count: 0,
+                    last_postpone: None,
                     overrun: Duration::seconds(0),
                 }
             }
@@ -699,7 +881,7 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                     time_since_last_check,
                     check_time,
                     start_of_transition_period,
-                    true,
+                    false,
                 ),
                 progress_towards_finish,
                 time_since_last_check,
@@ -719,6 +901,17 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
             ),
         };
 
+        // A timer that reset while idle means the user took a break of their own accord.
+        if let ModeState::Normal {
+            progress_towards_break,
+            ..
+        } = self.last_idle_info.last_mode_state
+            && progress_towards_break == Duration::seconds(0)
+        {
+            self.last_idle_info.postpone_count = 0;
+            self.last_idle_info.last_postpone = None;
+        }
+
         self.last_idle_info
     }
 
@@ -728,7 +921,8 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 progress_towards_break: _,
                 progress_towards_reset: _,
                 idle_state: _,
-            } => IdleInfo {
+            }
+            | ModeState::PreBreak { .. } => IdleInfo {
                 idle_since_seconds: self.last_idle_info.idle_since_seconds,
                 last_checked: self.last_idle_info.last_checked,
                 last_mode_state: ModeState::Break {
@@ -741,6 +935,9 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 reading_mode: self.last_idle_info.reading_mode,
                 time_to_break_secs: self.last_idle_info.time_to_break_secs,
                 break_length_secs: self.last_idle_info.break_length_secs,
+                prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+                postpone_count: self.last_idle_info.postpone_count,
+                last_postpone: self.last_idle_info.last_postpone,
                 overrun: self.last_idle_info.overrun,
             },
             _ => self.last_idle_info,
@@ -769,6 +966,9 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 reading_mode: self.last_idle_info.reading_mode,
                 time_to_break_secs: self.last_idle_info.time_to_break_secs,
                 break_length_secs: self.last_idle_info.break_length_secs,
+                prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
             _ => self.last_idle_info,
@@ -778,14 +978,27 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
     }
 
     pub fn postpone_break(&mut self, postpone_duration: Duration) -> IdleInfo {
+        // Never further away than a full interval
+        let postpone_duration = postpone_duration.clamp(
+            Duration::zero(),
+            Duration::seconds(self.last_idle_info.time_to_break_secs),
+        );
         let check_time = self.clock.get_time();
         let idle_since_seconds = self.idle_checker.get_idle_time_in_seconds();
 
-        self.last_idle_info = match self.last_idle_info.last_mode_state {
+        let idle_state = match self.last_idle_info.last_mode_state {
             ModeState::Break {
                 progress_towards_finish: _,
                 idle_state,
-            } => IdleInfo {
+            } => Some(idle_state),
+            // Still waiting for the user to let go of the keyboard, so they're active
+            ModeState::PreBreak { .. } => Some(DebouncedIdleState::Active {
+                active_since: check_time,
+            }),
+            ModeState::Normal { .. } => None,
+        };
+        self.last_idle_info = match idle_state {
+            Some(idle_state) => IdleInfo {
                 idle_since_seconds,
                 last_checked: check_time,
                 last_mode_state: ModeState::Normal {
@@ -799,13 +1012,26 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 reading_mode: self.last_idle_info.reading_mode,
                 time_to_break_secs: self.last_idle_info.time_to_break_secs,
                 break_length_secs: self.last_idle_info.break_length_secs,
+                prebreak_idle_secs: self.last_idle_info.prebreak_idle_secs,
+                postpone_count: self.last_idle_info.postpone_count + 1,
+                last_postpone: Some(postpone_duration),
                 overrun: self.last_idle_info.overrun,
             },
-            _ => self.last_idle_info,
+            None => self.last_idle_info,
         };
         self.last_idle_info
     }
 
+    // Like postponing, but the user didn't ask for it, so it doesn't count towards strict mode.
+    pub fn defer_break(&mut self, defer_duration: Duration) -> IdleInfo {
+        let postpone_count = self.last_idle_info.postpone_count;
+        let last_postpone = self.last_idle_info.last_postpone;
+        self.postpone_break(defer_duration);
+        self.last_idle_info.postpone_count = postpone_count;
+        self.last_idle_info.last_postpone = last_postpone;
+        self.last_idle_info
+    }
+
     pub fn set_time_to_break(&mut self, num_secs: i64) {
         self.last_idle_info.time_to_break_secs = num_secs;
         self.last_idle_info.last_mode_state = match self.last_idle_info.last_mode_state {
@@ -830,7 +1056,6 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 idle_state,
             },
         };
-        self.persist_settings_to_disk();
     }
 
     pub fn set_break_length(&mut self, num_secs: i64) {
@@ -857,30 +1082,16 @@ impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitor<T, U> {
                 idle_state,
             },
         };
-        self.persist_settings_to_disk();
     }
 
     pub fn export_persistable_state(&self) -> PersistableState {
         PersistableState {
-            progress_towards_break: match self.last_idle_info.last_mode_state {
-                ModeState::Normal {
-                    progress_towards_break,
-                    ..
-                } => progress_towards_break,
-                _ => Duration::seconds(0),
-            },
-            progress_towards_reset: match self.last_idle_info.last_mode_state {
-                ModeState::Normal {
-                    progress_towards_reset,
-                    ..
-                } => progress_towards_reset,
-                _ => Duration::seconds(0),
-            },
+            mode_state: self.last_idle_info.last_mode_state,
+            overrun: self.last_idle_info.overrun,
             last_checked: self.last_idle_info.last_checked,
             presence_mode: self.last_idle_info.presence_mode,
-            reading_mode: self.last_idle_info.reading_mode,
-            time_to_break_secs: self.last_idle_info.time_to_break_secs,
-            break_length_secs: self.last_idle_info.break_length_secs,
+            postpone_count: self.last_idle_info.postpone_count,
+            last_postpone: self.last_idle_info.last_postpone,
         }
     }
 }
@@ -911,7 +1122,7 @@ mod tests {
         let idle_checker = make_idle_checker(0);
         let clock = make_clock(&current_time);
 
-        let mut idle_monitor = IdleMonitor::new(idle_checker, clock, None);
+        let mut idle_monitor = IdleMonitor::new(idle_checker, clock, &Profile::default(), None);
         let expected_idle_info = IdleInfo {
             idle_since_seconds: 0,
             last_checked: current_time,
@@ -926,6 +1137,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -940,14 +1154,20 @@ mod tests {
         let mut idle_monitor = IdleMonitor::new(
             idle_checker,
             clock,
+            &Profile::default(),
             Some(PersistableState {
-                progress_towards_break: Duration::seconds(DEFAULT_TIME_TO_BREAK_SECS - 1),
-                progress_towards_reset: Duration::seconds(5),
+                mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::seconds(DEFAULT_TIME_TO_BREAK_SECS - 1),
+                    progress_towards_reset: Duration::seconds(5),
+                    idle_state: DebouncedIdleState::Active {
+                        active_since: current_time - Duration::minutes(10),
+                    },
+                },
+                overrun: Duration::seconds(12),
                 last_checked: current_time - Duration::seconds(DEFAULT_BREAK_LENGTH_SECS - 5 + 1),
                 presence_mode: PresenceMode::Muted,
-                reading_mode: false,
-                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
-                break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                postpone_count: 0,
+                last_postpone: None,
             }),
         );
         let expected_idle_info = IdleInfo {
@@ -964,6 +1184,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -978,14 +1201,25 @@ mod tests {
         let mut idle_monitor = IdleMonitor::new(
             idle_checker,
             clock,
+            &Profile {
+                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+                break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                reading_mode: true,
+                ..Profile::default()
+            },
             Some(PersistableState {
-                progress_towards_break: Duration::seconds(DEFAULT_TIME_TO_BREAK_SECS - 1),
-                progress_towards_reset: Duration::seconds(5),
+                mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::seconds(DEFAULT_TIME_TO_BREAK_SECS - 1),
+                    progress_towards_reset: Duration::seconds(5),
+                    idle_state: DebouncedIdleState::Active {
+                        active_since: current_time - Duration::minutes(10),
+                    },
+                },
+                overrun: Duration::seconds(12),
                 last_checked: current_time - Duration::seconds(DEFAULT_BREAK_LENGTH_SECS - 5 - 1),
                 presence_mode: PresenceMode::SnoozedUntil(current_time + Duration::minutes(30)),
-                reading_mode: true,
-                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
-                break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                postpone_count: 0,
+                last_postpone: None,
             }),
         );
         let expected_idle_info = IdleInfo {
@@ -1002,6 +1236,188 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::seconds(12),
+        };
+        assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
+    }
+
+    #[test]
+    fn initialized_with_restored_state_respects_configured_break_length() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(1);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor::new(
+            idle_checker,
+            clock,
+            &Profile {
+                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+                break_length_secs: 300,
+                reading_mode: false,
+                ..Profile::default()
+            },
+            Some(PersistableState {
+                mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::seconds(600),
+                    progress_towards_reset: Duration::seconds(0),
+                    idle_state: DebouncedIdleState::Active {
+                        active_since: current_time - Duration::minutes(10),
+                    },
+                },
+                overrun: Duration::seconds(0),
+                last_checked: current_time - Duration::seconds(200),
+                presence_mode: PresenceMode::Active,
+                postpone_count: 0,
+                last_postpone: None,
+            }),
+        );
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 1,
+            last_checked: current_time,
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(600),
+                progress_towards_reset: Duration::seconds(200),
+                idle_state: DebouncedIdleState::Idle {
+                    idle_since: current_time - Duration::seconds(200),
+                },
+            },
+            presence_mode: PresenceMode::Active,
+            reading_mode: false,
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: 300,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::seconds(0),
+        };
+        assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
+    }
+
+    #[test]
+    fn initialized_with_restored_prebreak() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(1);
+        let clock = make_clock(&current_time);
+        let started_at = current_time - Duration::seconds(40);
+
+        let mut idle_monitor = IdleMonitor::new(
+            idle_checker,
+            clock,
+            &Profile::default(),
+            Some(PersistableState {
+                mode_state: ModeState::PreBreak { started_at },
+                overrun: Duration::seconds(30),
+                last_checked: current_time - Duration::seconds(10),
+                presence_mode: PresenceMode::Active,
+                postpone_count: 0,
+                last_postpone: None,
+            }),
+        );
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 1,
+            last_checked: current_time,
+            last_mode_state: ModeState::PreBreak { started_at },
+            presence_mode: PresenceMode::Active,
+            reading_mode: false,
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::seconds(30),
+        };
+        assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
+    }
+
+    #[test]
+    fn initialized_with_restored_break_that_can_continue() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(20);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor::new(
+            idle_checker,
+            clock,
+            &Profile::default(),
+            Some(PersistableState {
+                mode_state: ModeState::Break {
+                    progress_towards_finish: Duration::seconds(30),
+                    idle_state: DebouncedIdleState::Idle {
+                        idle_since: current_time - Duration::seconds(60),
+                    },
+                },
+                overrun: Duration::seconds(45),
+                last_checked: current_time - Duration::seconds(20),
+                presence_mode: PresenceMode::Active,
+                postpone_count: 0,
+                last_postpone: None,
+            }),
+        );
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 20,
+            last_checked: current_time,
+            last_mode_state: ModeState::Break {
+                progress_towards_finish: Duration::seconds(50),
+                idle_state: DebouncedIdleState::Idle {
+                    idle_since: current_time - Duration::seconds(20),
+                },
+            },
+            presence_mode: PresenceMode::Active,
+            reading_mode: false,
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::seconds(45),
+        };
+        assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
+    }
+
+    #[test]
+    fn initialized_with_restored_break_that_finished_while_away() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(0);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor::new(
+            idle_checker,
+            clock,
+            &Profile::default(),
+            Some(PersistableState {
+                mode_state: ModeState::Break {
+                    progress_towards_finish: Duration::seconds(30),
+                    idle_state: DebouncedIdleState::Idle {
+                        idle_since: current_time - Duration::seconds(200),
+                    },
+                },
+                overrun: Duration::seconds(45),
+                last_checked: current_time - Duration::seconds(DEFAULT_BREAK_LENGTH_SECS - 30),
+                presence_mode: PresenceMode::Active,
+                postpone_count: 0,
+                last_postpone: None,
+            }),
+        );
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 0,
+            last_checked: current_time,
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(0),
+                progress_towards_reset: Duration::seconds(0),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: current_time,
+                },
+            },
+            presence_mode: PresenceMode::Active,
+            reading_mode: false,
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1016,6 +1432,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1009),
@@ -1030,6 +1447,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1047,6 +1467,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1061,6 +1484,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_009),
@@ -1075,6 +1499,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(18_000),
             },
         };
@@ -1092,6 +1519,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(19_009),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1106,6 +1536,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::seconds(FRAME_DROP_CUTOFF_POINT_SECS + 1),
@@ -1120,6 +1551,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1137,6 +1571,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1152,6 +1589,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::seconds(DEFAULT_TIME_TO_BREAK_SECS + 1),
@@ -1166,6 +1604,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1183,6 +1624,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1197,6 +1641,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1025),
@@ -1211,6 +1656,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1228,6 +1676,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1242,6 +1693,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 5,
                 last_checked: current_time - Duration::milliseconds(1999),
@@ -1256,6 +1708,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1274,6 +1729,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1288,6 +1746,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_999),
@@ -1303,6 +1762,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1321,6 +1783,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1335,6 +1800,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 2,
                 last_checked: current_time - Duration::milliseconds(1_000),
@@ -1350,6 +1816,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1367,6 +1836,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1381,6 +1853,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 1,
                 last_checked: current_time - Duration::milliseconds(1_123),
@@ -1396,6 +1869,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1413,6 +1889,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1427,6 +1906,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_000),
@@ -1441,6 +1921,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1459,6 +1942,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1473,6 +1959,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_000),
@@ -1487,6 +1974,9 @@ mod tests {
                 reading_mode: true,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1504,6 +1994,61 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::seconds(0),
+        };
+        assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
+    }
+
+    #[test]
+    fn active_dont_start_transition_to_idle_while_idle_inhibited() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(1);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor {
+            idle_checker,
+            clock,
+            idle_inhibited: true,
+            last_idle_info: IdleInfo {
+                idle_since_seconds: 0,
+                last_checked: current_time - Duration::milliseconds(1_000),
+                last_mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::milliseconds(8_000),
+                    progress_towards_reset: Duration::seconds(0),
+                    idle_state: DebouncedIdleState::Active {
+                        active_since: current_time - Duration::milliseconds(1_000),
+                    },
+                },
+                presence_mode: PresenceMode::Active,
+                reading_mode: false,
+                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+                break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
+                overrun: Duration::seconds(0),
+            },
+        };
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 1,
+            last_checked: current_time,
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::milliseconds(9_000),
+                progress_towards_reset: Duration::seconds(0),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: current_time - Duration::milliseconds(1_000),
+                },
+            },
+            presence_mode: PresenceMode::Active,
+            reading_mode: false,
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1518,6 +2063,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 1,
                 last_checked: current_time - Duration::milliseconds(1_001),
@@ -1533,6 +2079,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1551,6 +2100,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1565,6 +2117,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 3,
                 last_checked: current_time - Duration::milliseconds(1_002),
@@ -1580,6 +2133,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1597,6 +2153,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1611,6 +2170,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 3,
                 last_checked: current_time - Duration::milliseconds(1_005),
@@ -1626,6 +2186,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1643,6 +2206,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1657,6 +2223,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -1673,6 +2240,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 1,
+                last_postpone: Some(Duration::minutes(5)),
                 overrun: Duration::seconds(1),
             },
         };
@@ -1690,6 +2260,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1704,6 +2277,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -1718,6 +2292,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1736,6 +2313,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.snooze(resume_at_stamp), expected_idle_info);
@@ -1750,6 +2330,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 5,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -1764,6 +2345,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1781,6 +2365,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1795,6 +2382,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 5,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -1805,6 +2393,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1823,6 +2414,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         idle_monitor.mute();
@@ -1838,6 +2432,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 5,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -1852,6 +2447,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1869,6 +2467,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.unmute(), expected_idle_info);
@@ -1883,6 +2484,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 5,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -1897,6 +2499,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1914,6 +2519,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.unmute(), expected_idle_info);
@@ -1928,6 +2536,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 5,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -1942,6 +2551,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -1959,6 +2571,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -1973,6 +2588,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_009),
@@ -1989,6 +2605,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -2002,6 +2621,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2016,6 +2638,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_009),
@@ -2032,6 +2655,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -2049,6 +2675,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::seconds(0),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2063,6 +2692,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_009),
@@ -2077,6 +2707,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -2094,6 +2727,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(1_009),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2108,6 +2744,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2118,6 +2755,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::seconds(0),
             },
         };
@@ -2131,6 +2771,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(1_025),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2145,6 +2788,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 4,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2155,6 +2799,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(4_000),
             },
         };
@@ -2168,6 +2815,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(5_025),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2182,6 +2832,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 4,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2192,6 +2843,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(4_000),
             },
         };
@@ -2208,6 +2862,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(5_025),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2222,6 +2879,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 1,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2235,6 +2893,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(3_000),
             },
         };
@@ -2251,6 +2912,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(3_000),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2265,6 +2929,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2278,6 +2943,9 @@ mod tests {
                 reading_mode: true,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(3_000),
             },
         };
@@ -2295,6 +2963,9 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(4_025),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2309,6 +2980,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 1,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2322,6 +2994,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(3_000),
             },
         };
@@ -2338,6 +3013,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(4_025),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2352,6 +3030,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 27,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2367,6 +3046,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 1,
+                last_postpone: Some(Duration::minutes(5)),
                 overrun: Duration::milliseconds(3_000),
             },
         };
@@ -2384,6 +3066,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         assert_eq!(idle_monitor.refresh_idle_info(), expected_idle_info);
@@ -2398,6 +3083,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2412,6 +3098,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2429,6 +3118,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         assert_eq!(idle_monitor.trigger_break(), expected_idle_info);
@@ -2443,6 +3135,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2456,6 +3149,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2473,6 +3169,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         assert_eq!(idle_monitor.snooze(resume_at_stamp), expected_idle_info);
@@ -2487,6 +3186,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2500,6 +3200,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 2,
+                last_postpone: Some(Duration::minutes(5)),
                 overrun: Duration::milliseconds(8_000),
             },
         };
@@ -2517,6 +3220,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         assert_eq!(idle_monitor.skip_break(), expected_idle_info);
@@ -2531,6 +3237,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time - Duration::milliseconds(1_025),
@@ -2544,6 +3251,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 1,
+                last_postpone: Some(Duration::minutes(5)),
                 overrun: Duration::milliseconds(8_000),
             },
         };
@@ -2561,6 +3271,9 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 2,
+            last_postpone: Some(Duration::seconds(3 * 60)),
             overrun: Duration::milliseconds(8_000),
         };
         assert_eq!(
@@ -2569,6 +3282,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn postpone_break_from_prebreak() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(0);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor {
+            idle_checker,
+            clock,
+            idle_inhibited: false,
+            last_idle_info: IdleInfo {
+                idle_since_seconds: 0,
+                last_checked: current_time - Duration::milliseconds(1_025),
+                last_mode_state: ModeState::PreBreak {
+                    started_at: current_time - Duration::milliseconds(4_000),
+                },
+                presence_mode: PresenceMode::Active,
+                reading_mode: false,
+                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+                break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
+                overrun: Duration::milliseconds(4_000),
+            },
+        };
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 0,
+            last_checked: current_time,
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(DEFAULT_TIME_TO_BREAK_SECS - (5 * 60)),
+                progress_towards_reset: Duration::seconds(0),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: current_time,
+                },
+            },
+            presence_mode: PresenceMode::Active,
+            reading_mode: false,
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 1,
+            last_postpone: Some(Duration::minutes(5)),
+            overrun: Duration::milliseconds(4_000),
+        };
+        assert_eq!(
+            idle_monitor.postpone_break(Duration::seconds(5 * 60)),
+            expected_idle_info
+        );
+    }
+
+    #[test]
+    fn trigger_break_from_prebreak() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(0);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor {
+            idle_checker,
+            clock,
+            idle_inhibited: false,
+            last_idle_info: IdleInfo {
+                idle_since_seconds: 0,
+                last_checked: current_time,
+                last_mode_state: ModeState::PreBreak {
+                    started_at: current_time - Duration::milliseconds(4_000),
+                },
+                presence_mode: PresenceMode::Active,
+                reading_mode: false,
+                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+                break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
+                overrun: Duration::milliseconds(4_000),
+            },
+        };
+        assert_eq!(
+            idle_monitor.trigger_break().last_mode_state,
+            ModeState::Break {
+                progress_towards_finish: Duration::seconds(0),
+                idle_state: DebouncedIdleState::Idle {
+                    idle_since: current_time,
+                },
+            }
+        );
+    }
+
     #[test]
     fn set_reading_mode_normal_active() {
         let current_time = Utc::now();
@@ -2578,6 +3379,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2592,6 +3394,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2609,6 +3414,9 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         idle_monitor.set_reading_mode(true);
@@ -2624,6 +3432,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2638,6 +3447,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2655,12 +3467,68 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         idle_monitor.set_reading_mode(true);
         assert_eq!(idle_monitor.get_last_idle_info(), expected_idle_info);
     }
 
+    #[test]
+    fn set_idle_inhibited_normal_idle() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(0);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor {
+            idle_checker,
+            clock,
+            idle_inhibited: false,
+            last_idle_info: IdleInfo {
+                idle_since_seconds: 4,
+                last_checked: current_time,
+                last_mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::milliseconds(6_000),
+                    progress_towards_reset: Duration::milliseconds(2_000),
+                    idle_state: DebouncedIdleState::Idle {
+                        idle_since: current_time - Duration::milliseconds(2_000),
+                    },
+                },
+                presence_mode: PresenceMode::Active,
+                reading_mode: false,
+                time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+                break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
+                overrun: Duration::milliseconds(0_000),
+            },
+        };
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 4,
+            last_checked: current_time,
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::milliseconds(6_000),
+                progress_towards_reset: Duration::milliseconds(0_000),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: current_time,
+                },
+            },
+            presence_mode: PresenceMode::Active,
+            reading_mode: false,
+            time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
+            break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::milliseconds(0_000),
+        };
+        idle_monitor.set_idle_inhibited(true);
+        assert_eq!(idle_monitor.get_last_idle_info(), expected_idle_info);
+    }
+
     #[test]
     fn set_reading_mode_prebreak() {
         let current_time = Utc::now();
@@ -2670,6 +3538,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2680,6 +3549,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2693,6 +3565,9 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         idle_monitor.set_reading_mode(true);
@@ -2708,6 +3583,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2721,6 +3597,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2737,6 +3616,9 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         idle_monitor.set_reading_mode(true);
@@ -2752,6 +3634,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2765,6 +3648,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2781,6 +3667,9 @@ mod tests {
             reading_mode: true,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         idle_monitor.set_reading_mode(true);
@@ -2796,6 +3685,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2810,6 +3700,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2830,6 +3723,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: 600,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             }
         );
@@ -2844,6 +3740,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2858,6 +3755,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2878,6 +3778,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: 600,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             }
         );
@@ -2892,6 +3795,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2906,6 +3810,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2926,6 +3833,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: 600,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             }
         );
@@ -2940,6 +3850,7 @@ mod tests {
         let mut idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -2954,6 +3865,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -2974,6 +3888,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: 600,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             }
         );
@@ -2988,6 +3905,7 @@ mod tests {
         let idle_monitor = IdleMonitor {
             idle_checker,
             clock,
+            idle_inhibited: false,
             last_idle_info: IdleInfo {
                 idle_since_seconds: 0,
                 last_checked: current_time,
@@ -3002,6 +3920,9 @@ mod tests {
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
                 overrun: Duration::milliseconds(0_000),
             },
         };
@@ -3009,13 +3930,79 @@ mod tests {
             idle_monitor.export_persistable_state(),
             PersistableState {
                 last_checked: current_time,
-                progress_towards_break: Duration::milliseconds(6_000),
-                progress_towards_reset: Duration::milliseconds(2_000),
+                mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::milliseconds(6_000),
+                    progress_towards_reset: Duration::milliseconds(2_000),
+                    idle_state: DebouncedIdleState::Idle {
+                        idle_since: current_time - Duration::milliseconds(5_000),
+                    },
+                },
+                overrun: Duration::milliseconds(0_000),
+                presence_mode: PresenceMode::Active,
+                postpone_count: 0,
+                last_postpone: None,
+            }
+        );
+    }
+
+    #[test]
+    fn apply_profile_keeps_progress_within_new_limits() {
+        let current_time = Utc::now();
+        let idle_checker = make_idle_checker(0);
+        let clock = make_clock(&current_time);
+
+        let mut idle_monitor = IdleMonitor {
+            idle_checker,
+            clock,
+            idle_inhibited: false,
+            last_idle_info: IdleInfo {
+                idle_since_seconds: 0,
+                last_checked: current_time,
+                last_mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::seconds(600),
+                    progress_towards_reset: Duration::seconds(80),
+                    idle_state: DebouncedIdleState::Idle {
+                        idle_since: current_time - Duration::seconds(80),
+                    },
+                },
                 presence_mode: PresenceMode::Active,
                 reading_mode: false,
                 time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
                 break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
-            }
+                prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+                postpone_count: 0,
+                last_postpone: None,
+                overrun: Duration::seconds(0),
+            },
+        };
+        let expected_idle_info = IdleInfo {
+            idle_since_seconds: 0,
+            last_checked: current_time,
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(300),
+                progress_towards_reset: Duration::seconds(60),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: current_time,
+                },
+            },
+            presence_mode: PresenceMode::Active,
+            reading_mode: true,
+            time_to_break_secs: 300,
+            break_length_secs: 60,
+            prebreak_idle_secs: 10,
+            postpone_count: 0,
+            last_postpone: None,
+            overrun: Duration::seconds(0),
+        };
+        assert_eq!(
+            idle_monitor.apply_profile(&Profile {
+                time_to_break_secs: 300,
+                break_length_secs: 60,
+                reading_mode: true,
+                prebreak_idle_secs: 10,
+                ..Profile::default()
+            }),
+            expected_idle_info
         );
     }
 }
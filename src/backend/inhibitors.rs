@@ -0,0 +1,347 @@
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use zbus::blocking::Connection;
+use zbus::proxy;
+use zbus::zvariant::OwnedObjectPath;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// Flag used by org.gnome.SessionManager for inhibitors that keep the session from going idle.
+const GNOME_INHIBIT_IDLE: u32 = 8;
+
+// What, who, why, mode, uid and pid, as listed by logind.
+type LogindInhibitor = (String, String, String, String, u32, u32);
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait LoginManager {
+    fn list_inhibitors(&self) -> zbus::Result<Vec<LogindInhibitor>>;
+}
+
+#[proxy(
+    interface = "org.gnome.SessionManager",
+    default_service = "org.gnome.SessionManager",
+    default_path = "/org/gnome/SessionManager"
+)]
+trait GnomeSessionManager {
+    fn get_inhibitors(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.gnome.SessionManager.Inhibitor",
+    default_service = "org.gnome.SessionManager"
+)]
+trait GnomeInhibitor {
+    fn get_app_id(&self) -> zbus::Result<String>;
+    fn get_reason(&self) -> zbus::Result<String>;
+    fn get_flags(&self) -> zbus::Result<u32>;
+}
+
+#[proxy(
+    interface = "org.kde.Solid.PowerManagement.PolicyAgent",
+    default_service = "org.kde.Solid.PowerManagement",
+    default_path = "/org/kde/Solid/PowerManagement/PolicyAgent"
+)]
+trait KdePolicyAgent {
+    // App name and reason
+    fn list_inhibitions(&self) -> zbus::Result<Vec<(String, String)>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.PowerManagement.Inhibit",
+    default_service = "org.freedesktop.PowerManagement",
+    default_path = "/org/freedesktop/PowerManagement/Inhibit"
+)]
+trait PowerManagementInhibit {
+    // App names only
+    fn get_inhibitors(&self) -> zbus::Result<Vec<String>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inhibitor {
+    pub app: String,
+    pub reason: String,
+}
+
+fn get_logind_inhibitors(connection: &Connection, uid: u32) -> zbus::Result<Vec<Inhibitor>> {
+    Ok(filter_logind_inhibitors(
+        LoginManagerProxyBlocking::new(connection)?.list_inhibitors()?,
+        uid,
+    ))
+}
+
+// Other users' inhibitors and those that merely delay something don't say anything about
+// what this user is doing.
+fn filter_logind_inhibitors(raw_inhibitors: Vec<LogindInhibitor>, uid: u32) -> Vec<Inhibitor> {
+    raw_inhibitors
+        .into_iter()
+        .filter(|(what, _, _, mode, inhibitor_uid, _)| {
+            what.split(':').any(|what| what == "idle") && mode == "block" && *inhibitor_uid == uid
+        })
+        .map(|(_, who, why, ..)| Inhibitor {
+            app: who,
+            reason: why,
+        })
+        .collect()
+}
+
+// org.freedesktop.ScreenSaver inhibitors end up here on GNOME, which is what browsers and most
+// video players use.
+fn get_gnome_inhibitors(connection: &Connection) -> zbus::Result<Vec<Inhibitor>> {
+    // An inhibitor can go away between listing and asking about it, which says nothing
+    // about the others.
+    Ok(GnomeSessionManagerProxyBlocking::new(connection)?
+        .get_inhibitors()?
+        .into_iter()
+        .filter_map(|path| get_gnome_inhibitor(connection, path).ok().flatten())
+        .collect())
+}
+
+fn get_gnome_inhibitor(
+    connection: &Connection,
+    path: OwnedObjectPath,
+) -> zbus::Result<Option<Inhibitor>> {
+    let inhibitor = GnomeInhibitorProxyBlocking::builder(connection)
+        .path(path)?
+        .build()?;
+    if inhibitor.get_flags()? & GNOME_INHIBIT_IDLE == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Inhibitor {
+        app: inhibitor.get_app_id()?,
+        reason: inhibitor.get_reason()?,
+    }))
+}
+
+// Where org.freedesktop.ScreenSaver inhibitors end up on KDE Plasma.
+fn get_kde_inhibitors(connection: &Connection) -> zbus::Result<Vec<Inhibitor>> {
+    Ok(KdePolicyAgentProxyBlocking::new(connection)?
+        .list_inhibitions()?
+        .into_iter()
+        .map(|(app, reason)| Inhibitor { app, reason })
+        .collect())
+}
+
+// Where they end up on Xfce and other desktops that use xfce4-power-manager, which doesn't
+// keep the reason.
+fn get_power_management_inhibitors(connection: &Connection) -> zbus::Result<Vec<Inhibitor>> {
+    Ok(PowerManagementInhibitProxyBlocking::new(connection)?
+        .get_inhibitors()?
+        .into_iter()
+        .map(|app| Inhibitor {
+            app,
+            reason: String::new(),
+        })
+        .collect())
+}
+
+fn get_connection(
+    slot: &mut Option<Connection>,
+    connect: fn() -> zbus::Result<Connection>,
+) -> Option<&Connection> {
+    if slot.is_none() {
+        *slot = connect().ok();
+    }
+    slot.as_ref()
+}
+
+// Polls in the background and publishes the idle inhibitors currently held in this session.
+pub fn watch_idle_inhibitors() -> watch::Receiver<Vec<Inhibitor>> {
+    let (inhibitor_sender, inhibitor_receiver) = watch::channel(Vec::new());
+    thread::spawn(move || {
+        let uid = fs::metadata("/proc/self")
+            .map(|metadata| metadata.uid())
+            .ok();
+        let mut system_connection = None;
+        let mut session_connection = None;
+        while !inhibitor_sender.is_closed() {
+            let mut inhibitors = Vec::new();
+            // Not every desktop runs both services, so a missing one is not worth reporting.
+            if let Some(uid) = uid
+                && let Some(connection) = get_connection(&mut system_connection, Connection::system)
+            {
+                inhibitors.extend(get_logind_inhibitors(connection, uid).unwrap_or_default());
+            }
+            if let Some(connection) = get_connection(&mut session_connection, Connection::session) {
+                inhibitors.extend(get_gnome_inhibitors(connection).unwrap_or_default());
+                inhibitors.extend(get_kde_inhibitors(connection).unwrap_or_default());
+                inhibitors.extend(get_power_management_inhibitors(connection).unwrap_or_default());
+            }
+            inhibitor_sender.send_if_modified(|current_inhibitors| {
+                let changed = *current_inhibitors != inhibitors;
+                *current_inhibitors = inhibitors;
+                changed
+            });
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+    inhibitor_receiver
+}
+
+// Several inhibitors may belong to the same app, e.g. one per browser tab.
+pub fn describe_inhibiting_apps(inhibitors: &[Inhibitor]) -> String {
+    let mut apps: Vec<&str> = Vec::new();
+    for inhibitor in inhibitors {
+        if !apps.contains(&inhibitor.app.as_str()) {
+            apps.push(&inhibitor.app);
+        }
+    }
+    apps.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::interface;
+    use zbus::zvariant::ObjectPath;
+
+    use super::*;
+    use crate::backend::test_bus::PrivateBus;
+
+    struct FakeGnomeSessionManager;
+
+    #[interface(name = "org.gnome.SessionManager")]
+    impl FakeGnomeSessionManager {
+        fn get_inhibitors(&self) -> Vec<OwnedObjectPath> {
+            [
+                "/org/gnome/SessionManager/Inhibitor1",
+                "/org/gnome/SessionManager/Inhibitor2",
+            ]
+            .into_iter()
+            .map(|path| ObjectPath::try_from(path).unwrap().into())
+            .collect()
+        }
+    }
+
+    struct FakeGnomeInhibitor;
+
+    #[interface(name = "org.gnome.SessionManager.Inhibitor")]
+    impl FakeGnomeInhibitor {
+        fn get_app_id(&self) -> String {
+            String::from("firefox")
+        }
+
+        fn get_reason(&self) -> String {
+            String::from("Playing video")
+        }
+
+        fn get_flags(&self) -> u32 {
+            GNOME_INHIBIT_IDLE
+        }
+    }
+
+    struct FakeKdePolicyAgent;
+
+    #[interface(name = "org.kde.Solid.PowerManagement.PolicyAgent")]
+    impl FakeKdePolicyAgent {
+        fn list_inhibitions(&self) -> Vec<(String, String)> {
+            vec![(String::from("vlc"), String::from("Playing video"))]
+        }
+    }
+
+    fn make_raw_inhibitor(what: &str, who: &str, mode: &str, uid: u32) -> LogindInhibitor {
+        (
+            what.to_string(),
+            who.to_string(),
+            String::from("Playing video"),
+            mode.to_string(),
+            uid,
+            4242,
+        )
+    }
+
+    #[test]
+    fn keeps_only_blocking_idle_inhibitors_of_this_user() {
+        let inhibitors = filter_logind_inhibitors(
+            vec![
+                make_raw_inhibitor("idle:sleep", "mpv", "block", 1000),
+                make_raw_inhibitor("sleep", "NetworkManager", "delay", 0),
+                make_raw_inhibitor("handle-lid-switch", "GNOME Shell", "block", 1000),
+                make_raw_inhibitor("idle", "Totem", "delay", 1000),
+                make_raw_inhibitor("idle", "VLC", "block", 1001),
+            ],
+            1000,
+        );
+        assert_eq!(
+            inhibitors,
+            vec![Inhibitor {
+                app: String::from("mpv"),
+                reason: String::from("Playing video"),
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_gnome_inhibitors_that_are_gone() {
+        let bus = PrivateBus::start();
+        // The first inhibitor was released after being listed.
+        let _session_manager_connection = bus
+            .connect()
+            .name("org.gnome.SessionManager")
+            .unwrap()
+            .serve_at("/org/gnome/SessionManager", FakeGnomeSessionManager)
+            .unwrap()
+            .serve_at("/org/gnome/SessionManager/Inhibitor2", FakeGnomeInhibitor)
+            .unwrap()
+            .build()
+            .unwrap();
+        let connection = bus.connect().build().unwrap();
+
+        assert_eq!(
+            get_gnome_inhibitors(&connection).unwrap(),
+            vec![Inhibitor {
+                app: String::from("firefox"),
+                reason: String::from("Playing video"),
+            }]
+        );
+    }
+
+    #[test]
+    fn reads_kde_inhibitors() {
+        let bus = PrivateBus::start();
+        let _policy_agent_connection = bus
+            .connect()
+            .name("org.kde.Solid.PowerManagement")
+            .unwrap()
+            .serve_at(
+                "/org/kde/Solid/PowerManagement/PolicyAgent",
+                FakeKdePolicyAgent,
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let connection = bus.connect().build().unwrap();
+
+        assert_eq!(
+            get_kde_inhibitors(&connection).unwrap(),
+            vec![Inhibitor {
+                app: String::from("vlc"),
+                reason: String::from("Playing video"),
+            }]
+        );
+        assert!(get_power_management_inhibitors(&connection).is_err());
+    }
+
+    #[test]
+    fn describes_each_app_once() {
+        let make_inhibitor = |app: &str| Inhibitor {
+            app: app.to_string(),
+            reason: String::new(),
+        };
+        assert_eq!(
+            describe_inhibiting_apps(&[
+                make_inhibitor("firefox"),
+                make_inhibitor("mpv"),
+                make_inhibitor("firefox"),
+            ]),
+            "firefox, mpv"
+        );
+        assert_eq!(describe_inhibiting_apps(&[]), "");
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use zbus::blocking::{Connection, fdo::DBusProxy};
+use zbus::names::OwnedBusName;
+use zbus::proxy;
+use zbus::proxy::CacheProperties;
+
+const MPRIS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait MediaPlayer {
+    fn pause(&self) -> zbus::Result<()>;
+    fn play(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+}
+
+fn make_player_proxy<'a>(
+    connection: &Connection,
+    name: &'a OwnedBusName,
+) -> zbus::Result<MediaPlayerProxyBlocking<'a>> {
+    MediaPlayerProxyBlocking::builder(connection)
+        .destination(name)?
+        .cache_properties(CacheProperties::No)
+        .build()
+}
+
+// Returns the players that were paused, so exactly those can be resumed later.
+pub fn pause_playing_players(connection: &Connection) -> zbus::Result<Vec<OwnedBusName>> {
+    let mut paused_players = Vec::new();
+    for name in DBusProxy::new(connection)?.list_names()? {
+        if !name.starts_with(MPRIS_NAME_PREFIX) {
+            continue;
+        }
+        // One misbehaving player shouldn't keep the others from being paused.
+        let pause_result = make_player_proxy(connection, &name).and_then(|player| {
+            if player.playback_status()? != "Playing" {
+                return Ok(false);
+            }
+            player.pause().map(|_| true)
+        });
+        match pause_result {
+            Ok(true) => paused_players.push(name),
+            Ok(false) => {}
+            Err(error) => println!("Could not pause {}: {}", name, error),
+        }
+    }
+    Ok(paused_players)
+}
+
+pub fn resume_players(connection: &Connection, players: &[OwnedBusName]) {
+    for name in players {
+        // Players that quit during the break are simply gone.
+        if let Err(error) = make_player_proxy(connection, name).and_then(|player| player.play()) {
+            println!("Could not resume {}: {}", name, error);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakEvent {
+    Started,
+    Completed,
+    Abandoned,
+}
+
+// Talks to media players from its own thread, so a slow player can't hold up idle monitoring.
+#[derive(Clone)]
+pub struct MediaPlayerPauser {
+    event_sender: Sender<BreakEvent>,
+}
+
+impl MediaPlayerPauser {
+    pub fn spawn() -> Self {
+        let (event_sender, event_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut connection: Option<Connection> = None;
+            let mut paused_players = Vec::new();
+            for event in event_receiver {
+                match event {
+                    BreakEvent::Started => {
+                        if connection.is_none() {
+                            connection = Connection::session()
+                                .inspect_err(|error| {
+                                    println!("Could not connect to the session bus: {}", error)
+                                })
+                                .ok();
+                        }
+                        if let Some(ref connection) = connection {
+                            paused_players = pause_playing_players(connection)
+                                .inspect_err(|error| {
+                                    println!("Could not list media players: {}", error)
+                                })
+                                .unwrap_or_default();
+                        }
+                    }
+                    BreakEvent::Completed => {
+                        if let Some(ref connection) = connection {
+                            resume_players(connection, &paused_players);
+                        }
+                        paused_players.clear();
+                    }
+                    // Skipped or postponed. The user is back at the keyboard and can decide.
+                    BreakEvent::Abandoned => paused_players.clear(),
+                }
+            }
+        });
+        MediaPlayerPauser { event_sender }
+    }
+
+    fn send(&self, event: BreakEvent) {
+        if self.event_sender.send(event).is_err() {
+            println!("Media player thread is no longer running");
+        }
+    }
+
+    pub fn break_started(&self) {
+        self.send(BreakEvent::Started);
+    }
+
+    pub fn break_completed(&self) {
+        self.send(BreakEvent::Completed);
+    }
+
+    pub fn break_abandoned(&self) {
+        self.send(BreakEvent::Abandoned);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use zbus::interface;
+
+    use super::*;
+    use crate::backend::test_bus::PrivateBus;
+
+    struct FakePlayer {
+        playback_status: Arc<Mutex<String>>,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        fn pause(&self) {
+            *self.playback_status.lock().unwrap() = String::from("Paused");
+        }
+
+        fn play(&self) {
+            *self.playback_status.lock().unwrap() = String::from("Playing");
+        }
+
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.playback_status.lock().unwrap().clone()
+        }
+    }
+
+    fn start_fake_player(
+        bus: &PrivateBus,
+        name: &str,
+        playback_status: &str,
+    ) -> (Connection, Arc<Mutex<String>>) {
+        let playback_status = Arc::new(Mutex::new(String::from(playback_status)));
+        let connection = bus
+            .connect()
+            .name(name)
+            .unwrap()
+            .serve_at(
+                "/org/mpris/MediaPlayer2",
+                FakePlayer {
+                    playback_status: playback_status.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        (connection, playback_status)
+    }
+
+    #[test]
+    fn pauses_and_resumes_only_playing_players() {
+        let bus = PrivateBus::start();
+        let (_music_connection, music_status) =
+            start_fake_player(&bus, "org.mpris.MediaPlayer2.music", "Playing");
+        let (_podcast_connection, podcast_status) =
+            start_fake_player(&bus, "org.mpris.MediaPlayer2.podcast", "Paused");
+        let connection = bus.connect().build().unwrap();
+
+        let paused_players = pause_playing_players(&connection).unwrap();
+        assert_eq!(
+            paused_players
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["org.mpris.MediaPlayer2.music"]
+        );
+        assert_eq!(*music_status.lock().unwrap(), "Paused");
+
+        resume_players(&connection, &paused_players);
+        assert_eq!(*music_status.lock().unwrap(), "Playing");
+        assert_eq!(*podcast_status.lock().unwrap(), "Paused");
+    }
+
+    #[test]
+    fn resuming_a_player_that_quit_is_harmless() {
+        let bus = PrivateBus::start();
+        let (music_connection, _music_status) =
+            start_fake_player(&bus, "org.mpris.MediaPlayer2.music", "Playing");
+        let connection = bus.connect().build().unwrap();
+
+        let paused_players = pause_playing_players(&connection).unwrap();
+        drop(music_connection);
+
+        resume_players(&connection, &paused_players);
+    }
+}
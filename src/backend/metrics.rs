@@ -0,0 +1,283 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Local, NaiveDate};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+use tokio::time::{MissedTickBehavior, interval};
+
+use crate::backend::break_events::{BreakEvent, BreakEventKind};
+use crate::backend::file_io::write_atomically;
+use crate::backend::history::BreakOutcome;
+use crate::backend::idle_monitoring::{IdleInfo, ModeState};
+
+// Gaps longer than this, such as a suspended laptop, don't count as time at the computer.
+const MAX_ACTIVE_STEP_SECS: i64 = 10;
+
+// Break adherence in the Prometheus text format, as read by node_exporter's textfile
+// collector and by Prometheus itself.
+#[derive(Debug, Default)]
+pub struct BreakMetrics {
+    last_idle_info: Option<IdleInfo>,
+    // Since Stretch Break started. Prometheus copes with counters that reset.
+    breaks_completed: u64,
+    breaks_skipped: u64,
+    breaks_postponed: u64,
+    breaks_postponed_for_meetings: u64,
+    active_day: Option<NaiveDate>,
+    active_secs_today: i64,
+}
+
+fn is_user_active(idle_info: &IdleInfo) -> bool {
+    match idle_info.last_mode_state {
+        ModeState::Normal { idle_state, .. } | ModeState::Break { idle_state, .. } => {
+            idle_state.is_user_active()
+        }
+        ModeState::PreBreak { .. } => true,
+    }
+}
+
+impl BreakMetrics {
+    pub fn record_idle_info(&mut self, idle_info: &IdleInfo) {
+        let day = idle_info.last_checked.with_timezone(&Local).date_naive();
+        if self.active_day != Some(day) {
+            self.active_day = Some(day);
+            self.active_secs_today = 0;
+        }
+        if let Some(previous) = self.last_idle_info
+            && is_user_active(idle_info)
+        {
+            let step = idle_info.last_checked - previous.last_checked;
+            if step > Duration::zero() && step.num_seconds() <= MAX_ACTIVE_STEP_SECS {
+                self.active_secs_today += step.num_seconds();
+            }
+        }
+        self.last_idle_info = Some(*idle_info);
+    }
+
+    pub fn record_event(&mut self, event: &BreakEvent) {
+        match event.kind {
+            BreakEventKind::BreakEnded(BreakOutcome::Completed) => self.breaks_completed += 1,
+            BreakEventKind::BreakEnded(BreakOutcome::Skipped) => self.breaks_skipped += 1,
+            BreakEventKind::BreakEnded(BreakOutcome::Postponed { .. }) => {
+                self.breaks_postponed += 1
+            }
+            BreakEventKind::BreakEnded(BreakOutcome::PostponedForMeeting { .. }) => {
+                self.breaks_postponed_for_meetings += 1
+            }
+            BreakEventKind::PreBreak
+            | BreakEventKind::BreakStarted
+            | BreakEventKind::PresenceChanged => {}
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, i64)>| {
+            writeln!(text, "# HELP {} {}", name, help).unwrap();
+            writeln!(text, "# TYPE {} {}", name, kind).unwrap();
+            for (labels, value) in samples {
+                writeln!(text, "{}{} {}", name, labels, value).unwrap();
+            }
+        };
+        let unlabeled = |value| vec![(String::new(), value)];
+        let labeled = |label: &str, values: &[(&str, i64)]| {
+            values
+                .iter()
+                .map(|(option, value)| (format!("{{{}=\"{}\"}}", label, option), *value))
+                .collect()
+        };
+        // 1 for the current option, 0 for the others
+        let one_of = |label: &str, current: &str, options: [&str; 3]| {
+            labeled(
+                label,
+                &options.map(|option| (option, (option == current) as i64)),
+            )
+        };
+
+        metric(
+            "stretch_break_breaks_total",
+            "counter",
+            "Breaks by how they ended.",
+            labeled(
+                "outcome",
+                &[
+                    ("completed", self.breaks_completed as i64),
+                    ("skipped", self.breaks_skipped as i64),
+                    ("postponed", self.breaks_postponed as i64),
+                    (
+                        "postponed_for_meeting",
+                        self.breaks_postponed_for_meetings as i64,
+                    ),
+                ],
+            ),
+        );
+        metric(
+            "stretch_break_active_seconds_today",
+            "gauge",
+            "Time spent at the computer since midnight or since Stretch Break started, if later.",
+            unlabeled(self.active_secs_today),
+        );
+        let Some(idle_info) = self.last_idle_info else {
+            return text;
+        };
+        metric(
+            "stretch_break_seconds_until_break",
+            "gauge",
+            "Time left until the next break is due.",
+            unlabeled(idle_info.secs_until_break()),
+        );
+        metric(
+            "stretch_break_overrun_seconds",
+            "gauge",
+            "How long the due break has been put off.",
+            unlabeled(idle_info.overrun.num_seconds()),
+        );
+        metric(
+            "stretch_break_presence",
+            "gauge",
+            "Whether breaks are on, snoozed or muted.",
+            one_of(
+                "presence",
+                idle_info.presence_name(),
+                ["active", "snoozed", "muted"],
+            ),
+        );
+        metric(
+            "stretch_break_mode",
+            "gauge",
+            "Where the timer is at.",
+            one_of(
+                "mode",
+                idle_info.mode_name(),
+                ["normal", "pre_break", "break"],
+            ),
+        );
+        text
+    }
+}
+
+// Keeps the metrics up to date, writing them to the textfile, if any, and handing them to
+// the HTTP endpoint every interval_secs.
+#[tokio::main]
+pub async fn run_metrics_forever(
+    textfile: Option<PathBuf>,
+    interval_secs: u64,
+    mut idle_info_recv: watch::Receiver<IdleInfo>,
+    mut break_events: broadcast::Receiver<BreakEvent>,
+    rendered: watch::Sender<String>,
+) {
+    let mut metrics = BreakMetrics::default();
+    let mut ticks = interval(StdDuration::from_secs(interval_secs.max(1)));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            changed = idle_info_recv.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                metrics.record_idle_info(&idle_info_recv.borrow_and_update());
+            }
+            event = break_events.recv() => match event {
+                Ok(event) => metrics.record_event(&event),
+                Err(RecvError::Lagged(skipped)) => println!("Metrics missed {} events", skipped),
+                Err(RecvError::Closed) => return,
+            },
+            _ = ticks.tick() => {
+                let text = metrics.render();
+                if let Some(path) = &textfile
+                    && let Err(error) = write_atomically(path, text.as_bytes())
+                {
+                    println!("Could not write metrics to {}: {}", path.display(), error);
+                }
+                rendered.send_replace(text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::*;
+    use crate::backend::idle_monitoring::{DebouncedIdleState, PresenceMode};
+
+    fn make_idle_info(last_checked: DateTime<Utc>, idle_state: DebouncedIdleState) -> IdleInfo {
+        IdleInfo {
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(900),
+                progress_towards_reset: Duration::zero(),
+                idle_state,
+            },
+            presence_mode: PresenceMode::Muted,
+            overrun: Duration::seconds(12),
+            ..IdleInfo::for_tests(last_checked)
+        }
+    }
+
+    #[test]
+    fn counts_time_at_the_computer() {
+        let start = Local
+            .with_ymd_and_hms(2025, 2, 3, 12, 0, 0)
+            .unwrap()
+            .to_utc();
+        let active = DebouncedIdleState::Active {
+            active_since: start,
+        };
+        let idle = DebouncedIdleState::Idle { idle_since: start };
+        let mut metrics = BreakMetrics::default();
+
+        for (secs, idle_state) in [(0, active), (1, active), (2, active), (3, idle), (4, idle)] {
+            metrics.record_idle_info(&make_idle_info(start + Duration::seconds(secs), idle_state));
+        }
+        assert_eq!(metrics.active_secs_today, 2);
+
+        // Waking up from suspend
+        metrics.record_idle_info(&make_idle_info(start + Duration::hours(1), active));
+        assert_eq!(metrics.active_secs_today, 2);
+
+        let next_day = Local
+            .with_ymd_and_hms(2025, 2, 4, 9, 0, 0)
+            .unwrap()
+            .to_utc();
+        metrics.record_idle_info(&make_idle_info(next_day, active));
+        metrics.record_idle_info(&make_idle_info(next_day + Duration::seconds(1), active));
+        assert_eq!(metrics.active_secs_today, 1);
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let now = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        let mut metrics = BreakMetrics::default();
+        let idle_info = make_idle_info(now, DebouncedIdleState::Active { active_since: now });
+        metrics.record_idle_info(&idle_info);
+        for outcome in [
+            BreakOutcome::Completed,
+            BreakOutcome::Completed,
+            BreakOutcome::Skipped,
+        ] {
+            metrics.record_event(&BreakEvent {
+                kind: BreakEventKind::BreakEnded(outcome),
+                idle_info,
+            });
+        }
+
+        let text = metrics.render();
+        for line in [
+            "# TYPE stretch_break_breaks_total counter",
+            r#"stretch_break_breaks_total{outcome="completed"} 2"#,
+            r#"stretch_break_breaks_total{outcome="skipped"} 1"#,
+            r#"stretch_break_breaks_total{outcome="postponed"} 0"#,
+            r#"stretch_break_breaks_total{outcome="postponed_for_meeting"} 0"#,
+            "stretch_break_seconds_until_break 300",
+            "stretch_break_overrun_seconds 12",
+            r#"stretch_break_presence{presence="muted"} 1"#,
+            r#"stretch_break_presence{presence="active"} 0"#,
+            r#"stretch_break_mode{mode="normal"} 1"#,
+        ] {
+            assert!(text.lines().any(|other| other == line), "missing {}", line);
+        }
+    }
+}
@@ -1,2 +1,21 @@
+pub mod activitywatch;
+pub mod break_events;
+pub mod break_warnings;
+pub mod breathing;
+pub mod calendar;
+pub mod config;
+pub mod exercises;
 pub mod file_io;
+pub mod history;
+pub mod hooks;
 pub mod idle_monitoring;
+pub mod inhibitors;
+pub mod media_players;
+pub mod metrics;
+pub mod monitor_actor;
+pub mod notifications;
+pub mod policy;
+pub mod screen_lock;
+pub mod sounds;
+#[cfg(test)]
+pub mod test_bus;
@@ -0,0 +1,1294 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::{broadcast, oneshot, watch};
+
+use crate::backend::break_events::{BreakEvent, BreakEventKind, state_changes};
+use crate::backend::break_warnings::{BreakWarner, BreakWarning};
+use crate::backend::calendar::{CalendarWatcher, Meeting};
+use crate::backend::config::{BreakContent, BreakStyle, Config, is_strict_mode_exit_phrase};
+use crate::backend::exercises::ExerciseCategory;
+use crate::backend::history::{BreakHistory, BreakOutcome};
+use crate::backend::idle_monitoring::{
+    AbstractClock, AbstractIdleChecker, IdleInfo, IdleMonitor, ModeState, PresenceMode,
+};
+use crate::backend::policy::BreakPolicy;
+use crate::backend::sounds::SoundEvent;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdleMonitorCommand {
+    Mute,
+    Unmute,
+    Snooze(DateTime<Utc>),
+    SetReadingMode(bool),
+    TriggerBreak,
+    SkipBreak,
+    PostponeBreak(Duration),
+    SetTimeToBreak(i64),
+    SetBreakLength(i64),
+    UseProfile(String),
+    SetPauseMediaDuringBreaks(bool),
+    SetLockScreenDuringBreaks(bool),
+    SetBreakStyle(BreakStyle),
+    SetBreakContent(BreakContent),
+    SetExerciseCategories(Vec<ExerciseCategory>),
+    SetPostponeMinutes(Vec<i64>),
+    SetBreakWarningMinutes(Vec<i64>),
+    SetRespectIdleInhibitors(bool),
+    SetSoundEvents(Vec<SoundEvent>),
+    SetSoundVolume(u32),
+    // Leaving strict mode takes the exit phrase. Entering it doesn't.
+    SetStrictMode { enabled: bool, confirmation: String },
+}
+
+#[derive(Debug)]
+pub enum IdleMonitorRequest {
+    Command {
+        command: IdleMonitorCommand,
+        reply: Option<oneshot::Sender<Result<IdleInfo, IdleMonitorError>>>,
+    },
+    GetLastIdleInfo {
+        reply: oneshot::Sender<IdleInfo>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdleMonitorError {
+    Gone,
+    UnknownProfile(String),
+    SkipNotAllowed,
+    NoPostponesLeft,
+    InvalidPostpone,
+    WrongConfirmation,
+}
+
+impl fmt::Display for IdleMonitorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdleMonitorError::Gone => write!(f, "idle monitor is no longer running"),
+            IdleMonitorError::UnknownProfile(name) => write!(f, "no profile named {:?}", name),
+            IdleMonitorError::SkipNotAllowed => {
+                write!(
+                    f,
+                    "breaks can't be skipped, muted or snoozed in strict mode"
+                )
+            }
+            IdleMonitorError::NoPostponesLeft => {
+                write!(f, "this break has been postponed too often already")
+            }
+            IdleMonitorError::InvalidPostpone => {
+                write!(f, "breaks can only be postponed by more than zero minutes")
+            }
+            IdleMonitorError::WrongConfirmation => write!(
+                f,
+                "type the confirmation phrase exactly to leave strict mode"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IdleMonitorError {}
+
+/// Cheap to clone. Every clone talks to the same actor, which is the only
+/// place that ever touches the `IdleMonitor`.
+#[derive(Debug, Clone)]
+pub struct IdleMonitorHandle {
+    request_sender: Sender<IdleMonitorRequest>,
+}
+
+impl IdleMonitorHandle {
+    /// Fire and forget. Safe to call from GTK callbacks because it never blocks.
+    pub fn send(&self, command: IdleMonitorCommand) {
+        let request = IdleMonitorRequest::Command {
+            command,
+            reply: None,
+        };
+        if let Err(mpsc::SendError(request)) = self.request_sender.send(request) {
+            println!("Dropped {:?}: {}", request, IdleMonitorError::Gone);
+        }
+    }
+
+    pub async fn request(&self, command: IdleMonitorCommand) -> Result<IdleInfo, IdleMonitorError> {
+        let (reply, response) = oneshot::channel();
+        self.request_sender
+            .send(IdleMonitorRequest::Command {
+                command,
+                reply: Some(reply),
+            })
+            .map_err(|_| IdleMonitorError::Gone)?;
+        response.await.map_err(|_| IdleMonitorError::Gone)?
+    }
+
+    pub async fn get_last_idle_info(&self) -> Result<IdleInfo, IdleMonitorError> {
+        let (reply, response) = oneshot::channel();
+        self.request_sender
+            .send(IdleMonitorRequest::GetLastIdleInfo { reply })
+            .map_err(|_| IdleMonitorError::Gone)?;
+        response.await.map_err(|_| IdleMonitorError::Gone)
+    }
+}
+
+pub struct IdleMonitorActor<T: AbstractIdleChecker, U: AbstractClock> {
+    idle_monitor: IdleMonitor<T, U>,
+    request_receiver: Receiver<IdleMonitorRequest>,
+    idle_info_sender: watch::Sender<IdleInfo>,
+    config: Config,
+    // None keeps settings changes in memory only.
+    config_path: Option<PathBuf>,
+    config_sender: watch::Sender<Config>,
+    calendar_watcher: Option<CalendarWatcher>,
+    current_meeting: Option<Meeting>,
+    // Whether the current meeting's snooze is ours rather than a snooze or mute the user chose
+    snoozed_for_meeting: bool,
+    break_due_after_meeting: bool,
+    history: BreakHistory,
+    break_warner: BreakWarner,
+    break_warning_sender: broadcast::Sender<BreakWarning>,
+    break_event_sender: broadcast::Sender<BreakEvent>,
+    policy: BreakPolicy,
+}
+
+impl<T: AbstractIdleChecker, U: AbstractClock> IdleMonitorActor<T, U> {
+    pub fn new(
+        idle_monitor: IdleMonitor<T, U>,
+        idle_info_sender: watch::Sender<IdleInfo>,
+        config: Config,
+        config_path: Option<PathBuf>,
+        config_sender: watch::Sender<Config>,
+        history: BreakHistory,
+        policy: BreakPolicy,
+    ) -> (Self, IdleMonitorHandle) {
+        let (request_sender, request_receiver) = mpsc::channel();
+        (
+            Self {
+                idle_monitor,
+                request_receiver,
+                idle_info_sender,
+                calendar_watcher: make_calendar_watcher(&config),
+                config,
+                config_path,
+                config_sender,
+                current_meeting: None,
+                snoozed_for_meeting: false,
+                break_due_after_meeting: false,
+                history,
+                break_warner: BreakWarner::default(),
+                break_warning_sender: broadcast::channel(8).0,
+                break_event_sender: broadcast::channel(16).0,
+                policy,
+            },
+            IdleMonitorHandle { request_sender },
+        )
+    }
+
+    pub fn idle_monitor(&self) -> &IdleMonitor<T, U> {
+        &self.idle_monitor
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn subscribe_break_warnings(&self) -> broadcast::Receiver<BreakWarning> {
+        self.break_warning_sender.subscribe()
+    }
+
+    pub fn subscribe_break_events(&self) -> broadcast::Receiver<BreakEvent> {
+        self.break_event_sender.subscribe()
+    }
+
+    pub fn set_idle_inhibited(&mut self, idle_inhibited: bool) {
+        self.idle_monitor
+            .set_idle_inhibited(idle_inhibited && self.config.respect_idle_inhibitors);
+    }
+
+    pub fn refresh_idle_info(&mut self) -> IdleInfo {
+        let previous_idle_info = self.idle_monitor.get_last_idle_info();
+        let was_on_break = self.is_on_break();
+        let policy = &self.policy;
+        let history = self.history.recent_entries();
+        let refreshed_idle_info = self
+            .idle_monitor
+            .refresh_idle_info_with_policy(|idle_info| policy.is_user_idle(idle_info, history));
+        // Refreshing only ever ends a break by finishing it.
+        if was_on_break && !self.is_on_break() {
+            self.record_outcome(&refreshed_idle_info, BreakOutcome::Completed);
+        }
+        let idle_info = self.follow_policy(&previous_idle_info, refreshed_idle_info);
+        let idle_info = self.follow_calendar(idle_info);
+        let warning_secs: Vec<i64> = self
+            .config
+            .break_warning_minutes
+            .iter()
+            .map(|minutes| minutes * 60)
+            .collect();
+        if let Some(break_warning) = self.break_warner.check(&idle_info, &warning_secs) {
+            // Nobody listening is fine
+            self.break_warning_sender.send(break_warning).ok();
+        }
+        self.publish(idle_info);
+        idle_info
+    }
+
+    // Lets the policy script weigh in when a break comes due or starts. Breaks it lengthened
+    // or shortened are back to the profile's length once they're over.
+    fn follow_policy(&mut self, previous: &IdleInfo, current: IdleInfo) -> IdleInfo {
+        let history = self.history.recent_entries();
+        match (previous.last_mode_state, current.last_mode_state) {
+            (ModeState::Normal { .. }, ModeState::PreBreak { .. })
+                if !self.policy.should_start_break(&current, history) =>
+            {
+                self.idle_monitor.defer_break(Duration::minutes(1))
+            }
+            (ModeState::Break { .. }, ModeState::Break { .. }) => current,
+            (_, ModeState::Break { .. }) => {
+                let break_length_secs = self.policy.break_length_secs(&current, history);
+                self.use_break_length(current, break_length_secs)
+            }
+            (ModeState::Break { .. }, _) => {
+                let break_length_secs = self.config.active_profile().break_length_secs;
+                self.use_break_length(current, break_length_secs)
+            }
+            _ => current,
+        }
+    }
+
+    fn use_break_length(&mut self, current: IdleInfo, break_length_secs: i64) -> IdleInfo {
+        if current.break_length_secs == break_length_secs {
+            return current;
+        }
+        self.idle_monitor.set_break_length(break_length_secs);
+        self.idle_monitor.get_last_idle_info()
+    }
+
+    fn follow_calendar(&mut self, mut idle_info: IdleInfo) -> IdleInfo {
+        let Some(ref mut calendar_watcher) = self.calendar_watcher else {
+            return idle_info;
+        };
+
+        let meeting = calendar_watcher.current_meeting(idle_info.last_checked);
+        if meeting != self.current_meeting {
+            match meeting {
+                Some(meeting) => {
+                    // Don't cut short a longer snooze or a mute the user chose.
+                    let already_quiet = match idle_info.presence_mode {
+                        PresenceMode::Active => false,
+                        PresenceMode::SnoozedUntil(timestamp) => timestamp >= meeting.end,
+                        PresenceMode::Muted => true,
+                    };
+                    if !already_quiet {
+                        let was_on_break = self.is_on_break();
+                        idle_info = self.idle_monitor.snooze_until_meeting_ends(meeting.end);
+                        self.snoozed_for_meeting = true;
+                        // The break is offered again once the meeting is over.
+                        if was_on_break {
+                            self.record_outcome(
+                                &idle_info,
+                                BreakOutcome::PostponedForMeeting {
+                                    postponed_for_secs: (meeting.end - idle_info.last_checked)
+                                        .num_seconds(),
+                                },
+                            );
+                        }
+                    }
+                }
+                None => {
+                    if self.break_due_after_meeting {
+                        idle_info = self.idle_monitor.make_break_due();
+                    }
+                    self.break_due_after_meeting = false;
+                    self.snoozed_for_meeting = false;
+                }
+            }
+            self.current_meeting = meeting;
+        }
+
+        if self.snoozed_for_meeting
+            && let ModeState::Normal {
+                progress_towards_break,
+                ..
+            } = idle_info.last_mode_state
+            && progress_towards_break.num_seconds() >= idle_info.time_to_break_secs
+        {
+            self.break_due_after_meeting = true;
+        }
+        idle_info
+    }
+
+    // Applies settings that were changed outside of the app. Doesn't write them back.
+    pub fn apply_config(&mut self, mut config: Config) -> IdleInfo {
+        // Leaving strict mode takes the exit phrase, editing the file doesn't get around that.
+        if self.config.strict_mode.enabled {
+            if config.strict_mode != self.config.strict_mode {
+                println!("Ignoring changes to strict mode in the settings file while it is on");
+                config.strict_mode = self.config.strict_mode.clone();
+            }
+            // A gentler profile or longer intervals would get around breaks just the same.
+            if config.active_profile != self.config.active_profile {
+                println!(
+                    "Ignoring the switch to another profile in the settings file while strict mode is on"
+                );
+                config.active_profile = self.config.active_profile.clone();
+            }
+            let profile = self.config.active_profile();
+            let edited_profile = config.active_profile_mut();
+            if (
+                edited_profile.time_to_break_secs,
+                edited_profile.break_length_secs,
+                edited_profile.prebreak_idle_secs,
+            ) != (
+                profile.time_to_break_secs,
+                profile.break_length_secs,
+                profile.prebreak_idle_secs,
+            ) {
+                println!(
+                    "Ignoring changes to break timings in the settings file while strict mode is on"
+                );
+                edited_profile.time_to_break_secs = profile.time_to_break_secs;
+                edited_profile.break_length_secs = profile.break_length_secs;
+                edited_profile.prebreak_idle_secs = profile.prebreak_idle_secs;
+            }
+        }
+        if config != self.config {
+            if config.calendars != self.config.calendars {
+                self.calendar_watcher = make_calendar_watcher(&config);
+            }
+            // These start their own threads and servers once, when the app starts.
+            if (
+                &config.mqtt,
+                &config.http,
+                &config.metrics,
+                &config.activitywatch,
+            ) != (
+                &self.config.mqtt,
+                &self.config.http,
+                &self.config.metrics,
+                &self.config.activitywatch,
+            ) {
+                println!(
+                    "Changes to the mqtt, http, metrics and activitywatch settings take effect after a restart"
+                );
+            }
+            self.config = config;
+            self.config_sender.send_replace(self.config.clone());
+            self.idle_monitor
+                .apply_profile(&self.config.active_profile());
+        }
+        let idle_info = self.idle_monitor.get_last_idle_info();
+        self.publish(idle_info);
+        idle_info
+    }
+
+    /// Serves requests until the deadline passes. Returns false once every
+    /// handle has been dropped, which means nobody can talk to us anymore.
+    pub fn handle_requests_until(&mut self, deadline: Instant) -> bool {
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.request_receiver.recv_timeout(timeout) {
+                Ok(request) => self.handle_request(request),
+                Err(RecvTimeoutError::Timeout) => return true,
+                Err(RecvTimeoutError::Disconnected) => return false,
+            }
+        }
+    }
+
+    fn handle_request(&mut self, request: IdleMonitorRequest) {
+        match request {
+            IdleMonitorRequest::Command { command, reply } => {
+                let previous_idle_info = self.idle_monitor.get_last_idle_info();
+                let result = self
+                    .apply_command(command)
+                    .map(|idle_info| self.follow_policy(&previous_idle_info, idle_info));
+                match result {
+                    Ok(idle_info) => {
+                        self.publish(idle_info);
+                    }
+                    Err(ref error) if reply.is_none() => println!("{}", error),
+                    Err(_) => {}
+                }
+                if let Some(reply) = reply {
+                    // The requester may have given up waiting, which is fine.
+                    reply.send(result).ok();
+                }
+            }
+            IdleMonitorRequest::GetLastIdleInfo { reply } => {
+                reply.send(self.idle_monitor.get_last_idle_info()).ok();
+            }
+        }
+    }
+
+    fn apply_command(&mut self, command: IdleMonitorCommand) -> Result<IdleInfo, IdleMonitorError> {
+        let idle_info = match command {
+            // Muting or snoozing would skip every break to come.
+            IdleMonitorCommand::Mute | IdleMonitorCommand::Snooze(_)
+                if self.config.strict_mode.enabled =>
+            {
+                return Err(IdleMonitorError::SkipNotAllowed);
+            }
+            IdleMonitorCommand::Mute => self.idle_monitor.mute(),
+            IdleMonitorCommand::Unmute => self.idle_monitor.unmute(),
+            IdleMonitorCommand::Snooze(timestamp) => self.idle_monitor.snooze(timestamp),
+            IdleMonitorCommand::SetReadingMode(value) => {
+                self.idle_monitor.set_reading_mode(value);
+                self.config.active_profile_mut().reading_mode = value;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::TriggerBreak => self.idle_monitor.trigger_break(),
+            IdleMonitorCommand::SkipBreak => {
+                if self.config.strict_mode.enabled {
+                    return Err(IdleMonitorError::SkipNotAllowed);
+                }
+                let was_on_break = self.is_on_break();
+                let idle_info = self.idle_monitor.skip_break();
+                if was_on_break {
+                    self.record_outcome(&idle_info, BreakOutcome::Skipped);
+                }
+                idle_info
+            }
+            IdleMonitorCommand::PostponeBreak(duration) if duration <= Duration::zero() => {
+                return Err(IdleMonitorError::InvalidPostpone);
+            }
+            IdleMonitorCommand::PostponeBreak(duration) => {
+                let idle_info = self.idle_monitor.get_last_idle_info();
+                let Some(duration) = self.config.strict_mode.allowed_postpone(
+                    duration,
+                    idle_info.postpone_count,
+                    idle_info.last_postpone,
+                ) else {
+                    return Err(IdleMonitorError::NoPostponesLeft);
+                };
+                // Postponing from the notification that precedes a break counts too.
+                let break_was_due = !matches!(
+                    self.idle_monitor.get_last_idle_info().last_mode_state,
+                    ModeState::Normal { .. }
+                );
+                let idle_info = self.idle_monitor.postpone_break(duration);
+                if break_was_due {
+                    self.record_outcome(
+                        &idle_info,
+                        BreakOutcome::Postponed {
+                            postponed_for_secs: duration.num_seconds(),
+                        },
+                    );
+                }
+                idle_info
+            }
+            IdleMonitorCommand::SetTimeToBreak(num_secs) => {
+                self.idle_monitor.set_time_to_break(num_secs);
+                self.config.active_profile_mut().time_to_break_secs = num_secs;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetBreakLength(num_secs) => {
+                self.idle_monitor.set_break_length(num_secs);
+                self.config.active_profile_mut().break_length_secs = num_secs;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::UseProfile(name) => {
+                let Some(profile_name) = self.config.find_profile_name(&name) else {
+                    return Err(IdleMonitorError::UnknownProfile(name));
+                };
+                self.config.active_profile = profile_name.to_string();
+                self.persist_config();
+                self.idle_monitor
+                    .apply_profile(&self.config.active_profile())
+            }
+            IdleMonitorCommand::SetPauseMediaDuringBreaks(value) => {
+                self.config.pause_media_during_breaks = value;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetLockScreenDuringBreaks(value) => {
+                self.config.lock_screen_during_breaks = value;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetBreakStyle(break_style) => {
+                self.config.break_style = break_style;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetBreakContent(break_content) => {
+                self.config.break_content = break_content;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetExerciseCategories(exercise_categories) => {
+                self.config.exercise_categories = exercise_categories;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetBreakWarningMinutes(break_warning_minutes) => {
+                self.config.break_warning_minutes = break_warning_minutes;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetPostponeMinutes(postpone_minutes) => {
+                self.config.postpone_minutes = postpone_minutes;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetRespectIdleInhibitors(value) => {
+                self.config.respect_idle_inhibitors = value;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetSoundEvents(events) => {
+                self.config.sounds.events = events;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetSoundVolume(volume_percent) => {
+                self.config.sounds.volume_percent = volume_percent.min(100);
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+            IdleMonitorCommand::SetStrictMode {
+                enabled,
+                confirmation,
+            } => {
+                if !enabled
+                    && self.config.strict_mode.enabled
+                    && !is_strict_mode_exit_phrase(&confirmation)
+                {
+                    return Err(IdleMonitorError::WrongConfirmation);
+                }
+                self.config.strict_mode.enabled = enabled;
+                self.persist_config();
+                self.idle_monitor.get_last_idle_info()
+            }
+        };
+        Ok(idle_info)
+    }
+
+    // Hands the new state to the GUI and D-Bus, and tells listeners what changed.
+    fn publish(&self, idle_info: IdleInfo) {
+        let previous = self.idle_info_sender.send_replace(idle_info);
+        for kind in state_changes(&previous, &idle_info) {
+            // Nobody listening is fine
+            self.break_event_sender
+                .send(BreakEvent { kind, idle_info })
+                .ok();
+        }
+    }
+
+    fn record_outcome(&mut self, idle_info: &IdleInfo, outcome: BreakOutcome) {
+        self.history.record(idle_info.last_checked, outcome);
+        self.break_event_sender
+            .send(BreakEvent {
+                kind: BreakEventKind::BreakEnded(outcome),
+                idle_info: *idle_info,
+            })
+            .ok();
+    }
+
+    fn is_on_break(&self) -> bool {
+        matches!(
+            self.idle_monitor.get_last_idle_info().last_mode_state,
+            ModeState::Break { .. }
+        )
+    }
+
+    fn persist_config(&self) {
+        self.config_sender.send_replace(self.config.clone());
+        if let Some(ref path) = self.config_path
+            && let Err(error) = self.config.save_to_path(path)
+        {
+            println!("Could not save settings to disk: {}", error);
+        }
+    }
+}
+
+fn make_calendar_watcher(config: &Config) -> Option<CalendarWatcher> {
+    (!config.calendars.is_empty()).then(|| CalendarWatcher::new(&config.calendars))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration as StdDuration;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::backend::config::{Profile, STRICT_MODE_EXIT_PHRASE};
+    use crate::backend::history::HistoryEntry;
+    use crate::backend::idle_monitoring::{DebouncedIdleState, MockClock, MockIdleChecker};
+
+    fn make_actor() -> (
+        IdleMonitorActor<MockIdleChecker, MockClock>,
+        IdleMonitorHandle,
+        watch::Receiver<IdleInfo>,
+    ) {
+        make_actor_with_history(BreakHistory::new(None))
+    }
+
+    fn make_actor_with_history(
+        history: BreakHistory,
+    ) -> (
+        IdleMonitorActor<MockIdleChecker, MockClock>,
+        IdleMonitorHandle,
+        watch::Receiver<IdleInfo>,
+    ) {
+        let current_time = Utc::now();
+        let mut idle_checker = MockIdleChecker::new();
+        idle_checker
+            .expect_get_idle_time_in_seconds()
+            .return_const(0u64);
+        let mut clock = MockClock::new();
+        clock.expect_get_time().return_const(current_time);
+
+        let config = Config::default();
+        let idle_monitor = IdleMonitor::new(idle_checker, clock, &config.active_profile(), None);
+        let (idle_info_sender, idle_info_receiver) =
+            watch::channel(idle_monitor.get_last_idle_info());
+        let (config_sender, _) = watch::channel(config.clone());
+        let (actor, handle) = IdleMonitorActor::new(
+            idle_monitor,
+            idle_info_sender,
+            config,
+            None,
+            config_sender,
+            history,
+            BreakPolicy::default(),
+        );
+        (actor, handle, idle_info_receiver)
+    }
+
+    #[test]
+    fn fire_and_forget_command_is_published() {
+        let (mut actor, handle, idle_info_receiver) = make_actor();
+
+        handle.send(IdleMonitorCommand::TriggerBreak);
+        assert!(actor.handle_requests_until(Instant::now()));
+
+        assert!(matches!(
+            idle_info_receiver.borrow().last_mode_state,
+            ModeState::Break { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn request_receives_typed_response() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        let actor_thread = thread::spawn(move || {
+            actor.handle_requests_until(Instant::now() + StdDuration::from_secs(5))
+        });
+
+        let idle_info = handle
+            .request(IdleMonitorCommand::TriggerBreak)
+            .await
+            .unwrap();
+        assert!(matches!(idle_info.last_mode_state, ModeState::Break { .. }));
+        assert_eq!(handle.get_last_idle_info().await.unwrap(), idle_info);
+
+        drop(handle);
+        assert!(!actor_thread.join().unwrap());
+    }
+
+    #[tokio::test]
+    async fn request_fails_cleanly_when_actor_is_gone() {
+        let (actor, handle, _idle_info_receiver) = make_actor();
+        drop(actor);
+
+        assert_eq!(
+            handle.get_last_idle_info().await,
+            Err(IdleMonitorError::Gone)
+        );
+        assert_eq!(
+            handle.request(IdleMonitorCommand::Mute).await,
+            Err(IdleMonitorError::Gone)
+        );
+    }
+
+    #[test]
+    fn switching_profiles_applies_and_publishes_settings() {
+        let (mut actor, handle, idle_info_receiver) = make_actor();
+        let config_receiver = actor.config_sender.subscribe();
+
+        handle.send(IdleMonitorCommand::UseProfile(String::from("Gaming")));
+        assert!(actor.handle_requests_until(Instant::now()));
+
+        let gaming = Config::default().profiles["gaming"].clone();
+        assert_eq!(config_receiver.borrow().active_profile, "gaming");
+        let idle_info = *idle_info_receiver.borrow();
+        assert_eq!(idle_info.time_to_break_secs, gaming.time_to_break_secs);
+        assert_eq!(idle_info.break_length_secs, gaming.break_length_secs);
+        assert_eq!(idle_info.prebreak_idle_secs, gaming.prebreak_idle_secs);
+    }
+
+    #[test]
+    fn settings_changes_are_stored_in_the_active_profile() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+
+        handle.send(IdleMonitorCommand::UseProfile(String::from("coding")));
+        handle.send(IdleMonitorCommand::SetBreakLength(240));
+        assert!(actor.handle_requests_until(Instant::now()));
+
+        assert_eq!(actor.config.profiles["coding"].break_length_secs, 240);
+        assert_eq!(
+            actor.config.profiles["default"],
+            Config::default().profiles["default"]
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_profile_is_rejected() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        let actor_thread = thread::spawn(move || {
+            actor.handle_requests_until(Instant::now() + StdDuration::from_secs(5))
+        });
+
+        assert_eq!(
+            handle
+                .request(IdleMonitorCommand::UseProfile(String::from("sleeping")))
+                .await,
+            Err(IdleMonitorError::UnknownProfile(String::from("sleeping")))
+        );
+
+        drop(handle);
+        assert!(!actor_thread.join().unwrap());
+    }
+
+    #[test]
+    fn skipping_and_postponing_breaks_is_recorded() {
+        let path = std::env::temp_dir().join(format!(
+            "stretch-break-actor-history-test-{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::remove_file(&path).ok();
+        let (mut actor, handle, _idle_info_receiver) =
+            make_actor_with_history(BreakHistory::new(Some(path.clone())));
+
+        // Not on break, so there's nothing to record.
+        handle.send(IdleMonitorCommand::PostponeBreak(Duration::minutes(5)));
+        handle.send(IdleMonitorCommand::TriggerBreak);
+        handle.send(IdleMonitorCommand::PostponeBreak(Duration::minutes(5)));
+        handle.send(IdleMonitorCommand::TriggerBreak);
+        handle.send(IdleMonitorCommand::SkipBreak);
+        assert!(actor.handle_requests_until(Instant::now()));
+
+        let outcomes: Vec<BreakOutcome> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<HistoryEntry>(line).unwrap().outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                BreakOutcome::Postponed {
+                    postponed_for_secs: 300
+                },
+                BreakOutcome::Skipped,
+            ]
+        );
+    }
+
+    #[test]
+    fn break_events_follow_commands() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        let mut break_events = actor.subscribe_break_events();
+
+        handle.send(IdleMonitorCommand::TriggerBreak);
+        handle.send(IdleMonitorCommand::SkipBreak);
+        handle.send(IdleMonitorCommand::Mute);
+        assert!(actor.handle_requests_until(Instant::now()));
+
+        let kinds: Vec<BreakEventKind> = std::iter::from_fn(|| break_events.try_recv().ok())
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                BreakEventKind::BreakStarted,
+                BreakEventKind::BreakEnded(BreakOutcome::Skipped),
+                BreakEventKind::PresenceChanged,
+            ]
+        );
+    }
+
+    #[test]
+    fn policy_sets_the_length_of_a_single_break() {
+        let (mut actor, handle, idle_info_receiver) = make_actor();
+        actor.policy = BreakPolicy::from_script(
+            "fn break_length_secs(info, history) { info.break_length_secs * 2 }",
+        )
+        .unwrap();
+        let break_length_secs = Config::default().active_profile().break_length_secs;
+
+        handle.send(IdleMonitorCommand::TriggerBreak);
+        assert!(actor.handle_requests_until(Instant::now()));
+        assert_eq!(
+            idle_info_receiver.borrow().break_length_secs,
+            break_length_secs * 2
+        );
+
+        handle.send(IdleMonitorCommand::SkipBreak);
+        assert!(actor.handle_requests_until(Instant::now()));
+        assert_eq!(
+            idle_info_receiver.borrow().break_length_secs,
+            break_length_secs
+        );
+    }
+
+    #[test]
+    fn policy_judges_idleness_on_the_current_tick() {
+        // (current time, time of last input)
+        let start = Utc::now();
+        let times = Arc::new(Mutex::new((start, start)));
+        let mut idle_checker = MockIdleChecker::new();
+        let idle_times = times.clone();
+        idle_checker
+            .expect_get_idle_time_in_seconds()
+            .returning(move || {
+                let (now, last_input) = *idle_times.lock().unwrap();
+                (now - last_input).num_seconds() as u64
+            });
+        let mut clock = MockClock::new();
+        let clock_times = times.clone();
+        clock
+            .expect_get_time()
+            .returning(move || clock_times.lock().unwrap().0);
+
+        let config = Config::default();
+        let idle_monitor = IdleMonitor::new(idle_checker, clock, &config.active_profile(), None);
+        let (idle_info_sender, _) = watch::channel(idle_monitor.get_last_idle_info());
+        let (config_sender, _) = watch::channel(config.clone());
+        let (mut actor, _handle) = IdleMonitorActor::new(
+            idle_monitor,
+            idle_info_sender,
+            config,
+            None,
+            config_sender,
+            BreakHistory::new(None),
+            BreakPolicy::from_script("fn is_user_idle(info, history) { info.idle_secs > 3 }")
+                .unwrap(),
+        );
+
+        let mut states = Vec::new();
+        for _ in 0..4 {
+            times.lock().unwrap().0 += Duration::seconds(1);
+            states.push(actor.refresh_idle_info().last_mode_state);
+        }
+        assert!(states[..3].iter().all(|state| matches!(
+            state,
+            ModeState::Normal {
+                idle_state: DebouncedIdleState::Active { .. },
+                ..
+            }
+        )));
+        // Four seconds of sitting still is idle as soon as it happens, not a tick later.
+        assert!(matches!(
+            states[3],
+            ModeState::Normal {
+                idle_state: DebouncedIdleState::ActiveGoingToIdle { .. },
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn strict_mode_limits_skipping_and_postponing() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        let actor_thread = thread::spawn(move || {
+            actor.handle_requests_until(Instant::now() + StdDuration::from_secs(5))
+        });
+        let time_to_break =
+            Duration::seconds(Config::default().active_profile().time_to_break_secs);
+        let set_strict_mode = |enabled, confirmation: &str| IdleMonitorCommand::SetStrictMode {
+            enabled,
+            confirmation: confirmation.to_string(),
+        };
+
+        handle.request(set_strict_mode(true, "")).await.unwrap();
+        handle
+            .request(IdleMonitorCommand::TriggerBreak)
+            .await
+            .unwrap();
+        assert_eq!(
+            handle.request(IdleMonitorCommand::SkipBreak).await,
+            Err(IdleMonitorError::SkipNotAllowed)
+        );
+
+        for (postpone_count, postponed_by) in [(1, Duration::minutes(4)), (2, Duration::minutes(2))]
+        {
+            let idle_info = handle
+                .request(IdleMonitorCommand::PostponeBreak(Duration::minutes(4)))
+                .await
+                .unwrap();
+            assert_eq!(idle_info.postpone_count, postpone_count);
+            assert!(matches!(
+                idle_info.last_mode_state,
+                ModeState::Normal { progress_towards_break, .. }
+                    if progress_towards_break == time_to_break - postponed_by
+            ));
+            handle
+                .request(IdleMonitorCommand::TriggerBreak)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            handle
+                .request(IdleMonitorCommand::PostponeBreak(Duration::minutes(4)))
+                .await,
+            Err(IdleMonitorError::NoPostponesLeft)
+        );
+
+        assert_eq!(
+            handle.request(set_strict_mode(false, "please")).await,
+            Err(IdleMonitorError::WrongConfirmation)
+        );
+        handle
+            .request(set_strict_mode(false, STRICT_MODE_EXIT_PHRASE))
+            .await
+            .unwrap();
+        assert!(handle.request(IdleMonitorCommand::SkipBreak).await.is_ok());
+
+        drop(handle);
+        assert!(!actor_thread.join().unwrap());
+    }
+
+    #[test]
+    fn settings_file_cannot_loosen_strict_mode() {
+        let (mut actor, _handle, _idle_info_receiver) = make_actor();
+        actor.config.strict_mode.enabled = true;
+        let strict_mode = actor.config.strict_mode.clone();
+
+        let mut edited_config = actor.config.clone();
+        edited_config.strict_mode.enabled = false;
+        edited_config.strict_mode.max_postpones = 10;
+        edited_config.postpone_minutes = vec![3];
+        actor.apply_config(edited_config);
+
+        assert_eq!(actor.config.strict_mode, strict_mode);
+        assert_eq!(actor.config.postpone_minutes, vec![3]);
+    }
+
+    #[test]
+    fn settings_file_cannot_loosen_break_timings_in_strict_mode() {
+        let (mut actor, _handle, _idle_info_receiver) = make_actor();
+        actor.config.strict_mode.enabled = true;
+        let profile = actor.config.active_profile();
+
+        let mut edited_config = actor.config.clone();
+        edited_config.active_profile = String::from("gaming");
+        let edited_profile = edited_config.profiles.get_mut("default").unwrap();
+        edited_profile.time_to_break_secs *= 10;
+        edited_profile.break_length_secs = 1;
+        edited_profile.reading_mode = !profile.reading_mode;
+        let idle_info = actor.apply_config(edited_config);
+
+        assert_eq!(actor.config.active_profile, "default");
+        let kept_profile = actor.config.active_profile();
+        assert_eq!(kept_profile.time_to_break_secs, profile.time_to_break_secs);
+        assert_eq!(kept_profile.break_length_secs, profile.break_length_secs);
+        assert_eq!(kept_profile.reading_mode, !profile.reading_mode);
+        assert_eq!(idle_info.time_to_break_secs, profile.time_to_break_secs);
+    }
+
+    #[tokio::test]
+    async fn strict_mode_refuses_muting() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        actor.config.strict_mode.enabled = true;
+        let actor_thread = thread::spawn(move || {
+            actor.handle_requests_until(Instant::now() + StdDuration::from_secs(5))
+        });
+
+        assert_eq!(
+            handle.request(IdleMonitorCommand::Mute).await,
+            Err(IdleMonitorError::SkipNotAllowed)
+        );
+        assert_eq!(
+            handle.get_last_idle_info().await.unwrap().presence_mode,
+            PresenceMode::Active
+        );
+
+        drop(handle);
+        assert!(!actor_thread.join().unwrap());
+    }
+
+    #[tokio::test]
+    async fn postpones_stay_within_one_interval() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        let actor_thread = thread::spawn(move || {
+            actor.handle_requests_until(Instant::now() + StdDuration::from_secs(5))
+        });
+
+        assert_eq!(
+            handle
+                .request(IdleMonitorCommand::PostponeBreak(Duration::minutes(-5)))
+                .await,
+            Err(IdleMonitorError::InvalidPostpone)
+        );
+        handle
+            .request(IdleMonitorCommand::TriggerBreak)
+            .await
+            .unwrap();
+        let idle_info = handle
+            .request(IdleMonitorCommand::PostponeBreak(Duration::days(1)))
+            .await
+            .unwrap();
+        assert!(matches!(
+            idle_info.last_mode_state,
+            ModeState::Normal { progress_towards_break, .. } if progress_towards_break.is_zero()
+        ));
+        assert_eq!(
+            idle_info.last_postpone,
+            Some(Duration::seconds(idle_info.time_to_break_secs))
+        );
+
+        drop(handle);
+        assert!(!actor_thread.join().unwrap());
+    }
+
+    #[tokio::test]
+    async fn strict_mode_refuses_snoozing() {
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        actor.config.strict_mode.enabled = true;
+        let actor_thread = thread::spawn(move || {
+            actor.handle_requests_until(Instant::now() + StdDuration::from_secs(5))
+        });
+
+        assert_eq!(
+            handle
+                .request(IdleMonitorCommand::Snooze(Utc::now() + Duration::hours(1)))
+                .await,
+            Err(IdleMonitorError::SkipNotAllowed)
+        );
+        assert_eq!(
+            handle.get_last_idle_info().await.unwrap().presence_mode,
+            PresenceMode::Active
+        );
+
+        drop(handle);
+        assert!(!actor_thread.join().unwrap());
+    }
+
+    #[test]
+    fn meeting_that_interrupts_a_break_postpones_it() {
+        let meeting_start = Utc::now() - Duration::minutes(1);
+        let calendar_path = std::env::temp_dir().join(format!(
+            "stretch-break-actor-interrupted-break-{}.ics",
+            std::process::id()
+        ));
+        std::fs::write(
+            &calendar_path,
+            format!(
+                "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART:{}\r\n\
+                 DURATION:PT10M\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+                meeting_start.format("%Y%m%dT%H%M%SZ")
+            ),
+        )
+        .unwrap();
+        let (mut actor, handle, _idle_info_receiver) = make_actor();
+        actor.config.calendars = vec![calendar_path];
+        actor.calendar_watcher = make_calendar_watcher(&actor.config);
+        let mut break_events = actor.subscribe_break_events();
+
+        handle.send(IdleMonitorCommand::TriggerBreak);
+        assert!(actor.handle_requests_until(Instant::now()));
+        let idle_info = actor.refresh_idle_info();
+
+        assert!(matches!(
+            idle_info.last_mode_state,
+            ModeState::Normal { .. }
+        ));
+        let kinds: Vec<BreakEventKind> = std::iter::from_fn(|| break_events.try_recv().ok())
+            .map(|event| event.kind)
+            .collect();
+        assert!(matches!(
+            kinds[..],
+            [
+                BreakEventKind::BreakStarted,
+                BreakEventKind::BreakEnded(BreakOutcome::PostponedForMeeting { .. }),
+                BreakEventKind::PresenceChanged,
+            ]
+        ));
+    }
+
+    #[test]
+    fn snoozes_during_meetings_and_offers_the_missed_break_afterwards() {
+        let meeting_start = DateTime::parse_from_rfc3339("2025-02-03T10:00:00Z")
+            .unwrap()
+            .to_utc();
+        let meeting_end = meeting_start + Duration::minutes(5);
+        let calendar_path = std::env::temp_dir().join(format!(
+            "stretch-break-actor-calendar-{}.ics",
+            std::process::id()
+        ));
+        std::fs::write(
+            &calendar_path,
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART:20250203T100000Z\r\n\
+             DTEND:20250203T100500Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+        )
+        .unwrap();
+
+        // (current time, time of last input)
+        let times = Arc::new(Mutex::new((
+            meeting_start - Duration::seconds(30),
+            meeting_start - Duration::seconds(30),
+        )));
+        let mut idle_checker = MockIdleChecker::new();
+        let idle_times = times.clone();
+        idle_checker
+            .expect_get_idle_time_in_seconds()
+            .returning(move || {
+                let (now, last_input) = *idle_times.lock().unwrap();
+                (now - last_input).num_seconds() as u64
+            });
+        let mut clock = MockClock::new();
+        let clock_times = times.clone();
+        clock
+            .expect_get_time()
+            .returning(move || clock_times.lock().unwrap().0);
+
+        let config = Config {
+            calendars: vec![calendar_path],
+            ..Config::default()
+        };
+        let profile = Profile {
+            time_to_break_secs: 60,
+            break_length_secs: 30,
+            ..Profile::default()
+        };
+        let idle_monitor = IdleMonitor::new(idle_checker, clock, &profile, None);
+        let (idle_info_sender, _) = watch::channel(idle_monitor.get_last_idle_info());
+        let (config_sender, _) = watch::channel(config.clone());
+        let (mut actor, _handle) = IdleMonitorActor::new(
+            idle_monitor,
+            idle_info_sender,
+            config,
+            None,
+            config_sender,
+            BreakHistory::new(None),
+            BreakPolicy::default(),
+        );
+
+        // Busy typing until the break comes due halfway through the meeting, then sitting
+        // still for long enough that the timer would normally reset.
+        let mut idle_info = actor.refresh_idle_info();
+        while idle_info.last_checked < meeting_end - Duration::seconds(5) {
+            let mut times = times.lock().unwrap();
+            times.0 += Duration::seconds(5);
+            if times.0 < meeting_start + Duration::seconds(90) {
+                times.1 = times.0;
+            }
+            drop(times);
+            idle_info = actor.refresh_idle_info();
+            assert!(!matches!(
+                idle_info.last_mode_state,
+                ModeState::PreBreak { .. } | ModeState::Break { .. }
+            ));
+            if idle_info.last_checked >= meeting_start {
+                assert_eq!(
+                    idle_info.presence_mode,
+                    PresenceMode::SnoozedUntil(meeting_end)
+                );
+            }
+        }
+        assert!(matches!(
+            idle_info.last_mode_state,
+            ModeState::Normal { progress_towards_break, .. } if progress_towards_break.num_seconds() == 0
+        ));
+
+        for _ in 0..3 {
+            times.lock().unwrap().0 += Duration::seconds(5);
+            idle_info = actor.refresh_idle_info();
+        }
+        assert_eq!(idle_info.presence_mode, PresenceMode::Active);
+        assert!(matches!(
+            idle_info.last_mode_state,
+            ModeState::PreBreak { .. }
+        ));
+    }
+
+    #[test]
+    fn leaves_breaks_alone_after_meetings_the_user_had_already_snoozed_past() {
+        let meeting_start = DateTime::parse_from_rfc3339("2025-02-03T10:00:00Z")
+            .unwrap()
+            .to_utc();
+        let meeting_end = meeting_start + Duration::minutes(5);
+        let calendar_path = std::env::temp_dir().join(format!(
+            "stretch-break-actor-snoozed-calendar-{}.ics",
+            std::process::id()
+        ));
+        std::fs::write(
+            &calendar_path,
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART:20250203T100000Z\r\n\
+             DTEND:20250203T100500Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+        )
+        .unwrap();
+
+        // (current time, time of last input)
+        let times = Arc::new(Mutex::new((
+            meeting_start - Duration::seconds(30),
+            meeting_start - Duration::seconds(30),
+        )));
+        let mut idle_checker = MockIdleChecker::new();
+        let idle_times = times.clone();
+        idle_checker
+            .expect_get_idle_time_in_seconds()
+            .returning(move || {
+                let (now, last_input) = *idle_times.lock().unwrap();
+                (now - last_input).num_seconds() as u64
+            });
+        let mut clock = MockClock::new();
+        let clock_times = times.clone();
+        clock
+            .expect_get_time()
+            .returning(move || clock_times.lock().unwrap().0);
+
+        let config = Config {
+            calendars: vec![calendar_path],
+            ..Config::default()
+        };
+        let profile = Profile {
+            time_to_break_secs: 60,
+            break_length_secs: 30,
+            ..Profile::default()
+        };
+        let idle_monitor = IdleMonitor::new(idle_checker, clock, &profile, None);
+        let (idle_info_sender, _) = watch::channel(idle_monitor.get_last_idle_info());
+        let (config_sender, _) = watch::channel(config.clone());
+        let (mut actor, _handle) = IdleMonitorActor::new(
+            idle_monitor,
+            idle_info_sender,
+            config,
+            None,
+            config_sender,
+            BreakHistory::new(None),
+            BreakPolicy::default(),
+        );
+
+        let snoozed_until = meeting_end + Duration::minutes(2);
+        actor
+            .apply_command(IdleMonitorCommand::Snooze(snoozed_until))
+            .unwrap();
+        // Busy typing until the break comes due halfway through the meeting, then sitting
+        // still past its end. The user's own snooze covered the meeting, so nothing is owed.
+        let mut idle_info = actor.refresh_idle_info();
+        while idle_info.last_checked < meeting_end + Duration::seconds(15) {
+            let mut times = times.lock().unwrap();
+            times.0 += Duration::seconds(5);
+            if times.0 < meeting_start + Duration::seconds(90) {
+                times.1 = times.0;
+            }
+            drop(times);
+            idle_info = actor.refresh_idle_info();
+            assert_eq!(
+                idle_info.presence_mode,
+                PresenceMode::SnoozedUntil(snoozed_until)
+            );
+        }
+        assert!(matches!(
+            idle_info.last_mode_state,
+            ModeState::Normal { progress_towards_break, .. } if progress_towards_break.num_seconds() == 0
+        ));
+    }
+}
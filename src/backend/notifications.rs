@@ -0,0 +1,668 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::{DateTime, Duration, Utc};
+use zbus::blocking::Connection;
+use zbus::proxy;
+use zbus::zvariant::Value;
+
+use crate::APP_ID;
+use crate::backend::monitor_actor::IdleMonitorCommand;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+}
+
+// Both interfaces have an ActionInvoked signal, so the portal one lives apart.
+mod portal {
+    use std::collections::HashMap;
+
+    use zbus::proxy;
+    use zbus::zvariant::Value;
+
+    #[proxy(
+        interface = "org.freedesktop.portal.Notification",
+        default_service = "org.freedesktop.portal.Desktop",
+        default_path = "/org/freedesktop/portal/desktop"
+    )]
+    pub trait Notification {
+        fn add_notification(
+            &self,
+            id: &str,
+            notification: HashMap<&str, Value<'_>>,
+        ) -> zbus::Result<()>;
+
+        fn remove_notification(&self, id: &str) -> zbus::Result<()>;
+
+        #[zbus(signal)]
+        fn action_invoked(
+            &self,
+            id: &str,
+            action: &str,
+            parameter: Vec<Value<'_>>,
+        ) -> zbus::Result<()>;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    StartBreak,
+    // By the first of the configured postpone_minutes
+    Postpone { minutes: i64 },
+    Snooze,
+}
+
+impl NotificationAction {
+    // The key is all the notification server hands back, so it carries the postpone time.
+    fn key(&self) -> String {
+        match self {
+            NotificationAction::StartBreak => String::from("start-break"),
+            NotificationAction::Postpone { minutes } => format!("postpone-{}", minutes),
+            NotificationAction::Snooze => String::from("snooze"),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            NotificationAction::StartBreak => String::from("Start break now"),
+            NotificationAction::Postpone { minutes } => format!("Postpone {} min", minutes),
+            NotificationAction::Snooze => String::from("Snooze 1 h"),
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "start-break" => Some(NotificationAction::StartBreak),
+            "snooze" => Some(NotificationAction::Snooze),
+            _ => key
+                .strip_prefix("postpone-")?
+                .parse()
+                .ok()
+                .map(|minutes| NotificationAction::Postpone { minutes }),
+        }
+    }
+
+    pub fn command(&self, now: DateTime<Utc>) -> IdleMonitorCommand {
+        match self {
+            NotificationAction::StartBreak => IdleMonitorCommand::TriggerBreak,
+            NotificationAction::Postpone { minutes } => {
+                IdleMonitorCommand::PostponeBreak(Duration::minutes(*minutes))
+            }
+            NotificationAction::Snooze => IdleMonitorCommand::Snooze(now + Duration::hours(1)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationUrgency {
+    Normal,
+    Critical,
+}
+
+// Each kind is shown at most once. Showing it again replaces the one on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    PreBreak,
+    BreakWarning,
+    StillRunning,
+}
+
+impl NotificationKind {
+    fn portal_id(&self) -> &'static str {
+        match self {
+            NotificationKind::PreBreak => "prebreak",
+            NotificationKind::BreakWarning => "break-warning",
+            NotificationKind::StillRunning => "still-running",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopNotification {
+    pub summary: String,
+    pub body: String,
+    pub urgency: NotificationUrgency,
+    pub actions: Vec<NotificationAction>,
+}
+
+// The portal is the only way out of the Flatpak sandbox. Outside of it, talking to the notification
+// server directly works with more desktops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationBackendKind {
+    Portal,
+    Freedesktop,
+}
+
+impl NotificationBackendKind {
+    pub fn detect() -> Self {
+        match Path::new("/.flatpak-info").exists() {
+            true => NotificationBackendKind::Portal,
+            false => NotificationBackendKind::Freedesktop,
+        }
+    }
+
+    fn connect(
+        &self,
+        connection: Connection,
+        on_action: impl Fn(NotificationAction) + Send + 'static,
+    ) -> zbus::Result<Box<dyn NotificationBackend>> {
+        Ok(match self {
+            NotificationBackendKind::Portal => {
+                Box::new(PortalNotifications::new(connection, on_action)?)
+            }
+            NotificationBackendKind::Freedesktop => {
+                Box::new(FreedesktopNotifications::new(connection, on_action))
+            }
+        })
+    }
+}
+
+trait NotificationBackend {
+    fn show(
+        &mut self,
+        kind: NotificationKind,
+        notification: &DesktopNotification,
+    ) -> zbus::Result<()>;
+
+    fn close(&mut self, kind: NotificationKind) -> zbus::Result<()>;
+}
+
+// Notification IDs are handed out by the server, and the action signal carries no more than
+// that ID. Knowing which IDs are ours keeps other apps' buttons from being mistaken for ours.
+type ShownNotifications = Arc<Mutex<HashMap<NotificationKind, u32>>>;
+
+struct FreedesktopNotifications {
+    connection: Connection,
+    shown_notifications: ShownNotifications,
+}
+
+impl FreedesktopNotifications {
+    fn new(
+        connection: Connection,
+        on_action: impl Fn(NotificationAction) + Send + 'static,
+    ) -> Self {
+        let shown_notifications = ShownNotifications::default();
+        match NotificationsProxyBlocking::new(&connection)
+            .and_then(|proxy| proxy.receive_action_invoked())
+        {
+            Ok(action_signals) => {
+                let shown_notifications = shown_notifications.clone();
+                thread::spawn(move || {
+                    dispatch_actions(action_signals, shown_notifications, on_action)
+                });
+            }
+            Err(error) => println!("Notification buttons won't work: {}", error),
+        }
+        FreedesktopNotifications {
+            connection,
+            shown_notifications,
+        }
+    }
+}
+
+impl NotificationBackend for FreedesktopNotifications {
+    fn show(
+        &mut self,
+        kind: NotificationKind,
+        notification: &DesktopNotification,
+    ) -> zbus::Result<()> {
+        let replaces_id = self
+            .shown_notifications
+            .lock()
+            .unwrap()
+            .get(&kind)
+            .copied()
+            .unwrap_or(0);
+        let id = show_notification(&self.connection, replaces_id, notification)?;
+        self.shown_notifications.lock().unwrap().insert(kind, id);
+        Ok(())
+    }
+
+    fn close(&mut self, kind: NotificationKind) -> zbus::Result<()> {
+        let Some(id) = self.shown_notifications.lock().unwrap().remove(&kind) else {
+            return Ok(());
+        };
+        NotificationsProxyBlocking::new(&self.connection)?.close_notification(id)
+    }
+}
+
+fn show_notification(
+    connection: &Connection,
+    replaces_id: u32,
+    notification: &DesktopNotification,
+) -> zbus::Result<u32> {
+    let actions: Vec<String> = notification
+        .actions
+        .iter()
+        .flat_map(|action| [action.key(), action.label()])
+        .collect();
+    let actions: Vec<&str> = actions.iter().map(String::as_str).collect();
+    let urgency: u8 = match notification.urgency {
+        NotificationUrgency::Normal => 1,
+        NotificationUrgency::Critical => 2,
+    };
+    let hints = HashMap::from([
+        ("urgency", Value::from(urgency)),
+        ("desktop-entry", Value::from(APP_ID)),
+    ]);
+    NotificationsProxyBlocking::new(connection)?.notify(
+        "Stretch Break",
+        replaces_id,
+        APP_ID,
+        &notification.summary,
+        &notification.body,
+        &actions,
+        hints,
+        -1,
+    )
+}
+
+fn dispatch_actions(
+    action_signals: ActionInvokedIterator,
+    shown_notifications: ShownNotifications,
+    on_action: impl Fn(NotificationAction),
+) {
+    for signal in action_signals {
+        let Ok(args) = signal.args() else {
+            continue;
+        };
+        let is_ours = shown_notifications
+            .lock()
+            .unwrap()
+            .values()
+            .any(|id| *id == args.id);
+        // Clicking the notification itself reports "default", which needs no handling.
+        if is_ours && let Some(action) = NotificationAction::from_key(&args.action_key) {
+            on_action(action);
+        }
+    }
+}
+
+struct PortalNotifications {
+    proxy: portal::NotificationProxyBlocking<'static>,
+}
+
+impl PortalNotifications {
+    fn new(
+        connection: Connection,
+        on_action: impl Fn(NotificationAction) + Send + 'static,
+    ) -> zbus::Result<Self> {
+        let proxy = portal::NotificationProxyBlocking::new(&connection)?;
+        let action_signals = proxy.receive_action_invoked()?;
+        thread::spawn(move || dispatch_portal_actions(action_signals, on_action));
+        Ok(PortalNotifications { proxy })
+    }
+}
+
+impl NotificationBackend for PortalNotifications {
+    fn show(
+        &mut self,
+        kind: NotificationKind,
+        notification: &DesktopNotification,
+    ) -> zbus::Result<()> {
+        let buttons: Vec<HashMap<&str, Value>> = notification
+            .actions
+            .iter()
+            .map(|action| {
+                HashMap::from([
+                    ("label", Value::from(action.label())),
+                    ("action", Value::from(action.key())),
+                ])
+            })
+            .collect();
+        let priority = match notification.urgency {
+            NotificationUrgency::Normal => "normal",
+            NotificationUrgency::Critical => "urgent",
+        };
+        let portal_notification = HashMap::from([
+            ("title", Value::from(notification.summary.as_str())),
+            ("body", Value::from(notification.body.as_str())),
+            ("priority", Value::from(priority)),
+            ("buttons", Value::from(buttons)),
+        ]);
+        // Reusing the ID replaces a notification that is still on screen
+        self.proxy
+            .add_notification(kind.portal_id(), portal_notification)
+    }
+
+    fn close(&mut self, kind: NotificationKind) -> zbus::Result<()> {
+        self.proxy.remove_notification(kind.portal_id())
+    }
+}
+
+// The portal only reports actions of this app's own notifications.
+fn dispatch_portal_actions(
+    action_signals: portal::ActionInvokedIterator,
+    on_action: impl Fn(NotificationAction),
+) {
+    for signal in action_signals {
+        let Ok(args) = signal.args() else {
+            continue;
+        };
+        if let Some(action) = NotificationAction::from_key(args.action) {
+            on_action(action);
+        }
+    }
+}
+
+enum NotifierRequest {
+    Show(NotificationKind, DesktopNotification),
+    Close(NotificationKind),
+}
+
+// Talks to the notification server from its own thread, and reports clicked buttons back.
+#[derive(Clone)]
+pub struct Notifier {
+    request_sender: Sender<NotifierRequest>,
+}
+
+impl Notifier {
+    pub fn spawn(on_action: impl Fn(NotificationAction) + Send + 'static) -> Self {
+        let (request_sender, request_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let backend_kind = NotificationBackendKind::detect();
+            let mut backend = match Connection::session()
+                .and_then(|connection| backend_kind.connect(connection, on_action))
+            {
+                Ok(backend) => backend,
+                Err(error) => {
+                    println!(
+                        "Could not set up {:?} notifications: {}",
+                        backend_kind, error
+                    );
+                    return;
+                }
+            };
+            for request in request_receiver {
+                let result = match request {
+                    NotifierRequest::Show(kind, notification) => backend.show(kind, &notification),
+                    NotifierRequest::Close(kind) => backend.close(kind),
+                };
+                if let Err(error) = result {
+                    println!("Could not update notification: {}", error);
+                }
+            }
+        });
+        Notifier { request_sender }
+    }
+
+    fn send(&self, request: NotifierRequest) {
+        if self.request_sender.send(request).is_err() {
+            println!("Notification thread is no longer running");
+        }
+    }
+
+    pub fn show(&self, kind: NotificationKind, notification: DesktopNotification) {
+        self.send(NotifierRequest::Show(kind, notification));
+    }
+
+    pub fn close(&self, kind: NotificationKind) {
+        self.send(NotifierRequest::Close(kind));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
+
+    use zbus::interface;
+    use zbus::zvariant::OwnedValue;
+
+    use super::*;
+    use crate::backend::test_bus::PrivateBus;
+
+    type ReceivedNotification = (u32, String, Vec<String>, u8);
+
+    struct FakeNotificationServer {
+        received: Arc<Mutex<Vec<ReceivedNotification>>>,
+    }
+
+    #[interface(name = "org.freedesktop.Notifications")]
+    impl FakeNotificationServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: &str,
+            replaces_id: u32,
+            _app_icon: &str,
+            summary: &str,
+            _body: &str,
+            actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let urgency = u8::try_from(&hints["urgency"]).unwrap();
+            let mut received = self.received.lock().unwrap();
+            received.push((replaces_id, summary.to_string(), actions, urgency));
+            received.len() as u32
+        }
+
+        fn close_notification(&self, _id: u32) {}
+    }
+
+    fn make_notification(actions: Vec<NotificationAction>) -> DesktopNotification {
+        DesktopNotification {
+            summary: String::from("Time to stretch"),
+            body: String::new(),
+            urgency: NotificationUrgency::Critical,
+            actions,
+        }
+    }
+
+    #[test]
+    fn sends_actions_as_key_and_label_pairs() {
+        let bus = PrivateBus::start();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let _server_connection = bus
+            .connect()
+            .name("org.freedesktop.Notifications")
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/Notifications",
+                FakeNotificationServer {
+                    received: received.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let connection = bus.connect().build().unwrap();
+
+        let notification = make_notification(vec![
+            NotificationAction::StartBreak,
+            NotificationAction::Postpone { minutes: 10 },
+            NotificationAction::Snooze,
+        ]);
+        let id = show_notification(&connection, 0, &notification).unwrap();
+        show_notification(&connection, id, &notification).unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            received[0],
+            (
+                0,
+                String::from("Time to stretch"),
+                vec![
+                    String::from("start-break"),
+                    String::from("Start break now"),
+                    String::from("postpone-10"),
+                    String::from("Postpone 10 min"),
+                    String::from("snooze"),
+                    String::from("Snooze 1 h"),
+                ],
+                2
+            )
+        );
+        assert_eq!(received[1].0, id);
+    }
+
+    #[test]
+    fn only_dispatches_actions_of_own_notifications() {
+        let bus = PrivateBus::start();
+        let server_connection = bus.connect().build().unwrap();
+        let connection = bus.connect().build().unwrap();
+        let shown_notifications =
+            ShownNotifications::new(Mutex::new(HashMap::from([(NotificationKind::PreBreak, 7)])));
+        let action_signals = NotificationsProxyBlocking::builder(&connection)
+            .destination(server_connection.unique_name().unwrap().to_owned())
+            .unwrap()
+            .build()
+            .unwrap()
+            .receive_action_invoked()
+            .unwrap();
+        let (action_sender, action_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            dispatch_actions(action_signals, shown_notifications, move |action| {
+                action_sender.send(action).unwrap();
+            })
+        });
+
+        for (id, action_key) in [(3, "snooze"), (7, "default"), (7, "postpone-10")] {
+            server_connection
+                .emit_signal(
+                    None::<()>,
+                    "/org/freedesktop/Notifications",
+                    "org.freedesktop.Notifications",
+                    "ActionInvoked",
+                    &(id as u32, action_key),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(
+            action_receiver.recv_timeout(StdDuration::from_secs(5)),
+            Ok(NotificationAction::Postpone { minutes: 10 })
+        );
+        assert!(
+            action_receiver
+                .recv_timeout(StdDuration::from_millis(200))
+                .is_err()
+        );
+    }
+
+    // The title, priority and button actions of a notification
+    type PortalNotification = (String, String, Vec<String>);
+
+    struct FakePortal {
+        shown: Arc<Mutex<HashMap<String, PortalNotification>>>,
+    }
+
+    #[interface(name = "org.freedesktop.portal.Notification")]
+    impl FakePortal {
+        fn add_notification(&self, id: String, notification: HashMap<String, OwnedValue>) {
+            let text =
+                |key: &str| String::try_from(notification[key].try_clone().unwrap()).unwrap();
+            let buttons: Vec<HashMap<String, OwnedValue>> = notification["buttons"]
+                .try_clone()
+                .unwrap()
+                .try_into()
+                .unwrap();
+            let button_actions = buttons
+                .into_iter()
+                .map(|button| String::try_from(button["action"].try_clone().unwrap()).unwrap())
+                .collect();
+            self.shown
+                .lock()
+                .unwrap()
+                .insert(id, (text("title"), text("priority"), button_actions));
+        }
+
+        fn remove_notification(&self, id: String) {
+            self.shown.lock().unwrap().remove(&id);
+        }
+    }
+
+    #[test]
+    fn portal_shows_and_withdraws_notifications_by_kind() {
+        let bus = PrivateBus::start();
+        let shown = Arc::new(Mutex::new(HashMap::new()));
+        let server_connection = bus
+            .connect()
+            .name("org.freedesktop.portal.Desktop")
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/portal/desktop",
+                FakePortal {
+                    shown: shown.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let (action_sender, action_receiver) = mpsc::channel();
+        let mut backend = NotificationBackendKind::Portal
+            .connect(bus.connect().build().unwrap(), move |action| {
+                action_sender.send(action).unwrap();
+            })
+            .unwrap();
+
+        backend
+            .show(
+                NotificationKind::PreBreak,
+                &make_notification(vec![NotificationAction::Postpone { minutes: 10 }]),
+            )
+            .unwrap();
+        assert_eq!(
+            shown.lock().unwrap()["prebreak"],
+            (
+                String::from("Time to stretch"),
+                String::from("urgent"),
+                vec![String::from("postpone-10")]
+            )
+        );
+
+        server_connection
+            .emit_signal(
+                None::<()>,
+                "/org/freedesktop/portal/desktop",
+                "org.freedesktop.portal.Notification",
+                "ActionInvoked",
+                &("prebreak", "postpone-10", Vec::<Value>::new()),
+            )
+            .unwrap();
+        assert_eq!(
+            action_receiver.recv_timeout(StdDuration::from_secs(5)),
+            Ok(NotificationAction::Postpone { minutes: 10 })
+        );
+
+        backend.close(NotificationKind::PreBreak).unwrap();
+        assert!(shown.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn actions_become_idle_monitor_commands() {
+        let now = Utc::now();
+        assert_eq!(
+            NotificationAction::Postpone { minutes: 10 }.command(now),
+            IdleMonitorCommand::PostponeBreak(Duration::minutes(10))
+        );
+        assert_eq!(
+            NotificationAction::Snooze.command(now),
+            IdleMonitorCommand::Snooze(now + Duration::hours(1))
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Local, Timelike};
+use rhai::{AST, Array, Dynamic, Engine, Map, Scope};
+
+use crate::backend::config::{ConfigError, get_config_folder};
+use crate::backend::history::{BreakOutcome, HistoryEntry};
+use crate::backend::idle_monitoring::IdleInfo;
+
+#[derive(Debug)]
+pub enum PolicyError {
+    Config(ConfigError),
+    Script { path: PathBuf, message: String },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyError::Config(error) => write!(f, "{}", error),
+            PolicyError::Script { path, message } => {
+                write!(f, "{} is not a valid script: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+// Decisions that a policy script can take over, by defining a function with this name. Each
+// one is called with the timer state and the break history, and must return quickly.
+const SHOULD_START_BREAK: &str = "should_start_break";
+const BREAK_LENGTH_SECS: &str = "break_length_secs";
+const IS_USER_IDLE: &str = "is_user_idle";
+
+// Runs the user's policy.rhai. Scripts can't touch files, the network or other processes, and
+// are stopped when they run for too long. A failing script falls back to the built-in behaviour.
+pub struct BreakPolicy {
+    engine: Engine,
+    // None when there is no script
+    ast: Option<AST>,
+}
+
+impl Default for BreakPolicy {
+    fn default() -> Self {
+        BreakPolicy {
+            engine: make_engine(),
+            ast: None,
+        }
+    }
+}
+
+fn make_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(100_000);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(10_000);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.disable_symbol("eval");
+    // `import` would otherwise load any script on disk.
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine
+}
+
+impl BreakPolicy {
+    pub fn get_policy_filename() -> Result<PathBuf, ConfigError> {
+        Ok(get_config_folder()?.join("policy.rhai"))
+    }
+
+    // A missing file means the built-in behaviour.
+    pub fn load_from_path(path: &Path) -> Result<Self, PolicyError> {
+        let script = match fs::read_to_string(path) {
+            Ok(script) => script,
+            Err(error) => {
+                return match ConfigError::io(path, error) {
+                    ConfigError::NotFound(_) => Ok(Self::default()),
+                    error => Err(PolicyError::Config(error)),
+                };
+            }
+        };
+        Self::from_script(&script).map_err(|message| PolicyError::Script {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    pub(crate) fn from_script(script: &str) -> Result<Self, String> {
+        let engine = make_engine();
+        let ast = engine.compile(script).map_err(|error| error.to_string())?;
+        Ok(BreakPolicy {
+            engine,
+            ast: Some(ast),
+        })
+    }
+
+    fn call<T: Clone + Send + Sync + 'static>(
+        &self,
+        name: &str,
+        idle_info: &IdleInfo,
+        history: &[HistoryEntry],
+    ) -> Option<T> {
+        let ast = self.ast.as_ref()?;
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == 2)
+        {
+            return None;
+        }
+        let arguments = (idle_info_to_map(idle_info), history_to_array(history));
+        match self
+            .engine
+            .call_fn::<T>(&mut Scope::new(), ast, name, arguments)
+        {
+            Ok(value) => Some(value),
+            Err(error) => {
+                println!("Break policy {} failed: {}", name, error);
+                None
+            }
+        }
+    }
+
+    // Asked when the timer runs out. Declining puts the break off for a minute, then asks again.
+    pub fn should_start_break(&self, idle_info: &IdleInfo, history: &[HistoryEntry]) -> bool {
+        self.call(SHOULD_START_BREAK, idle_info, history)
+            .unwrap_or(true)
+    }
+
+    // Asked as a break starts
+    pub fn break_length_secs(&self, idle_info: &IdleInfo, history: &[HistoryEntry]) -> i64 {
+        self.call::<i64>(BREAK_LENGTH_SECS, idle_info, history)
+            .filter(|secs| *secs > 0)
+            .unwrap_or(idle_info.break_length_secs)
+    }
+
+    // Asked every tick. Saying no makes idle time count as activity, like a video player would.
+    pub fn is_user_idle(&self, idle_info: &IdleInfo, history: &[HistoryEntry]) -> bool {
+        self.call(IS_USER_IDLE, idle_info, history).unwrap_or(true)
+    }
+}
+
+fn idle_info_to_map(idle_info: &IdleInfo) -> Dynamic {
+    let local_time = idle_info.last_checked.with_timezone(&Local);
+    let mut map = Map::new();
+    map.insert("mode".into(), idle_info.mode_name().into());
+    map.insert("presence".into(), idle_info.presence_name().into());
+    map.insert(
+        "idle_secs".into(),
+        (idle_info.idle_since_seconds as i64).into(),
+    );
+    map.insert(
+        "secs_until_break".into(),
+        idle_info.secs_until_break().into(),
+    );
+    map.insert(
+        "time_to_break_secs".into(),
+        idle_info.time_to_break_secs.into(),
+    );
+    map.insert(
+        "break_length_secs".into(),
+        idle_info.break_length_secs.into(),
+    );
+    map.insert(
+        "overrun_secs".into(),
+        idle_info.overrun.num_seconds().into(),
+    );
+    map.insert(
+        "postpone_count".into(),
+        (idle_info.postpone_count as i64).into(),
+    );
+    map.insert("reading_mode".into(), idle_info.reading_mode.into());
+    map.insert(
+        "timestamp".into(),
+        idle_info.last_checked.timestamp().into(),
+    );
+    // Local time of day, to plan around meetings and the end of the working day
+    map.insert(
+        "minute_of_day".into(),
+        ((local_time.hour() * 60 + local_time.minute()) as i64).into(),
+    );
+    map.insert(
+        "weekday".into(),
+        (local_time.weekday().num_days_from_monday() as i64 + 1).into(),
+    );
+    Dynamic::from_map(map)
+}
+
+fn history_to_array(history: &[HistoryEntry]) -> Dynamic {
+    let entries: Array = history
+        .iter()
+        .map(|entry| {
+            let (outcome, postponed_for_secs) = match entry.outcome {
+                BreakOutcome::Completed => ("completed", 0),
+                BreakOutcome::Skipped => ("skipped", 0),
+                BreakOutcome::Postponed { postponed_for_secs } => ("postponed", postponed_for_secs),
+                BreakOutcome::PostponedForMeeting { postponed_for_secs } => {
+                    ("postponed_for_meeting", postponed_for_secs)
+                }
+            };
+            let mut map = Map::new();
+            map.insert("timestamp".into(), entry.timestamp.timestamp().into());
+            map.insert("outcome".into(), outcome.into());
+            map.insert("postponed_for_secs".into(), postponed_for_secs.into());
+            Dynamic::from_map(map)
+        })
+        .collect();
+    Dynamic::from_array(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::backend::idle_monitoring::ModeState;
+
+    fn make_idle_info(overrun_secs: i64) -> IdleInfo {
+        let now = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        IdleInfo {
+            last_mode_state: ModeState::PreBreak { started_at: now },
+            overrun: Duration::seconds(overrun_secs),
+            ..IdleInfo::for_tests(now)
+        }
+    }
+
+    #[test]
+    fn without_a_script_nothing_changes() {
+        let policy = BreakPolicy::default();
+        let idle_info = make_idle_info(0);
+        assert!(policy.should_start_break(&idle_info, &[]));
+        assert_eq!(policy.break_length_secs(&idle_info, &[]), 60);
+        assert!(policy.is_user_idle(&idle_info, &[]));
+    }
+
+    #[test]
+    fn script_decides_with_timer_state_and_history() {
+        let policy = BreakPolicy::from_script(
+            r#"
+            fn break_length_secs(info, history) {
+                info.break_length_secs + info.overrun_secs / 2
+            }
+
+            fn should_start_break(info, history) {
+                let skipped = history.filter(|entry| entry.outcome == "skipped");
+                skipped.len() < 2
+            }
+            "#,
+        )
+        .unwrap();
+        let skipped = HistoryEntry {
+            timestamp: Utc::now(),
+            outcome: BreakOutcome::Skipped,
+        };
+
+        assert_eq!(policy.break_length_secs(&make_idle_info(120), &[]), 120);
+        assert!(policy.should_start_break(&make_idle_info(0), &[skipped]));
+        assert!(!policy.should_start_break(&make_idle_info(0), &[skipped, skipped]));
+        // Not defined, so the built-in answer
+        assert!(policy.is_user_idle(&make_idle_info(0), &[]));
+    }
+
+    #[test]
+    fn failing_scripts_fall_back_to_built_in_behaviour() {
+        let policy = BreakPolicy::from_script(
+            r#"
+            fn is_user_idle(info, history) { loop {} }
+            fn break_length_secs(info, history) { "long" }
+            fn should_start_break(info, history) { info.no_such_field.len() > 0 }
+            "#,
+        )
+        .unwrap();
+        let idle_info = make_idle_info(0);
+        assert!(policy.is_user_idle(&idle_info, &[]));
+        assert_eq!(policy.break_length_secs(&idle_info, &[]), 60);
+        assert!(policy.should_start_break(&idle_info, &[]));
+    }
+
+    #[test]
+    fn scripts_cannot_reach_outside() {
+        assert!(BreakPolicy::from_script(r#"eval("1 + 1")"#).is_err());
+        let module_path = std::env::temp_dir().join(format!(
+            "stretch-break-policy-module-{}.rhai",
+            std::process::id()
+        ));
+        fs::write(&module_path, "fn answer() { false }").unwrap();
+        let policy = BreakPolicy::from_script(&format!(
+            r#"fn is_user_idle(info, history) {{ import {:?} as helpers; helpers::answer() }}"#,
+            module_path.with_extension("").display().to_string()
+        ))
+        .unwrap();
+        assert!(policy.is_user_idle(&make_idle_info(0), &[]));
+        fs::remove_file(&module_path).unwrap();
+    }
+
+    #[test]
+    fn reports_invalid_scripts() {
+        let path = std::env::temp_dir().join(format!(
+            "stretch-break-policy-test-{}.rhai",
+            std::process::id()
+        ));
+        fs::write(&path, "fn should_start_break(info, history) {").unwrap();
+        assert!(matches!(
+            BreakPolicy::load_from_path(&path),
+            Err(PolicyError::Script { .. })
+        ));
+        assert!(BreakPolicy::load_from_path(&path.with_extension("missing")).is_ok());
+    }
+}
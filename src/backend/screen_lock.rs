@@ -0,0 +1,100 @@
+use std::thread;
+
+use zbus::blocking::Connection;
+use zbus::proxy;
+
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait LoginSession {
+    fn lock(&self) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.ScreenSaver",
+    default_service = "org.freedesktop.ScreenSaver",
+    default_path = "/org/freedesktop/ScreenSaver"
+)]
+trait ScreenSaver {
+    fn lock(&self) -> zbus::Result<()>;
+}
+
+fn lock_login_session(connection: &Connection) -> zbus::Result<()> {
+    LoginSessionProxyBlocking::new(connection)?.lock()
+}
+
+fn lock_screen_saver(connection: &Connection) -> zbus::Result<()> {
+    ScreenSaverProxyBlocking::new(connection)?.lock()
+}
+
+// Unlocking takes typing, which the idle monitor sees like any other input. Cutting the break
+// short this way therefore counts towards overrun without any extra bookkeeping.
+pub fn lock_screen() {
+    thread::spawn(|| {
+        // logind accepts the request even when no lock screen is listening, so the desktop's
+        // own ScreenSaver interface goes first and logind is only the fallback.
+        let result = Connection::session()
+            .and_then(|connection| lock_screen_saver(&connection))
+            .or_else(|_| {
+                Connection::system().and_then(|connection| lock_login_session(&connection))
+            });
+        if let Err(error) = result {
+            println!("Could not lock the screen: {}", error);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use zbus::interface;
+
+    use super::*;
+    use crate::backend::test_bus::PrivateBus;
+
+    struct FakeScreenSaver {
+        locked: Arc<AtomicBool>,
+    }
+
+    #[interface(name = "org.freedesktop.ScreenSaver")]
+    impl FakeScreenSaver {
+        fn lock(&self) {
+            self.locked.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn locks_through_the_screen_saver() {
+        let bus = PrivateBus::start();
+        let locked = Arc::new(AtomicBool::new(false));
+        let _screen_saver_connection = bus
+            .connect()
+            .name("org.freedesktop.ScreenSaver")
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/ScreenSaver",
+                FakeScreenSaver {
+                    locked: locked.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let connection = bus.connect().build().unwrap();
+
+        lock_screen_saver(&connection).unwrap();
+        assert!(locked.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn reports_missing_screen_saver() {
+        let bus = PrivateBus::start();
+        let connection = bus.connect().build().unwrap();
+
+        assert!(lock_screen_saver(&connection).is_err());
+    }
+}
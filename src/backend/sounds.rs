@@ -0,0 +1,305 @@
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+#[cfg(test)]
+use mockall::automock;
+use rodio::{Decoder, OutputStream, Sink};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::break_events::BreakEventKind;
+use crate::backend::config::{ConfigError, Sounds, get_config_folder};
+use crate::backend::history::BreakOutcome;
+use crate::backend::idle_monitoring::{IdleInfo, ModeState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundEvent {
+    PreBreak,
+    BreakStart,
+    Halfway,
+    BreakEnd,
+}
+
+impl SoundEvent {
+    pub const ALL: [SoundEvent; 4] = [
+        SoundEvent::PreBreak,
+        SoundEvent::BreakStart,
+        SoundEvent::Halfway,
+        SoundEvent::BreakEnd,
+    ];
+
+    fn file_stem(&self) -> &'static str {
+        match self {
+            SoundEvent::PreBreak => "pre_break",
+            SoundEvent::BreakStart => "break_start",
+            SoundEvent::Halfway => "halfway",
+            SoundEvent::BreakEnd => "break_end",
+        }
+    }
+
+    fn builtin_sound(&self) -> &'static [u8] {
+        match self {
+            SoundEvent::PreBreak => include_bytes!("../sounds/pre_break.wav"),
+            SoundEvent::BreakStart => include_bytes!("../sounds/break_start.wav"),
+            SoundEvent::Halfway => include_bytes!("../sounds/halfway.wav"),
+            SoundEvent::BreakEnd => include_bytes!("../sounds/break_end.wav"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sound {
+    Builtin(&'static [u8]),
+    File(PathBuf),
+}
+
+pub trait AbstractSoundPlayer {
+    // Volume goes from 0 for silent to 1 for the file's own loudness
+    fn play(&self, sound: Sound, volume: f32);
+}
+
+pub struct SoundPlayer;
+#[cfg_attr(test, automock)]
+impl AbstractSoundPlayer for SoundPlayer {
+    // Silently fails if audio isn't available, apart from a line in the log.
+    fn play(&self, sound: Sound, volume: f32) {
+        thread::spawn(move || {
+            fn helper(sound: Sound, volume: f32) -> Result<(), ()> {
+                let (_stream, handle) = OutputStream::try_default().map_err(|_| ())?;
+                let sink = Sink::try_new(&handle).map_err(|_| ())?;
+                sink.set_volume(volume);
+                match sound {
+                    Sound::Builtin(bytes) => sink
+                        .append(Decoder::new(BufReader::new(Cursor::new(bytes))).map_err(|_| ())?),
+                    Sound::File(path) => sink.append(
+                        Decoder::new(BufReader::new(File::open(path).map_err(|_| ())?))
+                            .map_err(|_| ())?,
+                    ),
+                }
+                sink.sleep_until_end();
+                Ok(())
+            }
+
+            let description = sound_description(&sound);
+            if helper(sound, volume).is_err() {
+                println!("Could not play {} sound", description);
+            }
+        });
+    }
+}
+
+fn sound_description(sound: &Sound) -> String {
+    match sound {
+        Sound::Builtin(_) => String::from("built-in"),
+        Sound::File(path) => path.display().to_string(),
+    }
+}
+
+// Which cues are due, going by how the mode changed since the last refresh.
+pub fn sound_events(previous: &IdleInfo, current: &IdleInfo) -> Vec<SoundEvent> {
+    match (previous.last_mode_state, current.last_mode_state) {
+        (ModeState::Normal { .. }, ModeState::PreBreak { .. }) => vec![SoundEvent::PreBreak],
+        (ModeState::Break { .. }, ModeState::Break { .. }) => {
+            let halfway = current.break_length_secs * 1000 / 2;
+            let progress_ms = |idle_info: &IdleInfo| match idle_info.last_mode_state {
+                ModeState::Break {
+                    progress_towards_finish,
+                    ..
+                } => progress_towards_finish.num_milliseconds(),
+                _ => 0,
+            };
+            match progress_ms(previous) < halfway && progress_ms(current) >= halfway {
+                true => vec![SoundEvent::Halfway],
+                false => Vec::new(),
+            }
+        }
+        (_, ModeState::Break { .. }) => vec![SoundEvent::BreakStart],
+        _ => Vec::new(),
+    }
+}
+
+// How a break ended can't be read from the state, so the end cue follows the break events.
+// Only a break that ran its course ends with a sound, not one that was skipped or postponed.
+pub fn break_event_sounds(kind: BreakEventKind) -> Vec<SoundEvent> {
+    match kind {
+        BreakEventKind::BreakEnded(BreakOutcome::Completed) => vec![SoundEvent::BreakEnd],
+        _ => Vec::new(),
+    }
+}
+
+// Plays the cues that are turned on, preferring the user's own sounds over the built-in ones.
+pub struct SoundCues<T: AbstractSoundPlayer> {
+    player: T,
+    sounds_folder: Option<PathBuf>,
+}
+
+impl<T: AbstractSoundPlayer> SoundCues<T> {
+    pub fn new(player: T, sounds_folder: Option<PathBuf>) -> Self {
+        SoundCues {
+            player,
+            sounds_folder,
+        }
+    }
+
+    pub fn get_sounds_folder() -> Result<PathBuf, ConfigError> {
+        Ok(get_config_folder()?.join("sounds"))
+    }
+
+    fn find_sound(&self, event: SoundEvent) -> Sound {
+        self.sounds_folder
+            .as_deref()
+            .and_then(|folder| find_user_sound(folder, event))
+            .map(Sound::File)
+            .unwrap_or(Sound::Builtin(event.builtin_sound()))
+    }
+
+    pub fn play_cues(&self, previous: &IdleInfo, current: &IdleInfo, settings: &Sounds) {
+        self.play(sound_events(previous, current), settings);
+    }
+
+    pub fn play_break_event_cues(&self, kind: BreakEventKind, settings: &Sounds) {
+        self.play(break_event_sounds(kind), settings);
+    }
+
+    fn play(&self, events: Vec<SoundEvent>, settings: &Sounds) {
+        let volume = settings.volume_percent.min(100) as f32 / 100.0;
+        for event in events {
+            if settings.events.contains(&event) {
+                self.player.play(self.find_sound(event), volume);
+            }
+        }
+    }
+}
+
+fn find_user_sound(folder: &Path, event: SoundEvent) -> Option<PathBuf> {
+    ["wav", "ogg", "flac"]
+        .into_iter()
+        .map(|extension| folder.join(format!("{}.{}", event.file_stem(), extension)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::backend::idle_monitoring::DebouncedIdleState;
+
+    fn make_idle_info(last_mode_state: ModeState) -> IdleInfo {
+        IdleInfo {
+            last_mode_state,
+            ..IdleInfo::for_tests(Utc::now())
+        }
+    }
+
+    fn normal() -> IdleInfo {
+        let now = Utc::now();
+        make_idle_info(ModeState::Normal {
+            progress_towards_break: Duration::seconds(1199),
+            progress_towards_reset: Duration::zero(),
+            idle_state: DebouncedIdleState::Active { active_since: now },
+        })
+    }
+
+    fn prebreak() -> IdleInfo {
+        make_idle_info(ModeState::PreBreak {
+            started_at: Utc::now(),
+        })
+    }
+
+    fn on_break(progress_secs: i64, user_is_idle: bool) -> IdleInfo {
+        let now = Utc::now();
+        make_idle_info(ModeState::Break {
+            progress_towards_finish: Duration::seconds(progress_secs),
+            idle_state: match user_is_idle {
+                true => DebouncedIdleState::Idle { idle_since: now },
+                false => DebouncedIdleState::Active { active_since: now },
+            },
+        })
+    }
+
+    #[test]
+    fn cues_follow_the_break() {
+        assert_eq!(
+            sound_events(&normal(), &prebreak()),
+            vec![SoundEvent::PreBreak]
+        );
+        assert_eq!(
+            sound_events(&prebreak(), &on_break(0, true)),
+            vec![SoundEvent::BreakStart]
+        );
+        assert_eq!(
+            sound_events(&on_break(20, true), &on_break(29, true)),
+            Vec::new()
+        );
+        assert_eq!(
+            sound_events(&on_break(29, true), &on_break(30, true)),
+            vec![SoundEvent::Halfway]
+        );
+        assert_eq!(
+            sound_events(&on_break(30, true), &on_break(31, true)),
+            Vec::new()
+        );
+        assert_eq!(sound_events(&on_break(60, true), &normal()), Vec::new());
+        assert_eq!(
+            break_event_sounds(BreakEventKind::BreakEnded(BreakOutcome::Completed)),
+            vec![SoundEvent::BreakEnd]
+        );
+    }
+
+    #[test]
+    fn skipped_break_does_not_sound_the_end() {
+        assert_eq!(
+            break_event_sounds(BreakEventKind::BreakEnded(BreakOutcome::Skipped)),
+            Vec::new()
+        );
+        assert_eq!(
+            break_event_sounds(BreakEventKind::BreakEnded(BreakOutcome::Postponed {
+                postponed_for_secs: 300
+            })),
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn plays_only_enabled_cues_at_the_set_volume() {
+        let mut player = MockSoundPlayer::new();
+        player
+            .expect_play()
+            .withf(|sound, volume| {
+                *sound == Sound::Builtin(SoundEvent::BreakStart.builtin_sound()) && *volume == 0.4
+            })
+            .times(1)
+            .return_const(());
+        let cues = SoundCues::new(player, None);
+        let settings = Sounds {
+            events: vec![SoundEvent::BreakStart],
+            volume_percent: 40,
+        };
+
+        cues.play_cues(&prebreak(), &on_break(0, true), &settings);
+        cues.play_cues(&normal(), &prebreak(), &settings);
+    }
+
+    #[test]
+    fn user_sounds_replace_builtin_ones() {
+        let folder =
+            std::env::temp_dir().join(format!("stretch-break-sounds-test-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("halfway.ogg"), b"").unwrap();
+        let cues = SoundCues::new(MockSoundPlayer::new(), Some(folder.clone()));
+
+        assert_eq!(
+            cues.find_sound(SoundEvent::Halfway),
+            Sound::File(folder.join("halfway.ogg"))
+        );
+        assert_eq!(
+            cues.find_sound(SoundEvent::BreakEnd),
+            Sound::Builtin(SoundEvent::BreakEnd.builtin_sound())
+        );
+    }
+}
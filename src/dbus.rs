@@ -1,17 +1,18 @@
-use std::{
-    error::Error,
-    sync::{Arc, Mutex, MutexGuard},
-};
-use zbus::{object_server::SignalEmitter, proxy};
+use std::error::Error;
+use zbus::{fdo, object_server::SignalEmitter, proxy};
 
 use chrono::{DateTime, Duration, Local, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 use tokio::sync::watch::{Receiver, Sender};
 use zbus::{connection, interface};
 
 use crate::{
-    backend::idle_monitoring::{
-        Clock, DebouncedIdleState, IdleChecker, IdleInfo, IdleMonitor, ModeState, PresenceMode,
+    backend::{
+        break_warnings,
+        config::Config,
+        idle_monitoring::{DebouncedIdleState, IdleInfo, ModeState, PresenceMode},
+        monitor_actor::{IdleMonitorCommand, IdleMonitorError, IdleMonitorHandle},
     },
     frontend::formatting::{format_timedelta_timecode, format_timer_timecode},
 };
@@ -24,21 +25,22 @@ pub(crate) struct WidgetInfo {
     pub(crate) countdown_to_reset_value: String,
     pub(crate) overrun_value: String,
     pub(crate) presence_mode: PresenceMode,
-    snoozed_until_time: Option<String>,
-    reading_mode: bool,
+    pub(crate) snoozed_until_time: Option<String>,
+    pub(crate) reading_mode: bool,
+    pub(crate) active_profile: String,
+    pub(crate) profiles: Vec<String>,
 }
 
-fn get_widget_info(idle_info: &IdleInfo) -> WidgetInfo {
+pub(crate) fn get_widget_info(idle_info: &IdleInfo, config: &Config) -> WidgetInfo {
     let overrun_value = if idle_info.overrun == Duration::seconds(0) {
         String::from("")
     } else {
         match idle_info.last_mode_state {
-            ModeState::Break { idle_state, .. } => match idle_state {
-                DebouncedIdleState::Idle { .. } | DebouncedIdleState::IdleGoingToActive { .. } => {
-                    String::from("")
-                }
-                _ => format_timedelta_timecode(&idle_info.overrun),
-            },
+            ModeState::Break {
+                idle_state:
+                    DebouncedIdleState::Idle { .. } | DebouncedIdleState::IdleGoingToActive { .. },
+                ..
+            } => String::from(""),
             _ => format_timedelta_timecode(&idle_info.overrun),
         }
     };
@@ -92,14 +94,18 @@ fn get_widget_info(idle_info: &IdleInfo) -> WidgetInfo {
             PresenceMode::Muted => None,
         },
         reading_mode: idle_info.reading_mode,
+        active_profile: config.active_profile.clone(),
+        profiles: config.profile_names(),
     }
 }
 
 #[tokio::main]
 pub async fn run_server(
     mut idle_info_recv: Receiver<IdleInfo>,
+    mut config_recv: Receiver<Config>,
+    mut break_warning_recv: broadcast::Receiver<break_warnings::BreakWarning>,
     show_main_window_send: Sender<bool>,
-    idle_monitor_arc: Arc<Mutex<IdleMonitor<IdleChecker, Clock>>>,
+    idle_monitor: IdleMonitorHandle,
 ) -> Result<(), Box<dyn Error>> {
     let conn = connection::Builder::session()?
         .name("io.github.pieterdd.StretchBreak.Core")?
@@ -107,7 +113,8 @@ pub async fn run_server(
             "/io/github/pieterdd/StretchBreak/Core",
             DBusServer {
                 show_main_window_send,
-                idle_monitor_arc,
+                idle_monitor,
+                config_recv: config_recv.clone(),
             },
         )?
         .build()
@@ -115,28 +122,47 @@ pub async fn run_server(
 
     loop {
         let idle_info = *idle_info_recv.borrow_and_update();
-        let serialized_idle_info = serde_json::to_string(&get_widget_info(&idle_info))
-            .expect("Serde JSON conversion failed");
+        let widget_info = get_widget_info(&idle_info, &config_recv.borrow_and_update());
+        let serialized_idle_info =
+            serde_json::to_string(&widget_info).expect("Serde JSON conversion failed");
         conn.object_server()
             .interface("/io/github/pieterdd/StretchBreak/Core")
             .await?
             .widget_info_updated(serialized_idle_info)
             .await?;
 
-        idle_info_recv.changed().await?;
+        tokio::select! {
+            changed = idle_info_recv.changed() => changed?,
+            changed = config_recv.changed() => changed?,
+            // A lagging receiver only missed warnings that are stale by now
+            Ok(break_warning) = break_warning_recv.recv() => {
+                conn.object_server()
+                    .interface("/io/github/pieterdd/StretchBreak/Core")
+                    .await?
+                    .break_warning(break_warning.secs_until_break)
+                    .await?;
+            }
+        }
     }
 }
 
 struct DBusServer {
     show_main_window_send: Sender<bool>,
-    idle_monitor_arc: Arc<Mutex<IdleMonitor<IdleChecker, Clock>>>,
+    idle_monitor: IdleMonitorHandle,
+    config_recv: Receiver<Config>,
 }
 
-impl DBusServer {
-    fn _unlock_monitor(&self) -> MutexGuard<'_, IdleMonitor<IdleChecker, Clock>> {
-        self.idle_monitor_arc
-            .lock()
-            .expect("Unlocking idle monitor failed")
+impl From<IdleMonitorError> for fdo::Error {
+    fn from(error: IdleMonitorError) -> Self {
+        match error {
+            IdleMonitorError::Gone => fdo::Error::Failed(error.to_string()),
+            IdleMonitorError::UnknownProfile(_)
+            | IdleMonitorError::InvalidPostpone
+            | IdleMonitorError::WrongConfirmation => fdo::Error::InvalidArgs(error.to_string()),
+            IdleMonitorError::SkipNotAllowed | IdleMonitorError::NoPostponesLeft => {
+                fdo::Error::AccessDenied(error.to_string())
+            }
+        }
     }
 }
 
@@ -148,19 +174,30 @@ impl DBusServer {
 pub trait DBusApp {
     fn reveal_window(&self) -> zbus::Result<()>;
     fn get_widget_info(&self) -> zbus::Result<String>;
+    fn get_idle_info(&self) -> zbus::Result<String>;
     fn unmute(&self) -> zbus::Result<()>;
     fn mute(&self) -> zbus::Result<()>;
     fn snooze_for_minutes(&self, num_minutes: i64) -> zbus::Result<()>;
     fn trigger_break(&self) -> zbus::Result<()>;
     fn set_reading_mode(&self, value: bool) -> zbus::Result<()>;
+    fn use_profile(&self, name: &str) -> zbus::Result<()>;
+    fn skip_break(&self) -> zbus::Result<()>;
+    fn postpone_break(&self, minutes: i64) -> zbus::Result<()>;
+    fn set_strict_mode(&self, enabled: bool, confirmation: &str) -> zbus::Result<()>;
 }
 
 #[interface(name = "io.github.pieterdd.StretchBreak.Core", proxy())]
 impl DBusServer {
-    fn get_widget_info(&self) -> String {
-        let monitor = self._unlock_monitor();
-        let widget_info = get_widget_info(&monitor.get_last_idle_info());
-        serde_json::to_string(&widget_info).expect("Serde JSON conversion failed")
+    async fn get_widget_info(&self) -> fdo::Result<String> {
+        let idle_info = self.idle_monitor.get_last_idle_info().await?;
+        let widget_info = get_widget_info(&idle_info, &self.config_recv.borrow());
+        Ok(serde_json::to_string(&widget_info).expect("Serde JSON conversion failed"))
+    }
+
+    // The raw timer state, for clients that want more detail than the widget info
+    async fn get_idle_info(&self) -> fdo::Result<String> {
+        let idle_info = self.idle_monitor.get_last_idle_info().await?;
+        Ok(serde_json::to_string(&idle_info).expect("Serde JSON conversion failed"))
     }
 
     #[zbus(signal)]
@@ -169,6 +206,13 @@ impl DBusServer {
         serialized_idle_info: String,
     ) -> zbus::Result<()>;
 
+    // Sent ahead of a break for each of the configured break_warning_minutes.
+    #[zbus(signal)]
+    async fn break_warning(
+        signal_emitter: &SignalEmitter<'_>,
+        secs_until_break: i64,
+    ) -> zbus::Result<()>;
+
     fn toggle_window(&self) {
         // Deprecated - remove in 0.1.7
         self.reveal_window();
@@ -178,32 +222,78 @@ impl DBusServer {
         self.show_main_window_send.send(true).expect("Send failed");
     }
 
-    fn mute(&self) {
-        let mut monitor = self._unlock_monitor();
-        monitor.mute();
+    async fn mute(&self) -> fdo::Result<()> {
+        self.idle_monitor.request(IdleMonitorCommand::Mute).await?;
+        Ok(())
     }
 
-    fn snooze_for_minutes(&self, num_minutes: i64) {
+    async fn snooze_for_minutes(&self, num_minutes: i64) -> fdo::Result<()> {
         let unmute_time = Utc::now()
             .checked_add_signed(TimeDelta::minutes(num_minutes))
             .unwrap();
-        let mut monitor = self._unlock_monitor();
-        monitor.snooze(unmute_time);
+        self.idle_monitor
+            .request(IdleMonitorCommand::Snooze(unmute_time))
+            .await?;
+        Ok(())
+    }
+
+    async fn unmute(&self) -> fdo::Result<()> {
+        self.idle_monitor
+            .request(IdleMonitorCommand::Unmute)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_reading_mode(&self, value: bool) -> fdo::Result<()> {
+        self.idle_monitor
+            .request(IdleMonitorCommand::SetReadingMode(value))
+            .await?;
+        Ok(())
+    }
+
+    async fn trigger_break(&self) -> fdo::Result<()> {
+        self.idle_monitor
+            .request(IdleMonitorCommand::TriggerBreak)
+            .await?;
+        Ok(())
     }
 
-    fn unmute(&self) {
-        let mut monitor = self._unlock_monitor();
-        monitor.unmute();
+    async fn use_profile(&self, name: String) -> fdo::Result<()> {
+        self.idle_monitor
+            .request(IdleMonitorCommand::UseProfile(name))
+            .await?;
+        Ok(())
+    }
+
+    async fn skip_break(&self) -> fdo::Result<()> {
+        self.idle_monitor
+            .request(IdleMonitorCommand::SkipBreak)
+            .await?;
+        Ok(())
     }
 
-    fn set_reading_mode(&self, value: bool) {
-        let mut monitor = self._unlock_monitor();
-        monitor.set_reading_mode(value);
+    async fn postpone_break(&self, minutes: i64) -> fdo::Result<()> {
+        if minutes <= 0 {
+            return Err(fdo::Error::InvalidArgs(String::from(
+                "minutes must be positive",
+            )));
+        }
+        self.idle_monitor
+            .request(IdleMonitorCommand::PostponeBreak(TimeDelta::minutes(
+                minutes,
+            )))
+            .await?;
+        Ok(())
     }
 
-    fn trigger_break(&self) {
-        let mut monitor = self._unlock_monitor();
-        monitor.trigger_break();
+    async fn set_strict_mode(&self, enabled: bool, confirmation: String) -> fdo::Result<()> {
+        self.idle_monitor
+            .request(IdleMonitorCommand::SetStrictMode {
+                enabled,
+                confirmation,
+            })
+            .await?;
+        Ok(())
     }
 }
 
@@ -212,9 +302,12 @@ mod tests {
     use chrono::{DateTime, Duration, Local, TimeDelta, TimeZone, Utc};
 
     use crate::{
-        backend::idle_monitoring::{
-            DEFAULT_BREAK_LENGTH_SECS, DEFAULT_TIME_TO_BREAK_SECS, DebouncedIdleState, IdleInfo,
-            ModeState, PresenceMode,
+        backend::{
+            config::{Config, DEFAULT_PROFILE_NAME},
+            idle_monitoring::{
+                DEFAULT_BREAK_LENGTH_SECS, DEFAULT_TIME_TO_BREAK_SECS, DebouncedIdleState,
+                IdleInfo, ModeState, PresenceMode, REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            },
         },
         dbus::{SECS_THRESHOLD_TO_SHOW_RESET_COUNTDOWN, WidgetInfo, get_widget_info},
     };
@@ -236,10 +329,13 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         assert_eq!(
-            get_widget_info(&info),
+            get_widget_info(&info, &Config::default()),
             WidgetInfo {
                 normal_timer_value: String::from("19:29"),
                 countdown_to_reset_value: String::from(""),
@@ -247,6 +343,8 @@ mod tests {
                 presence_mode: PresenceMode::Active,
                 snoozed_until_time: None,
                 reading_mode: false,
+                active_profile: String::from(DEFAULT_PROFILE_NAME),
+                profiles: Config::default().profile_names(),
             }
         )
     }
@@ -264,10 +362,13 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(1_000),
         };
         assert_eq!(
-            get_widget_info(&info),
+            get_widget_info(&info, &Config::default()),
             WidgetInfo {
                 normal_timer_value: String::from(""),
                 countdown_to_reset_value: String::from(""),
@@ -275,6 +376,8 @@ mod tests {
                 presence_mode: PresenceMode::Active,
                 snoozed_until_time: None,
                 reading_mode: false,
+                active_profile: String::from(DEFAULT_PROFILE_NAME),
+                profiles: Config::default().profile_names(),
             }
         )
     }
@@ -296,10 +399,13 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(1_000),
         };
         assert_eq!(
-            get_widget_info(&info),
+            get_widget_info(&info, &Config::default()),
             WidgetInfo {
                 normal_timer_value: String::from("19:58"),
                 countdown_to_reset_value: String::from("1:15"),
@@ -307,6 +413,8 @@ mod tests {
                 presence_mode: PresenceMode::Active,
                 snoozed_until_time: None,
                 reading_mode: false,
+                active_profile: String::from(DEFAULT_PROFILE_NAME),
+                profiles: Config::default().profile_names(),
             }
         )
     }
@@ -329,10 +437,13 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(0_000),
         };
         assert_eq!(
-            get_widget_info(&info),
+            get_widget_info(&info, &Config::default()),
             WidgetInfo {
                 normal_timer_value: String::from("19:29"),
                 countdown_to_reset_value: String::from(""),
@@ -343,6 +454,8 @@ mod tests {
                     DateTime::<Local>::from(snoozed_until_time).format("%R"),
                 )),
                 reading_mode: false,
+                active_profile: String::from(DEFAULT_PROFILE_NAME),
+                profiles: Config::default().profile_names(),
             }
         )
     }
@@ -363,10 +476,13 @@ mod tests {
             reading_mode: false,
             time_to_break_secs: DEFAULT_TIME_TO_BREAK_SECS,
             break_length_secs: DEFAULT_BREAK_LENGTH_SECS,
+            prebreak_idle_secs: REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+            postpone_count: 0,
+            last_postpone: None,
             overrun: Duration::milliseconds(1_000),
         };
         assert_eq!(
-            get_widget_info(&info),
+            get_widget_info(&info, &Config::default()),
             WidgetInfo {
                 normal_timer_value: String::from(""),
                 countdown_to_reset_value: String::from("1:25"),
@@ -374,6 +490,8 @@ mod tests {
                 presence_mode: PresenceMode::Active,
                 snoozed_until_time: None,
                 reading_mode: false,
+                active_profile: String::from(DEFAULT_PROFILE_NAME),
+                profiles: Config::default().profile_names(),
             }
         )
     }
@@ -1,15 +1,42 @@
-use crate::backend::idle_monitoring::{Clock, IdleChecker, IdleInfo, IdleMonitor, ModeState};
+use crate::backend::breathing::{BreathingPattern, BreathingPhase, pacer_elapsed};
+use crate::backend::config::StrictMode;
+use crate::backend::exercises::{Exercise, Illustration};
+use crate::backend::idle_monitoring::{IdleInfo, ModeState};
+use crate::backend::monitor_actor::{IdleMonitorCommand, IdleMonitorHandle};
+use crate::frontend::formatting::format_postpone_duration;
 use crate::icons::icon_names;
-use chrono::TimeDelta;
-use gtk::prelude::{BoxExt, ButtonExt, GtkWindowExt, OrientableExt, WidgetExt};
+use chrono::{TimeDelta, Utc};
+use gtk::prelude::{
+    BoxExt, ButtonExt, DrawingAreaExt, DrawingAreaExtManual, GtkWindowExt, OrientableExt,
+    PopoverExt, WidgetExt, WidgetExtManual,
+};
 use relm4::{Component, ComponentParts};
 use relm4::{ComponentSender, RelmWidgetExt};
-use std::sync::{Arc, Mutex};
+use std::f64::consts::PI;
 use std::thread::sleep;
 use std::time::Duration;
+use tokio::sync::watch::Receiver;
+
+pub const BREAK_OVERLAY_CSS: &str = "
+window.break-overlay {
+    background-color: rgba(0, 0, 0, 0.85);
+    color: white;
+}
+window.break-overlay label {
+    font-size: 150%;
+}
+";
 
 pub struct BreakWindowInit {
-    pub idle_monitor_arc: Arc<Mutex<IdleMonitor<IdleChecker, Clock>>>,
+    pub idle_monitor: IdleMonitorHandle,
+    pub idle_info_recv: Receiver<IdleInfo>,
+    pub strict_mode: StrictMode,
+    pub postpone_minutes: Vec<i64>,
+    pub exercise: Option<Exercise>,
+    pub breathing_pattern: Option<BreathingPattern>,
+    // Covers this monitor instead of opening a small window.
+    pub fullscreen_on: Option<gtk::gdk::Monitor>,
+    pub show_controls: bool,
 }
 
 #[derive(Debug)]
@@ -20,14 +47,19 @@ pub enum BreakWindowCmd {
 #[derive(Debug)]
 pub enum BreakWindowMsg {
     Update,
-    Postpone,
+    Postpone { minutes: i64 },
     Skip,
 }
 
 pub struct BreakWindow {
-    idle_monitor_arc: Arc<Mutex<IdleMonitor<IdleChecker, Clock>>>,
+    idle_monitor: IdleMonitorHandle,
+    idle_info_recv: Receiver<IdleInfo>,
     last_idle_info: IdleInfo,
     user_is_active: bool,
+    strict_mode: StrictMode,
+    default_postpone_minutes: i64,
+    show_controls: bool,
+    breathing_pattern: Option<BreathingPattern>,
 }
 
 #[relm4::component(pub)]
@@ -53,6 +85,20 @@ impl Component for BreakWindow {
                 gtk::Image {
                     set_icon_name: Some(icon_names::TIMER),
                     set_pixel_size: 80,
+                    set_visible: illustration.is_none() && init.breathing_pattern.is_none(),
+                },
+
+                #[local]
+                pacer -> gtk::DrawingArea {
+                    set_content_width: 160,
+                    set_content_height: 160,
+                    set_visible: init.breathing_pattern.is_some(),
+                },
+
+                gtk::Picture {
+                    set_paintable: illustration.as_ref(),
+                    set_visible: illustration.is_some(),
+                    set_size_request: (160, 160),
                 },
 
                 gtk::Box {
@@ -80,18 +126,48 @@ impl Component for BreakWindow {
                         }
                     },
 
+                    gtk::Label {
+                        #[watch]
+                        set_markup: &format!("<b>{}</b>", model.describe_breathing_phase()),
+                        set_halign: gtk::Align::Start,
+                        set_visible: init.breathing_pattern.is_some(),
+                    },
+
+                    gtk::Label {
+                        set_markup: &exercise_title,
+                        set_halign: gtk::Align::Start,
+                        set_visible: init.exercise.is_some(),
+                    },
+
+                    gtk::Label {
+                        set_label: &exercise_instructions,
+                        set_halign: gtk::Align::Start,
+                        set_xalign: 0.0,
+                        set_wrap: true,
+                        set_max_width_chars: 40,
+                        set_visible: init.exercise.is_some(),
+                    },
+
                     gtk::Box {
                         set_orientation: gtk::Orientation::Horizontal,
                         set_spacing: 10,
                         set_halign: gtk::Align::Start,
+                        set_visible: model.show_controls,
 
-                        gtk::Button {
-                            set_label: "Postpone",
-                            connect_clicked => BreakWindowMsg::Postpone,
+                        adw::SplitButton {
+                            #[watch]
+                            set_label: &model.postpone_label(),
+                            #[watch]
+                            set_sensitive: model.strict_mode.postpones_left(model.last_idle_info.postpone_count) != Some(0),
+                            set_popover: Some(&postpone_popover),
+                            connect_clicked[sender, minutes = model.default_postpone_minutes] => move |_| {
+                                sender.input(BreakWindowMsg::Postpone { minutes });
+                            },
                         },
 
                         gtk::Button {
                             set_label: "Skip",
+                            set_visible: !model.strict_mode.enabled,
                             connect_clicked => BreakWindowMsg::Skip,
                         },
                     }
@@ -105,14 +181,74 @@ impl Component for BreakWindow {
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let last_idle_info = init.idle_monitor_arc.lock().unwrap().get_last_idle_info();
+        let last_idle_info = *init.idle_info_recv.borrow();
+        let illustration = init
+            .exercise
+            .as_ref()
+            .and_then(|exercise| exercise.illustration.as_ref())
+            .and_then(load_illustration);
+        let (exercise_title, exercise_instructions) = match &init.exercise {
+            Some(exercise) => (
+                format!("<b>{}</b>", gtk::glib::markup_escape_text(&exercise.title)),
+                exercise.instructions.clone(),
+            ),
+            None => (String::new(), String::new()),
+        };
+
+        let pacer = gtk::DrawingArea::new();
+        if let Some(breathing_pattern) = init.breathing_pattern {
+            let idle_info_recv = init.idle_info_recv.clone();
+            pacer.set_draw_func(move |_, context, width, height| {
+                let idle_info = *idle_info_recv.borrow();
+                let pacer_state = breathing_pattern.pacer_state(
+                    pacer_elapsed(&idle_info, Utc::now()),
+                    TimeDelta::seconds(idle_info.break_length_secs),
+                );
+                draw_pacer(context, width, height, pacer_state.fullness);
+            });
+            pacer.add_tick_callback(|pacer, _| {
+                pacer.queue_draw();
+                gtk::glib::ControlFlow::Continue
+            });
+        }
+
+        // The remaining choices, in the menu next to the postpone button.
+        let postpone_popover = gtk::Popover::new();
+        let postpone_options = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        for &minutes in &init.postpone_minutes {
+            let option = gtk::Button::builder()
+                .label(format!("{} min", minutes))
+                .css_classes(["flat"])
+                .build();
+            let sender = sender.clone();
+            let popover = postpone_popover.clone();
+            option.connect_clicked(move |_| {
+                popover.popdown();
+                sender.input(BreakWindowMsg::Postpone { minutes });
+            });
+            postpone_options.append(&option);
+        }
+        postpone_popover.set_child(Some(&postpone_options));
+
         let model = BreakWindow {
-            idle_monitor_arc: init.idle_monitor_arc,
+            idle_monitor: init.idle_monitor,
+            idle_info_recv: init.idle_info_recv,
             last_idle_info,
             user_is_active: false,
+            strict_mode: init.strict_mode,
+            default_postpone_minutes: init.postpone_minutes.first().copied().unwrap_or(1),
+            show_controls: init.show_controls,
+            breathing_pattern: init.breathing_pattern,
         };
         let widgets = view_output!();
 
+        if let Some(monitor) = init.fullscreen_on {
+            // Window managers won't fullscreen a window with a fixed size
+            root.set_resizable(true);
+            root.add_css_class("break-overlay");
+            root.fullscreen_on_monitor(&monitor);
+        }
+
         sender.input(BreakWindowMsg::Update);
         ComponentParts { model, widgets }
     }
@@ -140,14 +276,14 @@ impl Component for BreakWindow {
                     }
                 }
             }
-            BreakWindowMsg::Postpone => {
-                self.idle_monitor_arc
-                    .lock()
-                    .unwrap()
-                    .postpone_break(TimeDelta::minutes(1));
+            BreakWindowMsg::Postpone { minutes } => {
+                self.idle_monitor
+                    .send(IdleMonitorCommand::PostponeBreak(TimeDelta::minutes(
+                        minutes,
+                    )));
             }
             BreakWindowMsg::Skip => {
-                self.idle_monitor_arc.lock().unwrap().skip_break();
+                self.idle_monitor.send(IdleMonitorCommand::SkipBreak);
             }
         }
     }
@@ -158,10 +294,75 @@ impl Component for BreakWindow {
         sender: ComponentSender<Self>,
         _root: &Self::Root,
     ) {
-        self.last_idle_info = self.idle_monitor_arc.lock().unwrap().get_last_idle_info();
+        self.last_idle_info = *self.idle_info_recv.borrow();
         if let ModeState::Break { idle_state, .. } = self.last_idle_info.last_mode_state {
             self.user_is_active = idle_state.is_user_active();
         }
         sender.input(BreakWindowMsg::Update);
     }
 }
+
+impl BreakWindow {
+    fn describe_breathing_phase(&self) -> &'static str {
+        let Some(breathing_pattern) = self.breathing_pattern else {
+            return "";
+        };
+        let pacer_state = breathing_pattern.pacer_state(
+            pacer_elapsed(&self.last_idle_info, Utc::now()),
+            TimeDelta::seconds(self.last_idle_info.break_length_secs),
+        );
+        match pacer_state.phase {
+            BreathingPhase::BreatheIn => "Breathe in",
+            BreathingPhase::HoldIn | BreathingPhase::HoldOut => "Hold",
+            BreathingPhase::BreatheOut => "Breathe out",
+        }
+    }
+
+    // Shows what strict mode will actually grant, which can be less than was asked for.
+    fn postpone_label(&self) -> String {
+        let postpone_count = self.last_idle_info.postpone_count;
+        let requested = TimeDelta::minutes(self.default_postpone_minutes);
+        let granted = self
+            .strict_mode
+            .allowed_postpone(requested, postpone_count, self.last_idle_info.last_postpone)
+            .unwrap_or(requested);
+        match self.strict_mode.postpones_left(postpone_count) {
+            Some(postpones_left) => format!(
+                "Postpone {} ({} left)",
+                format_postpone_duration(&granted),
+                postpones_left
+            ),
+            None => format!("Postpone {}", format_postpone_duration(&granted)),
+        }
+    }
+}
+
+// A disc that grows while breathing in and shrinks while breathing out
+fn draw_pacer(context: &gtk::cairo::Context, width: i32, height: i32, fullness: f64) {
+    let (center_x, center_y) = (width as f64 / 2.0, height as f64 / 2.0);
+    let max_radius = center_x.min(center_y);
+    context.set_source_rgba(0.21, 0.52, 0.89, 0.25);
+    context.arc(center_x, center_y, max_radius, 0.0, 2.0 * PI);
+    context.fill().ok();
+    context.set_source_rgb(0.21, 0.52, 0.89);
+    context.arc(
+        center_x,
+        center_y,
+        max_radius * (0.3 + 0.7 * fullness),
+        0.0,
+        2.0 * PI,
+    );
+    context.fill().ok();
+}
+
+fn load_illustration(illustration: &Illustration) -> Option<gtk::gdk::Texture> {
+    let result = match illustration {
+        Illustration::Builtin(svg) => {
+            gtk::gdk::Texture::from_bytes(&gtk::glib::Bytes::from_static(svg.as_bytes()))
+        }
+        Illustration::File(path) => gtk::gdk::Texture::from_filename(path),
+    };
+    result
+        .inspect_err(|error| println!("Could not load exercise illustration: {}", error))
+        .ok()
+}
@@ -17,11 +17,20 @@ pub fn format_timer_timecode(progress: TimeDelta, full_length_in_secs: i64) -> S
     }
 }
 
+// For postpones, which strict mode can cut down to less than a minute
+pub fn format_postpone_duration(timedelta: &TimeDelta) -> String {
+    match (timedelta.num_minutes(), timedelta.num_seconds() % 60) {
+        (minutes, 0) => format!("{} min", minutes),
+        (0, seconds) => format!("{} s", seconds),
+        (minutes, seconds) => format!("{} min {} s", minutes, seconds),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::TimeDelta;
 
-    use super::format_timedelta_timecode;
+    use super::{format_postpone_duration, format_timedelta_timecode};
 
     #[test]
     fn format_timedelta_below_60s() {
@@ -38,4 +47,14 @@ mod tests {
             String::from("7:54")
         );
     }
+
+    #[test]
+    fn format_postpone_durations() {
+        assert_eq!(format_postpone_duration(&TimeDelta::minutes(5)), "5 min");
+        assert_eq!(format_postpone_duration(&TimeDelta::seconds(30)), "30 s");
+        assert_eq!(
+            format_postpone_duration(&TimeDelta::seconds(450)),
+            "7 min 30 s"
+        );
+    }
 }
@@ -1,24 +1,39 @@
 use std::cmp::min;
 use std::process;
-use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread::sleep;
 use std::time::Duration;
 
 use crate::APP_ID;
-use crate::backend::idle_monitoring::{
-    Clock, DebouncedIdleState, IdleChecker, IdleInfo, IdleMonitor, ModeState, PresenceMode,
-    REQUIRED_PREBREAK_IDLE_STREAK_SECONDS,
+use crate::backend::break_warnings::BreakWarning;
+use crate::backend::config::{
+    BreakContent, BreakStyle, Config, NotificationStyle, STRICT_MODE_EXIT_PHRASE,
+    format_minutes_list, is_strict_mode_exit_phrase, parse_minutes_list,
 };
+use crate::backend::exercises::{ExerciseCategory, ExerciseLibrary};
+use crate::backend::idle_monitoring::{DebouncedIdleState, IdleInfo, ModeState, PresenceMode};
+use crate::backend::inhibitors::{Inhibitor, describe_inhibiting_apps};
+use crate::backend::monitor_actor::{IdleMonitorCommand, IdleMonitorHandle};
+use crate::backend::notifications::{
+    DesktopNotification, NotificationAction, NotificationKind, NotificationUrgency, Notifier,
+};
+use crate::backend::sounds::SoundEvent;
 use crate::frontend::formatting::format_timer_timecode;
 use crate::icons::icon_names;
-use adw::prelude::{ActionRowExt, AdwDialogExt, PreferencesRowExt};
+use adw::prelude::{
+    ActionRowExt, AdwDialogExt, AlertDialogExt, ComboRowExt, EntryRowExt, ExpanderRowExt,
+    PreferencesRowExt,
+};
 use chrono::{DateTime, Local, TimeDelta, Utc};
-use gtk::prelude::{BoxExt, ButtonExt, GtkWindowExt, OrientableExt, WidgetExt};
-use libnotify::{Notification, Urgency};
+use gtk::gdk::prelude::DisplayExt;
+use gtk::prelude::{
+    BoxExt, ButtonExt, EditableExt, GtkWindowExt, ListModelExt, ListModelExtManual, OrientableExt,
+    WidgetExt,
+};
 use relm4::RelmWidgetExt;
 use relm4::actions::{RelmAction, RelmActionGroup};
 use relm4::prelude::ComponentParts;
 use relm4::{Component, ComponentController, ComponentSender, Controller};
+use tokio::sync::broadcast;
 use tokio::sync::watch::Receiver;
 
 use super::break_window::{BreakWindow, BreakWindowInit};
@@ -32,9 +47,29 @@ relm4::new_stateless_action!(Snooze30mAction, SnoozeActionGroup, "snooze_30m");
 relm4::new_stateless_action!(Snooze1hAction, SnoozeActionGroup, "snooze_1h");
 relm4::new_stateless_action!(Snooze3hAction, SnoozeActionGroup, "snooze_6h");
 
+// In the order they're listed in Settings
+const BREAK_STYLES: [(BreakStyle, &str); 2] = [
+    (BreakStyle::Window, "Small window"),
+    (BreakStyle::Fullscreen, "Fullscreen on every monitor"),
+];
+
+const BREAK_CONTENTS: [(BreakContent, &str); 3] = [
+    (BreakContent::Exercise, "Exercise suggestion"),
+    (BreakContent::BoxBreathing, "Box breathing pacer"),
+    (
+        BreakContent::FourSevenEightBreathing,
+        "4-7-8 breathing pacer",
+    ),
+];
+
 pub struct MainWindowInit {
-    pub idle_monitor_arc: Arc<Mutex<IdleMonitor<IdleChecker, Clock>>>,
+    pub idle_monitor: IdleMonitorHandle,
     pub last_idle_info: Receiver<IdleInfo>,
+    pub config: Receiver<Config>,
+    pub idle_inhibitors: Receiver<Vec<Inhibitor>>,
+    pub exercise_library: ExerciseLibrary,
+    pub break_warnings: broadcast::Receiver<BreakWarning>,
+    pub notifier: Notifier,
     pub show_main_window: Receiver<bool>,
 }
 
@@ -42,13 +77,39 @@ pub struct MainWindowInit {
 pub enum MainWindowMsg {
     Update,
     ForceBreak,
-    Snooze { minutes: i64 },
+    Snooze {
+        minutes: i64,
+    },
     Mute,
     Unmute,
     SetReadingMode(bool),
     SetTimeToBreak(i64),
     SetBreakLength(i64),
-    Hide { notify: bool },
+    SetPauseMediaDuringBreaks(bool),
+    SetLockScreenDuringBreaks(bool),
+    SetBreakStyle(u32),
+    SetBreakContent(u32),
+    SetPostponeMinutes(String),
+    SetBreakWarningMinutes(String),
+    SetRespectIdleInhibitors(bool),
+    SetStrictMode(bool),
+    SetExerciseCategory {
+        category: ExerciseCategory,
+        enabled: bool,
+    },
+    SetSoundEvent {
+        event: SoundEvent,
+        enabled: bool,
+    },
+    SetSoundVolume(u32),
+    LeaveStrictMode {
+        confirmation: String,
+    },
+    SelectProfile(u32),
+    ProfileListRefreshed,
+    Hide {
+        notify: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -57,12 +118,22 @@ pub enum MainWindowCmd {
 }
 
 pub struct MainWindow {
-    idle_monitor_arc: Arc<Mutex<IdleMonitor<IdleChecker, Clock>>>,
-    previous_mode_state: ModeState,
+    idle_monitor: IdleMonitorHandle,
+    previous_mode_state: Option<ModeState>,
     last_idle_info: IdleInfo,
-    break_window: Option<Controller<BreakWindow>>,
+    idle_info_recv: Receiver<IdleInfo>,
+    config: Config,
+    config_recv: Receiver<Config>,
+    idle_inhibitors: Vec<Inhibitor>,
+    idle_inhibitors_recv: Receiver<Vec<Inhibitor>>,
+    exercise_library: ExerciseLibrary,
+    break_warning_recv: broadcast::Receiver<BreakWarning>,
+    profile_list: gtk::StringList,
+    // Rebuilding the profile list makes the combo row report selections nobody made.
+    ignore_profile_selection: bool,
+    break_windows: Vec<Controller<BreakWindow>>,
     show_main_window: Receiver<bool>,
-    prebreak_notification: Option<Notification>,
+    notifier: Notifier,
     time_to_break_secs: i64,
 }
 
@@ -186,7 +257,7 @@ impl Component for MainWindow {
                                         adw::ActionRow {
                                             set_title: "Prebreak",
                                             #[watch]
-                                            set_subtitle: &format!("{} seconds to break", REQUIRED_PREBREAK_IDLE_STREAK_SECONDS - min(model.last_idle_info.idle_since_seconds, REQUIRED_PREBREAK_IDLE_STREAK_SECONDS)),
+                                            set_subtitle: &format!("{} seconds to break", model.last_idle_info.prebreak_idle_secs - min(model.last_idle_info.idle_since_seconds, model.last_idle_info.prebreak_idle_secs)),
                                         },
                                     }
                                 }
@@ -207,6 +278,16 @@ impl Component for MainWindow {
                             set_spacing: 10,
 
                             adw::PreferencesGroup {
+                                adw::ComboRow {
+                                    set_title: "Profile",
+                                    set_model: Some(&model.profile_list),
+                                    #[watch]
+                                    #[block_signal(profile_handler)]
+                                    set_selected: model.active_profile_position(),
+                                    connect_selected_notify[sender] => move |row| {
+                                        sender.input(MainWindowMsg::SelectProfile(row.selected()))
+                                    } @profile_handler
+                                },
                                 adw::SwitchRow {
                                     set_title: "Reading mode",
                                     #[watch]
@@ -247,6 +328,8 @@ impl Component for MainWindow {
                                                 set_icon_name: "audio-volume-muted-symbolic",
                                                 set_valign: gtk::Align::Center,
                                                 set_tooltip: "Mute",
+                                                #[watch]
+                                                set_visible: !model.config.strict_mode.enabled,
                                                 connect_clicked[sender] => move |_| {
                                                     sender.input(MainWindowMsg::Mute);
                                                 },
@@ -257,6 +340,8 @@ impl Component for MainWindow {
                                             set_icon_name: "snooze-filled",
                                             set_direction: gtk::ArrowType::Down,
                                             set_menu_model: Some(&snooze),
+                                            #[watch]
+                                            set_visible: !model.config.strict_mode.enabled,
                                         }
                                     }
                                 },
@@ -269,6 +354,9 @@ impl Component for MainWindow {
                                         0.0, 1440.0, 1.0, 1.0, 0.0,
                                     )),
                                     set_snap_to_ticks: false,
+                                    #[watch]
+                                    #[block_signal(time_to_break_handler)]
+                                    set_value: model.last_idle_info.time_to_break_secs as f64 / 60.0,
                                     connect_value_notify[sender] => move |row| {
                                         sender.input(MainWindowMsg::SetTimeToBreak(row.value().round() as i64))
                                     } @time_to_break_handler
@@ -282,9 +370,192 @@ impl Component for MainWindow {
                                         0.0, 86400.0, 10.0, 1.0, 0.0,
                                     )),
                                     set_snap_to_ticks: false,
+                                    #[watch]
+                                    #[block_signal(break_length_handler)]
+                                    set_value: model.last_idle_info.break_length_secs as f64,
                                     connect_value_notify[sender] => move |row| {
                                         sender.input(MainWindowMsg::SetBreakLength(row.value().round() as i64))
                                     } @break_length_handler
+                                },
+                                adw::ComboRow {
+                                    set_title: "Break style",
+                                    set_model: Some(&gtk::StringList::new(&BREAK_STYLES.map(|(_, label)| label))),
+                                    #[watch]
+                                    #[block_signal(break_style_handler)]
+                                    set_selected: BREAK_STYLES
+                                        .iter()
+                                        .position(|(break_style, _)| *break_style == model.config.break_style)
+                                        .unwrap_or_default() as u32,
+                                    connect_selected_notify[sender] => move |row| {
+                                        sender.input(MainWindowMsg::SetBreakStyle(row.selected()))
+                                    } @break_style_handler
+                                },
+                                adw::ComboRow {
+                                    set_title: "During breaks",
+                                    set_model: Some(&gtk::StringList::new(&BREAK_CONTENTS.map(|(_, label)| label))),
+                                    #[watch]
+                                    #[block_signal(break_content_handler)]
+                                    set_selected: BREAK_CONTENTS
+                                        .iter()
+                                        .position(|(break_content, _)| *break_content == model.config.break_content)
+                                        .unwrap_or_default() as u32,
+                                    connect_selected_notify[sender] => move |row| {
+                                        sender.input(MainWindowMsg::SetBreakContent(row.selected()))
+                                    } @break_content_handler
+                                },
+                                adw::SwitchRow {
+                                    set_title: "Pause media during breaks",
+                                    set_subtitle: "Playback resumes when the break is over",
+                                    #[watch]
+                                    set_active: model.config.pause_media_during_breaks,
+                                    connect_active_notify[sender] => move |switch| {
+                                        sender.input(MainWindowMsg::SetPauseMediaDuringBreaks(switch.is_active()));
+                                    }
+                                },
+                                adw::SwitchRow {
+                                    set_title: "Lock screen during breaks",
+                                    set_subtitle: "Unlocking early counts as skipping part of the break",
+                                    #[watch]
+                                    set_active: model.config.lock_screen_during_breaks,
+                                    connect_active_notify[sender] => move |switch| {
+                                        sender.input(MainWindowMsg::SetLockScreenDuringBreaks(switch.is_active()));
+                                    }
+                                },
+                                adw::EntryRow {
+                                    set_title: "Warn this many minutes before breaks",
+                                    set_text: &format_minutes_list(&model.config.break_warning_minutes),
+                                    set_show_apply_button: true,
+                                    connect_apply[sender] => move |row| {
+                                        sender.input(MainWindowMsg::SetBreakWarningMinutes(row.text().to_string()));
+                                    }
+                                },
+                                adw::EntryRow {
+                                    set_title: "Postpone options in minutes",
+                                    set_text: &format_minutes_list(&model.config.postpone_minutes),
+                                    set_show_apply_button: true,
+                                    connect_apply[sender] => move |row| {
+                                        sender.input(MainWindowMsg::SetPostponeMinutes(row.text().to_string()));
+                                    }
+                                },
+                                adw::SwitchRow {
+                                    set_title: "Count watching videos as activity",
+                                    #[watch]
+                                    set_subtitle: &model.describe_idle_inhibitors(),
+                                    #[watch]
+                                    set_active: model.config.respect_idle_inhibitors,
+                                    connect_active_notify[sender] => move |switch| {
+                                        sender.input(MainWindowMsg::SetRespectIdleInhibitors(switch.is_active()));
+                                    }
+                                },
+                                adw::SwitchRow {
+                                    set_title: "Strict mode",
+                                    #[watch]
+                                    set_subtitle: &format!(
+                                        "Breaks can't be skipped and can be postponed {} times, each time for less",
+                                        model.config.strict_mode.max_postpones
+                                    ),
+                                    #[watch]
+                                    #[block_signal(strict_mode_handler)]
+                                    set_active: model.config.strict_mode.enabled,
+                                    connect_active_notify[sender] => move |switch| {
+                                        sender.input(MainWindowMsg::SetStrictMode(switch.is_active()));
+                                    } @strict_mode_handler
+                                },
+                                adw::ExpanderRow {
+                                    set_title: "Exercise suggestions",
+                                    set_subtitle: "Shown during breaks, a different one each time",
+                                    add_row = &adw::SwitchRow {
+                                        set_title: "Stretches",
+                                        #[watch]
+                                        set_active: model.config.exercise_categories.contains(&ExerciseCategory::Stretch),
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(MainWindowMsg::SetExerciseCategory {
+                                                category: ExerciseCategory::Stretch,
+                                                enabled: switch.is_active(),
+                                            });
+                                        }
+                                    },
+                                    add_row = &adw::SwitchRow {
+                                        set_title: "Eye exercises",
+                                        #[watch]
+                                        set_active: model.config.exercise_categories.contains(&ExerciseCategory::Eyes),
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(MainWindowMsg::SetExerciseCategory {
+                                                category: ExerciseCategory::Eyes,
+                                                enabled: switch.is_active(),
+                                            });
+                                        }
+                                    },
+                                    add_row = &adw::SwitchRow {
+                                        set_title: "Breathing exercises",
+                                        #[watch]
+                                        set_active: model.config.exercise_categories.contains(&ExerciseCategory::Breathing),
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(MainWindowMsg::SetExerciseCategory {
+                                                category: ExerciseCategory::Breathing,
+                                                enabled: switch.is_active(),
+                                            });
+                                        }
+                                    },
+                                },
+                                adw::ExpanderRow {
+                                    set_title: "Sounds",
+                                    set_subtitle: "Your own WAV, OGG or FLAC files in the sounds folder take precedence",
+                                    add_row = &adw::SwitchRow {
+                                        set_title: "When a break is coming up",
+                                        #[watch]
+                                        set_active: model.config.sounds.events.contains(&SoundEvent::PreBreak),
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(MainWindowMsg::SetSoundEvent {
+                                                event: SoundEvent::PreBreak,
+                                                enabled: switch.is_active(),
+                                            });
+                                        }
+                                    },
+                                    add_row = &adw::SwitchRow {
+                                        set_title: "When a break starts",
+                                        #[watch]
+                                        set_active: model.config.sounds.events.contains(&SoundEvent::BreakStart),
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(MainWindowMsg::SetSoundEvent {
+                                                event: SoundEvent::BreakStart,
+                                                enabled: switch.is_active(),
+                                            });
+                                        }
+                                    },
+                                    add_row = &adw::SwitchRow {
+                                        set_title: "Halfway through a break",
+                                        #[watch]
+                                        set_active: model.config.sounds.events.contains(&SoundEvent::Halfway),
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(MainWindowMsg::SetSoundEvent {
+                                                event: SoundEvent::Halfway,
+                                                enabled: switch.is_active(),
+                                            });
+                                        }
+                                    },
+                                    add_row = &adw::SwitchRow {
+                                        set_title: "When a break is over",
+                                        #[watch]
+                                        set_active: model.config.sounds.events.contains(&SoundEvent::BreakEnd),
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(MainWindowMsg::SetSoundEvent {
+                                                event: SoundEvent::BreakEnd,
+                                                enabled: switch.is_active(),
+                                            });
+                                        }
+                                    },
+                                    add_row = &adw::SpinRow {
+                                        set_title: "Volume",
+                                        set_subtitle: "In percent",
+                                        set_adjustment: Some(&gtk::Adjustment::new(
+                                            model.config.sounds.volume_percent as f64,
+                                            0.0, 100.0, 10.0, 10.0, 0.0,
+                                        )),
+                                        connect_value_notify[sender] => move |row| {
+                                            sender.input(MainWindowMsg::SetSoundVolume(row.value().round() as u32))
+                                        }
+                                    },
                                 }
                             }
                         }
@@ -305,13 +576,28 @@ impl Component for MainWindow {
             sender.input(MainWindowMsg::Hide { notify: false });
         }
 
+        let config = init.config.borrow().clone();
+        let idle_inhibitors = init.idle_inhibitors.borrow().clone();
+        let profile_names = config.profile_names();
+        let profile_list =
+            gtk::StringList::new(&profile_names.iter().map(String::as_str).collect::<Vec<_>>());
         let model = MainWindow {
-            idle_monitor_arc: init.idle_monitor_arc,
-            previous_mode_state: previous_last_idle_info.last_mode_state,
+            idle_monitor: init.idle_monitor,
+            // Starts out empty so a break restored from disk still opens the break window
+            previous_mode_state: None,
             last_idle_info: previous_last_idle_info,
-            break_window: None,
+            idle_info_recv: init.last_idle_info,
+            config,
+            config_recv: init.config,
+            idle_inhibitors,
+            idle_inhibitors_recv: init.idle_inhibitors,
+            exercise_library: init.exercise_library,
+            break_warning_recv: init.break_warnings,
+            profile_list,
+            ignore_profile_selection: false,
+            break_windows: Vec::new(),
             show_main_window: init.show_main_window,
-            prebreak_notification: None,
+            notifier: init.notifier,
             time_to_break_secs: previous_last_idle_info.time_to_break_secs,
         };
         let snooze_button = gtk::MenuButton::builder().build();
@@ -371,51 +657,92 @@ impl Component for MainWindow {
                     sleep(Duration::from_millis(100));
                     MainWindowCmd::TriggerUpdate
                 });
-                #[cfg(target_os = "linux")]
-                if let Some(notification) = &self.prebreak_notification {
-                    if let ModeState::PreBreak { .. } = self.last_idle_info.last_mode_state {
-                    } else {
-                        if let Err(_) = notification.close() {
-                            println!("Warning: failed to close notification");
-                        }
-                        self.prebreak_notification = None;
+                if let Some(ModeState::PreBreak { .. }) = self.previous_mode_state
+                    && !matches!(
+                        self.last_idle_info.last_mode_state,
+                        ModeState::PreBreak { .. }
+                    )
+                {
+                    self.notifier.close(NotificationKind::PreBreak);
+                }
+                while let Ok(break_warning) = self.break_warning_recv.try_recv() {
+                    if self.config.active_profile().notification_style != NotificationStyle::Silent
+                    {
+                        let minutes = break_warning.secs_until_break / 60;
+                        self.notifier.show(
+                            NotificationKind::BreakWarning,
+                            DesktopNotification {
+                                summary: String::from("Break coming up"),
+                                body: match minutes {
+                                    1 => String::from("Your next break starts in a minute."),
+                                    _ => format!("Your next break starts in {} minutes.", minutes),
+                                },
+                                urgency: NotificationUrgency::Normal,
+                                actions: self.notification_actions(false),
+                            },
+                        );
                     }
                 }
                 match self.last_idle_info.last_mode_state {
                     ModeState::Normal { .. } => {}
-                    ModeState::PreBreak { .. } => {
-                        match self.previous_mode_state {
-                            ModeState::PreBreak { .. } => {}
-                            _ => {
-                                // Try to warn about prebreak if notify-send is installed
-                                #[cfg(target_os = "linux")]
-                                {
-                                    let prebreak_notification = Notification::new(
-                                        "Time to stretch",
-                                        "Break will start when mouse and keyboard are released.",
-                                        None,
+                    ModeState::PreBreak { .. } => match self.previous_mode_state {
+                        Some(ModeState::PreBreak { .. }) => {}
+                        _ => {
+                            let urgency = match self.config.active_profile().notification_style {
+                                NotificationStyle::Urgent => Some(NotificationUrgency::Critical),
+                                NotificationStyle::Normal => Some(NotificationUrgency::Normal),
+                                NotificationStyle::Silent => None,
+                            };
+                            if let Some(urgency) = urgency {
+                                self.notifier.show(
+                                        NotificationKind::PreBreak,
+                                        DesktopNotification {
+                                            summary: String::from("Time to stretch"),
+                                            body: String::from(
+                                                "Break will start when mouse and keyboard are released.",
+                                            ),
+                                            urgency,
+                                            actions: self.notification_actions(true),
+                                        },
                                     );
-                                    prebreak_notification.set_urgency(Urgency::Critical);
-                                    prebreak_notification.show().ok();
-                                    self.prebreak_notification = Some(prebreak_notification);
-                                }
                             }
                         }
-                    }
+                    },
                     ModeState::Break { .. } => match self.previous_mode_state {
-                        ModeState::Break { .. } => {}
+                        Some(ModeState::Break { .. }) => {}
                         _ => {
-                            let break_window_init = BreakWindowInit {
-                                idle_monitor_arc: self.idle_monitor_arc.clone(),
+                            let breathing_pattern = self.config.break_content.breathing_pattern();
+                            // The pacer takes the place of the exercise suggestion
+                            let exercise = match breathing_pattern {
+                                Some(_) => None,
+                                None => {
+                                    self.exercise_library.next(&self.config.exercise_categories)
+                                }
                             };
-                            let break_window =
-                                BreakWindow::builder().launch(break_window_init).detach();
-                            break_window.widget().present();
-                            self.break_window = Some(break_window);
+                            self.break_windows = self
+                                .break_window_placements()
+                                .into_iter()
+                                .map(|(fullscreen_on, show_controls)| {
+                                    let break_window_init = BreakWindowInit {
+                                        idle_monitor: self.idle_monitor.clone(),
+                                        idle_info_recv: self.idle_info_recv.clone(),
+                                        strict_mode: self.config.strict_mode.clone(),
+                                        postpone_minutes: self.config.postpone_minutes.clone(),
+                                        exercise: exercise.clone(),
+                                        breathing_pattern,
+                                        fullscreen_on,
+                                        show_controls,
+                                    };
+                                    let break_window =
+                                        BreakWindow::builder().launch(break_window_init).detach();
+                                    break_window.widget().present();
+                                    break_window
+                                })
+                                .collect();
                         }
                     },
                 }
-                self.previous_mode_state = self.last_idle_info.last_mode_state;
+                self.previous_mode_state = Some(self.last_idle_info.last_mode_state);
                 if self.show_main_window.has_changed().unwrap() {
                     let visible = *self.show_main_window.borrow_and_update();
                     root.set_visible(visible);
@@ -425,45 +752,172 @@ impl Component for MainWindow {
                 }
             }
             MainWindowMsg::ForceBreak => {
-                self._unwrapped_idle_monitor().trigger_break();
+                self.idle_monitor.send(IdleMonitorCommand::TriggerBreak);
             }
             MainWindowMsg::Snooze { minutes } => {
                 let unmute_timestamp = Utc::now()
                     .checked_add_signed(TimeDelta::minutes(minutes))
                     .unwrap();
-                self._unwrapped_idle_monitor().snooze(unmute_timestamp);
+                self.idle_monitor
+                    .send(IdleMonitorCommand::Snooze(unmute_timestamp));
             }
             MainWindowMsg::Mute => {
-                self._unwrapped_idle_monitor().mute();
+                self.idle_monitor.send(IdleMonitorCommand::Mute);
             }
             MainWindowMsg::Unmute => {
-                self._unwrapped_idle_monitor().unmute();
+                self.idle_monitor.send(IdleMonitorCommand::Unmute);
             }
             MainWindowMsg::SetReadingMode(value) => {
-                self._unwrapped_idle_monitor().set_reading_mode(value);
-                self.last_idle_info = self.idle_monitor_arc.lock().unwrap().get_last_idle_info();
+                self.idle_monitor
+                    .send(IdleMonitorCommand::SetReadingMode(value));
+                // Reflect the switch right away so the next render doesn't flip it back
+                // before the backend has published the change.
+                self.last_idle_info.reading_mode = value;
+            }
+            MainWindowMsg::SetPauseMediaDuringBreaks(value) => {
+                self.idle_monitor
+                    .send(IdleMonitorCommand::SetPauseMediaDuringBreaks(value));
+                self.config.pause_media_during_breaks = value;
+            }
+            MainWindowMsg::SetLockScreenDuringBreaks(value) => {
+                self.idle_monitor
+                    .send(IdleMonitorCommand::SetLockScreenDuringBreaks(value));
+                self.config.lock_screen_during_breaks = value;
+            }
+            MainWindowMsg::SetBreakStyle(position) => {
+                if let Some(&(break_style, _)) = BREAK_STYLES.get(position as usize) {
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::SetBreakStyle(break_style));
+                    self.config.break_style = break_style;
+                }
+            }
+            MainWindowMsg::SetBreakContent(position) => {
+                if let Some(&(break_content, _)) = BREAK_CONTENTS.get(position as usize) {
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::SetBreakContent(break_content));
+                    self.config.break_content = break_content;
+                }
+            }
+            MainWindowMsg::SetExerciseCategory { category, enabled } => {
+                let exercise_categories: Vec<ExerciseCategory> = ExerciseCategory::ALL
+                    .into_iter()
+                    .filter(|&other_category| match other_category == category {
+                        true => enabled,
+                        false => self.config.exercise_categories.contains(&other_category),
+                    })
+                    .collect();
+                self.idle_monitor
+                    .send(IdleMonitorCommand::SetExerciseCategories(
+                        exercise_categories.clone(),
+                    ));
+                self.config.exercise_categories = exercise_categories;
+            }
+            MainWindowMsg::SetSoundEvent { event, enabled } => {
+                let events: Vec<SoundEvent> = SoundEvent::ALL
+                    .into_iter()
+                    .filter(|&other_event| match other_event == event {
+                        true => enabled,
+                        false => self.config.sounds.events.contains(&other_event),
+                    })
+                    .collect();
+                self.idle_monitor
+                    .send(IdleMonitorCommand::SetSoundEvents(events.clone()));
+                self.config.sounds.events = events;
+            }
+            MainWindowMsg::SetSoundVolume(volume_percent) => {
+                if volume_percent != self.config.sounds.volume_percent {
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::SetSoundVolume(volume_percent));
+                    self.config.sounds.volume_percent = volume_percent;
+                }
+            }
+            MainWindowMsg::SetPostponeMinutes(text) => match parse_minutes_list(&text) {
+                Some(postpone_minutes) if !postpone_minutes.is_empty() => {
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::SetPostponeMinutes(
+                            postpone_minutes.clone(),
+                        ));
+                    self.config.postpone_minutes = postpone_minutes;
+                }
+                _ => println!("Ignoring postpone options that aren't positive numbers: {text}"),
+            },
+            MainWindowMsg::SetBreakWarningMinutes(text) => match parse_minutes_list(&text) {
+                Some(break_warning_minutes) => {
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::SetBreakWarningMinutes(
+                            break_warning_minutes.clone(),
+                        ));
+                    self.config.break_warning_minutes = break_warning_minutes;
+                }
+                None => println!("Ignoring warning times that aren't positive numbers: {text}"),
+            },
+            MainWindowMsg::SetStrictMode(true) => {
+                self.idle_monitor.send(IdleMonitorCommand::SetStrictMode {
+                    enabled: true,
+                    confirmation: String::new(),
+                });
+                self.config.strict_mode.enabled = true;
+            }
+            MainWindowMsg::SetStrictMode(false) => {
+                // The switch flips back until the confirmation phrase has been typed.
+                show_leave_strict_mode_dialog(root, sender.clone());
+            }
+            MainWindowMsg::LeaveStrictMode { confirmation } => {
+                self.idle_monitor.send(IdleMonitorCommand::SetStrictMode {
+                    enabled: false,
+                    confirmation,
+                });
+                self.config.strict_mode.enabled = false;
+            }
+            MainWindowMsg::SetRespectIdleInhibitors(value) => {
+                self.idle_monitor
+                    .send(IdleMonitorCommand::SetRespectIdleInhibitors(value));
+                self.config.respect_idle_inhibitors = value;
             }
             MainWindowMsg::SetTimeToBreak(value) => {
-                if self.last_idle_info.time_to_break_secs != value {
-                    self._unwrapped_idle_monitor().set_time_to_break(value * 60);
+                if self.last_idle_info.time_to_break_secs != value * 60 {
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::SetTimeToBreak(value * 60));
+                    self.last_idle_info.time_to_break_secs = value * 60;
                 }
             }
             MainWindowMsg::SetBreakLength(value) => {
                 if self.last_idle_info.break_length_secs != value {
-                    self._unwrapped_idle_monitor().set_break_length(value);
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::SetBreakLength(value));
+                    self.last_idle_info.break_length_secs = value;
+                }
+            }
+            MainWindowMsg::SelectProfile(position) => {
+                if self.ignore_profile_selection {
+                    return;
                 }
+                let profile_names = self.config.profile_names();
+                if let Some(name) = profile_names.get(position as usize)
+                    && *name != self.config.active_profile
+                {
+                    self.idle_monitor
+                        .send(IdleMonitorCommand::UseProfile(name.clone()));
+                    self.config.active_profile = name.clone();
+                }
+            }
+            MainWindowMsg::ProfileListRefreshed => {
+                self.ignore_profile_selection = false;
             }
             MainWindowMsg::Hide { notify } => {
                 root.set_visible(false);
-                #[cfg(target_os = "linux")]
                 if notify {
-                    let notification = Notification::new(
-                        "Still here!",
-                        "Stretch Break continues running in the background.",
-                        None,
+                    self.notifier.show(
+                        NotificationKind::StillRunning,
+                        DesktopNotification {
+                            summary: String::from("Still here!"),
+                            body: String::from(
+                                "Stretch Break continues running in the background.",
+                            ),
+                            urgency: NotificationUrgency::Normal,
+                            actions: Vec::new(),
+                        },
                     );
-                    notification.set_timeout(3_000);
-                    notification.show().ok();
                 }
             }
         }
@@ -477,17 +931,123 @@ impl Component for MainWindow {
     ) {
         match message {
             Self::CommandOutput::TriggerUpdate => {
-                self.last_idle_info = self.idle_monitor_arc.lock().unwrap().get_last_idle_info();
+                self.last_idle_info = *self.idle_info_recv.borrow();
+                if self.config_recv.has_changed().unwrap_or(false) {
+                    let config = self.config_recv.borrow_and_update().clone();
+                    if config.profile_names() != self.config.profile_names() {
+                        self.ignore_profile_selection = true;
+                        let profile_names = config.profile_names();
+                        self.profile_list.splice(
+                            0,
+                            self.profile_list.n_items(),
+                            &profile_names.iter().map(String::as_str).collect::<Vec<_>>(),
+                        );
+                        sender.input(MainWindowMsg::ProfileListRefreshed);
+                    }
+                    self.config = config;
+                }
+                if self.idle_inhibitors_recv.has_changed().unwrap_or(false) {
+                    self.idle_inhibitors = self.idle_inhibitors_recv.borrow_and_update().clone();
+                }
                 sender.input(MainWindowMsg::Update);
             }
         }
     }
 }
 
+fn show_leave_strict_mode_dialog(
+    root: &adw::ApplicationWindow,
+    sender: ComponentSender<MainWindow>,
+) {
+    let dialog = adw::AlertDialog::new(
+        Some("Leave strict mode?"),
+        Some(&format!(
+            "Type \u{201c}{}\u{201d} to confirm.",
+            STRICT_MODE_EXIT_PHRASE
+        )),
+    );
+    let entry = gtk::Entry::new();
+    dialog.set_extra_child(Some(&entry));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("leave", "Leave Strict Mode");
+    dialog.set_response_appearance("leave", adw::ResponseAppearance::Destructive);
+    dialog.set_response_enabled("leave", false);
+    dialog.set_close_response("cancel");
+
+    let cloned_dialog = dialog.clone();
+    entry.connect_changed(move |entry| {
+        cloned_dialog.set_response_enabled("leave", is_strict_mode_exit_phrase(&entry.text()));
+    });
+    dialog.connect_response(Some("leave"), move |_, _| {
+        sender.input(MainWindowMsg::LeaveStrictMode {
+            confirmation: entry.text().to_string(),
+        });
+    });
+    dialog.present(Some(root));
+}
+
 impl MainWindow {
-    fn _unwrapped_idle_monitor(&self) -> MutexGuard<'_, IdleMonitor<IdleChecker, Clock>> {
-        self.idle_monitor_arc
-            .lock()
-            .expect("Unable to obtain idle monitor lock")
+    fn describe_idle_inhibitors(&self) -> String {
+        match (
+            self.config.respect_idle_inhibitors,
+            self.idle_inhibitors.is_empty(),
+        ) {
+            (true, false) => format!(
+                "Break timer will not reset while {} keeps the screen awake",
+                describe_inhibiting_apps(&self.idle_inhibitors)
+            ),
+            (false, false) => format!(
+                "{} is keeping the screen awake",
+                describe_inhibiting_apps(&self.idle_inhibitors)
+            ),
+            (_, true) => String::from("When an app keeps the screen awake"),
+        }
+    }
+
+    // Pairs of the monitor to cover, if any, and whether to show the break controls there.
+    fn break_window_placements(&self) -> Vec<(Option<gtk::gdk::Monitor>, bool)> {
+        let monitors: Vec<gtk::gdk::Monitor> = match self.config.break_style {
+            BreakStyle::Window => Vec::new(),
+            BreakStyle::Fullscreen => gtk::gdk::Display::default()
+                .map(|display| {
+                    display
+                        .monitors()
+                        .iter::<gtk::gdk::Monitor>()
+                        .filter_map(Result::ok)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        if monitors.is_empty() {
+            return vec![(None, true)];
+        }
+        // GDK has no notion of a primary monitor on Wayland. The first one is where the
+        // compositor puts new windows, which is as close as it gets.
+        monitors
+            .into_iter()
+            .enumerate()
+            .map(|(position, monitor)| (Some(monitor), position == 0))
+            .collect()
+    }
+
+    fn active_profile_position(&self) -> u32 {
+        self.config
+            .profiles
+            .keys()
+            .position(|name| *name == self.config.active_profile)
+            .map_or(gtk::INVALID_LIST_POSITION, |position| position as u32)
+    }
+
+    // Postponing goes by the first of the configured postpone times. Strict mode doesn't allow
+    // snoozing, so that button would only fail.
+    fn notification_actions(&self, offer_postpone: bool) -> Vec<NotificationAction> {
+        let mut actions = vec![NotificationAction::StartBreak];
+        if offer_postpone && let Some(&minutes) = self.config.postpone_minutes.first() {
+            actions.push(NotificationAction::Postpone { minutes });
+        }
+        if !self.config.strict_mode.enabled {
+            actions.push(NotificationAction::Snooze);
+        }
+        actions
     }
 }
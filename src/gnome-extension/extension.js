@@ -47,6 +47,9 @@ const DBUS_IFACE = `
             <arg type="b" direction="in" />
         </method>
         <method name="TriggerBreak"></method>
+        <method name="UseProfile">
+            <arg type="s" direction="in" />
+        </method>
     </interface>
 </node>`;
 const ProxyWrapper = Gio.DBusProxy.makeProxyWrapper(DBUS_IFACE);
@@ -151,6 +154,10 @@ class DBusClient {
     triggerBreak() {
         this._proxy.TriggerBreakAsync();
     }
+
+    useProfile(name) {
+        this._proxy.UseProfileAsync(name);
+    }
 }
 
 const Indicator = GObject.registerClass(
@@ -207,6 +214,12 @@ const Indicator = GObject.registerClass(
             });
             this.menu.addMenuItem(this._readingModeSwitch);
 
+            this._dbusClient = dbusClient;
+            this._profileNames = [];
+            this._profileSubMenuItem = new PopupMenu.PopupSubMenuMenuItem("Profile");
+            this._profileSubMenuItem.visible = false;
+            this.menu.addMenuItem(this._profileSubMenuItem);
+
             this._modeSeparator = new PopupMenu.PopupSeparatorMenuItem("");
 
             this.menu.addMenuItem(this._modeSeparator);
@@ -279,6 +292,30 @@ const Indicator = GObject.registerClass(
         updateReadingModeStatus(value) {
             this._readingModeSwitch.setToggleState(value);
         }
+
+        updateProfiles(profileNames, activeProfile) {
+            // Older servers don't report profiles
+            profileNames = profileNames ?? [];
+            if (profileNames.join('\n') !== this._profileNames.join('\n')) {
+                this._profileNames = profileNames;
+                this._profileSubMenuItem.menu.removeAll();
+                this._profileMenuItems = profileNames.map(name => {
+                    const item = new PopupMenu.PopupMenuItem(name);
+                    item.connect("activate", () => {
+                        this._dbusClient.useProfile(name);
+                    });
+                    this._profileSubMenuItem.menu.addMenuItem(item);
+                    return item;
+                });
+            }
+            this._profileSubMenuItem.visible = profileNames.length > 0;
+            this._profileSubMenuItem.label.text = activeProfile ? `Profile: ${activeProfile}` : 'Profile';
+            profileNames.forEach((name, index) => {
+                this._profileMenuItems[index].setOrnament(
+                    name === activeProfile ? PopupMenu.Ornament.CHECK : PopupMenu.Ornament.NONE
+                );
+            });
+        }
     });
 
 export default class StretchBreakCompanionExtension extends Extension {
@@ -291,6 +328,7 @@ export default class StretchBreakCompanionExtension extends Extension {
             this._indicator.updateResetLabel(widgetInfo.countdown_to_reset_value ?? '');
             this._indicator.updatePresenceMode(widgetInfo.snoozed_until_time, presenceModeType === 'muted');
             this._indicator.updateReadingModeStatus(widgetInfo.reading_mode);
+            this._indicator.updateProfiles(widgetInfo.profiles, widgetInfo.active_profile);
         }
     }
 
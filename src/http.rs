@@ -0,0 +1,435 @@
+use std::convert::Infallible;
+use std::fs;
+use std::io;
+use std::net::Ipv4Addr;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::broadcast;
+use tokio::sync::watch::Receiver;
+use tokio_stream::wrappers::{BroadcastStream, WatchStream};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::backend::break_events::BreakEvent;
+use crate::backend::config::{Config, Http};
+use crate::backend::idle_monitoring::IdleInfo;
+use crate::backend::monitor_actor::{IdleMonitorCommand, IdleMonitorError, IdleMonitorHandle};
+use crate::dbus::{WidgetInfo, get_widget_info};
+
+const DEFAULT_SNOOZE_MINUTES: i64 = 60;
+
+#[derive(Serialize)]
+struct Status {
+    widget_info: WidgetInfo,
+    idle_info: IdleInfo,
+}
+
+impl Status {
+    fn new(idle_info: IdleInfo, config: &Config) -> Self {
+        Status {
+            widget_info: get_widget_info(&idle_info, config),
+            idle_info,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MinutesRequest {
+    minutes: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ReadingModeRequest {
+    enabled: bool,
+}
+
+#[derive(Clone)]
+struct HttpState {
+    idle_monitor: IdleMonitorHandle,
+    idle_info_recv: Receiver<IdleInfo>,
+    config_recv: Receiver<Config>,
+    // Each /events client gets its own copy to read from
+    break_events: Arc<broadcast::Receiver<BreakEvent>>,
+    metrics_recv: Receiver<String>,
+}
+
+struct HttpError(IdleMonitorError);
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            IdleMonitorError::Gone => StatusCode::SERVICE_UNAVAILABLE,
+            IdleMonitorError::UnknownProfile(_)
+            | IdleMonitorError::InvalidPostpone
+            | IdleMonitorError::WrongConfirmation => StatusCode::BAD_REQUEST,
+            IdleMonitorError::SkipNotAllowed | IdleMonitorError::NoPostponesLeft => {
+                StatusCode::FORBIDDEN
+            }
+        };
+        (status, self.0.to_string()).into_response()
+    }
+}
+
+// Any web page can send requests to localhost, so we only answer to local host names, which
+// stops DNS rebinding, and turn away requests that a web page on another site started.
+// Scripts and browser extensions don't send a web origin.
+fn is_local_request(host: Option<&str>, origin: Option<&str>) -> bool {
+    fn is_local_host(host: &str) -> bool {
+        let name = match host.strip_prefix('[') {
+            Some(rest) => rest.split(']').next().unwrap_or_default(),
+            None => host.split(':').next().unwrap_or_default(),
+        };
+        matches!(name, "localhost" | "127.0.0.1" | "::1")
+    }
+
+    let host_is_local = host.is_none_or(is_local_host);
+    let origin_is_local = match origin {
+        Some(origin) => match origin
+            .strip_prefix("http://")
+            .or(origin.strip_prefix("https://"))
+        {
+            Some(origin_host) => is_local_host(origin_host),
+            None => origin != "null",
+        },
+        None => true,
+    };
+    host_is_local && origin_is_local
+}
+
+async fn only_local_requests(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let header_value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if !is_local_request(header_value(header::HOST), header_value(header::ORIGIN)) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(request).await
+}
+
+async fn status(State(state): State<HttpState>) -> Result<Json<Status>, HttpError> {
+    let idle_info = state
+        .idle_monitor
+        .get_last_idle_info()
+        .await
+        .map_err(HttpError)?;
+    Ok(Json(Status::new(idle_info, &state.config_recv.borrow())))
+}
+
+// A status event whenever the timer changes, and one named after each break event.
+async fn events(
+    State(state): State<HttpState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let make_event = move |name: &str, idle_info: IdleInfo, config_recv: &Receiver<Config>| {
+        let status = Status::new(idle_info, &config_recv.borrow());
+        Ok(Event::default()
+            .event(name)
+            .json_data(status)
+            .expect("Serde JSON conversion failed"))
+    };
+    let config_recv = state.config_recv.clone();
+    let status_events = WatchStream::new(state.idle_info_recv.clone())
+        .map(move |idle_info| make_event("status", idle_info, &config_recv));
+    let config_recv = state.config_recv.clone();
+    // A client that falls behind only misses events, it isn't disconnected
+    let break_events = BroadcastStream::new(state.break_events.resubscribe())
+        .filter_map(|event| event.ok())
+        .map(move |event| make_event(event.kind.name(), event.idle_info, &config_recv));
+    Sse::new(status_events.merge(break_events)).keep_alive(KeepAlive::default())
+}
+
+async fn metrics(State(state): State<HttpState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics_recv.borrow().clone(),
+    )
+}
+
+async fn apply(state: &HttpState, command: IdleMonitorCommand) -> Result<Json<Status>, HttpError> {
+    let idle_info = state
+        .idle_monitor
+        .request(command)
+        .await
+        .map_err(HttpError)?;
+    Ok(Json(Status::new(idle_info, &state.config_recv.borrow())))
+}
+
+async fn mute(State(state): State<HttpState>) -> Result<Json<Status>, HttpError> {
+    apply(&state, IdleMonitorCommand::Mute).await
+}
+
+async fn unmute(State(state): State<HttpState>) -> Result<Json<Status>, HttpError> {
+    apply(&state, IdleMonitorCommand::Unmute).await
+}
+
+async fn snooze(
+    State(state): State<HttpState>,
+    Json(request): Json<MinutesRequest>,
+) -> Result<Json<Status>, Response> {
+    let minutes = request.minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES);
+    if minutes <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "minutes must be positive").into_response());
+    }
+    let command = IdleMonitorCommand::Snooze(Utc::now() + Duration::minutes(minutes));
+    apply(&state, command)
+        .await
+        .map_err(IntoResponse::into_response)
+}
+
+async fn trigger_break(State(state): State<HttpState>) -> Result<Json<Status>, HttpError> {
+    apply(&state, IdleMonitorCommand::TriggerBreak).await
+}
+
+async fn skip_break(State(state): State<HttpState>) -> Result<Json<Status>, HttpError> {
+    apply(&state, IdleMonitorCommand::SkipBreak).await
+}
+
+async fn postpone_break(
+    State(state): State<HttpState>,
+    Json(request): Json<MinutesRequest>,
+) -> Result<Json<Status>, Response> {
+    let default_minutes = state.config_recv.borrow().postpone_minutes.first().copied();
+    let minutes = request.minutes.or(default_minutes).unwrap_or(0);
+    if minutes <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "minutes must be positive").into_response());
+    }
+    let command = IdleMonitorCommand::PostponeBreak(Duration::minutes(minutes));
+    apply(&state, command)
+        .await
+        .map_err(IntoResponse::into_response)
+}
+
+async fn set_reading_mode(
+    State(state): State<HttpState>,
+    Json(request): Json<ReadingModeRequest>,
+) -> Result<Json<Status>, HttpError> {
+    apply(&state, IdleMonitorCommand::SetReadingMode(request.enabled)).await
+}
+
+fn make_router(state: HttpState) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        .route("/events", get(events))
+        .route("/metrics", get(metrics))
+        .route("/mute", post(mute))
+        .route("/unmute", post(unmute))
+        .route("/snooze", post(snooze))
+        .route("/break", post(trigger_break))
+        .route("/skip", post(skip_break))
+        .route("/postpone", post(postpone_break))
+        .route("/reading-mode", post(set_reading_mode))
+        .layer(middleware::from_fn(only_local_requests))
+        .with_state(state)
+}
+
+#[tokio::main]
+pub async fn run_http_server(
+    settings: Http,
+    idle_info_recv: Receiver<IdleInfo>,
+    config_recv: Receiver<Config>,
+    break_events: broadcast::Receiver<BreakEvent>,
+    metrics_recv: Receiver<String>,
+    idle_monitor: IdleMonitorHandle,
+) -> io::Result<()> {
+    let router = make_router(HttpState {
+        idle_monitor,
+        idle_info_recv,
+        config_recv,
+        break_events: Arc::new(break_events),
+        metrics_recv,
+    });
+    match settings.unix_socket {
+        Some(path) => {
+            // Left behind by an earlier run, as only one instance runs at a time
+            if fs::metadata(&path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+                fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(&path)?;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            axum::serve(listener, router).await
+        }
+        None => {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, settings.port)).await?;
+            axum::serve(listener, router).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::{Duration as StdDuration, Instant};
+
+    use axum::body::{Body, to_bytes};
+    use axum::http::Method;
+    use serde_json::Value;
+    use tokio::sync::watch;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::backend::history::BreakHistory;
+    use crate::backend::idle_monitoring::{IdleMonitor, MockClock, MockIdleChecker};
+    use crate::backend::monitor_actor::IdleMonitorActor;
+    use crate::backend::policy::BreakPolicy;
+
+    // An idle monitor answering requests for a few seconds, behind the router
+    fn make_router_with_actor() -> Router {
+        let mut idle_checker = MockIdleChecker::new();
+        idle_checker
+            .expect_get_idle_time_in_seconds()
+            .return_const(0u64);
+        let mut clock = MockClock::new();
+        clock.expect_get_time().return_const(Utc::now());
+        let config = Config::default();
+        let idle_monitor = IdleMonitor::new(idle_checker, clock, &config.active_profile(), None);
+        let (idle_info_sender, idle_info_recv) = watch::channel(idle_monitor.get_last_idle_info());
+        let (config_sender, config_recv) = watch::channel(config.clone());
+        let (mut actor, idle_monitor) = IdleMonitorActor::new(
+            idle_monitor,
+            idle_info_sender,
+            config,
+            None,
+            config_sender,
+            BreakHistory::new(None),
+            BreakPolicy::default(),
+        );
+        let break_events = actor.subscribe_break_events();
+        thread::spawn(move || {
+            actor.handle_requests_until(Instant::now() + StdDuration::from_secs(5))
+        });
+        make_router(HttpState {
+            idle_monitor,
+            idle_info_recv,
+            config_recv,
+            break_events: Arc::new(break_events),
+            metrics_recv: watch::channel(String::from("stretch_break_mode{mode=\"normal\"} 1\n")).1,
+        })
+    }
+
+    fn make_request(method: Method, uri: &str, body: &str) -> Request {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::HOST, "localhost:7207")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn send(router: &Router, request: Request) -> (StatusCode, Value) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[test]
+    fn only_local_requests_are_answered() {
+        assert!(is_local_request(Some("localhost:7207"), None));
+        assert!(is_local_request(Some("127.0.0.1"), None));
+        assert!(is_local_request(Some("[::1]:7207"), None));
+        assert!(is_local_request(
+            Some("localhost"),
+            Some("chrome-extension://abcdef")
+        ));
+        assert!(is_local_request(
+            Some("localhost"),
+            Some("http://localhost:3000")
+        ));
+        assert!(!is_local_request(Some("attacker.example:7207"), None));
+        assert!(!is_local_request(
+            Some("localhost"),
+            Some("https://attacker.example")
+        ));
+        assert!(!is_local_request(Some("localhost"), Some("null")));
+    }
+
+    #[tokio::test]
+    async fn status_and_commands() {
+        let router = make_router_with_actor();
+
+        let (status, body) = send(&router, make_request(Method::GET, "/status", "")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["idle_info"]["presence_mode"]["type"], "active");
+        assert_eq!(body["widget_info"]["reading_mode"], false);
+
+        let (status, body) = send(&router, make_request(Method::POST, "/mute", "")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["idle_info"]["presence_mode"]["type"], "muted");
+
+        let (status, body) = send(
+            &router,
+            make_request(Method::POST, "/snooze", r#"{"minutes": 30}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["idle_info"]["presence_mode"]["type"], "snoozed_until");
+
+        let (status, body) = send(
+            &router,
+            make_request(Method::POST, "/reading-mode", r#"{"enabled": true}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["widget_info"]["reading_mode"], true);
+
+        let (status, body) = send(&router, make_request(Method::POST, "/break", "")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["idle_info"]["last_mode_state"]["state"], "Break");
+
+        let (status, _) = send(
+            &router,
+            make_request(Method::POST, "/postpone", r#"{"minutes": -1}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn serves_metrics() {
+        let router = make_router_with_actor();
+        let response = router
+            .oneshot(make_request(Method::GET, "/metrics", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "stretch_break_mode{mode=\"normal\"} 1\n");
+    }
+
+    #[tokio::test]
+    async fn requests_from_web_pages_are_refused() {
+        let router = make_router_with_actor();
+        let mut request = make_request(Method::POST, "/break", "");
+        request
+            .headers_mut()
+            .insert(header::ORIGIN, "https://attacker.example".parse().unwrap());
+        let (status, _) = send(&router, request).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn events_stream_starts_with_the_status() {
+        let router = make_router_with_actor();
+        let response = router
+            .oneshot(make_request(Method::GET, "/events", ""))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let mut body = response.into_body().into_data_stream();
+        let first_event = body.next().await.unwrap().unwrap();
+        let first_event = String::from_utf8_lossy(&first_event);
+        assert!(first_event.starts_with("event: status\ndata: {\"widget_info\":"));
+    }
+}
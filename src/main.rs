@@ -1,30 +1,47 @@
 use std::cmp::max;
-use std::io::{BufReader, Cursor};
+use std::mem::discriminant;
 use std::process;
-use std::sync::{Arc, Mutex};
-use std::thread::{self, sleep};
-use std::time::Duration as StdDuration;
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
 mod backend;
-use backend::idle_monitoring::{
-    Clock, DebouncedIdleState, IdleChecker, IdleInfo, IdleMonitor, ModeState,
+use backend::activitywatch::{
+    ActivityWatchClient, ActivityWatchIdleChecker, send_heartbeats_forever, watch_afk_state,
 };
+use backend::idle_monitoring::{AbstractIdleChecker, Clock, IdleChecker, IdleInfo, IdleMonitor};
+use backend::inhibitors::{Inhibitor, watch_idle_inhibitors};
+use backend::media_players::MediaPlayerPauser;
+use backend::monitor_actor::IdleMonitorActor;
+use backend::screen_lock::lock_screen;
+use backend::sounds::{SoundCues, SoundPlayer};
 use chrono::{TimeDelta, Utc};
 use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 use dbus::run_server;
 use relm4::RelmApp;
-use rodio::{Decoder, OutputStream, Sink};
 use single_instance::SingleInstance;
-use tokio::sync::watch::{Sender, channel};
-use tracing::error;
+use tokio::sync::watch::{Receiver, channel};
 mod frontend;
+use frontend::break_window::BREAK_OVERLAY_CSS;
 use frontend::main_window::{MainWindow, MainWindowInit};
 use zbus::{Connection, Error};
 mod icons;
 use crate::dbus::{DBusAppProxy, WidgetInfo};
 use crate::icons::icon_names;
 
-use crate::backend::file_io::PersistableState;
+use crate::backend::break_events::BreakEventKind;
+use crate::backend::config::{Config, ConfigError, ConfigWatcher};
+use crate::backend::exercises::ExerciseLibrary;
+use crate::backend::file_io::{PersistableState, migrate_legacy_state_file};
+use crate::backend::history::{BreakHistory, BreakOutcome};
+use crate::backend::hooks::{get_hooks_folder, run_hooks_forever};
+use crate::backend::metrics::run_metrics_forever;
+use crate::backend::notifications::Notifier;
+use crate::backend::policy::{BreakPolicy, PolicyError};
 mod dbus;
+mod http;
+mod mqtt;
+mod tui;
+use crate::http::run_http_server;
+use crate::mqtt::run_mqtt_forever;
 
 const APP_ID: &str = "io.github.pieterdd.StretchBreak";
 
@@ -33,83 +50,80 @@ pub enum IdleMonitorMessage {
     IdleInfoUpdate(IdleInfo),
 }
 
-fn play_break_end_sound() {
-    thread::spawn(|| {
-        fn helper() -> Result<(), ()> {
-            let (_stream, handle) = OutputStream::try_default().map_err(|_| ())?;
-            let sink = Sink::try_new(&handle).map_err(|_| ())?;
-            let file = BufReader::new(Cursor::new(include_bytes!("sounds/break_end.wav")));
-            let decoder = Decoder::new(file).map_err(|_| ())?;
-            sink.append(decoder);
-            sink.sleep_until_end();
-            Ok(())
-        }
-
-        match helper() {
-            Ok(()) => {}
-            Err(()) => {
-                error!("Could not play break end sound");
-            }
-        }
-    });
-}
-
 fn monitor_idle_forever(
-    idle_monitor_ref: Arc<Mutex<IdleMonitor<IdleChecker, Clock>>>,
-    idle_info_sender: Sender<IdleInfo>,
+    mut actor: IdleMonitorActor<Box<dyn AbstractIdleChecker + Send>, Clock>,
+    mut config_watcher: Option<ConfigWatcher>,
+    idle_inhibitors: Receiver<Vec<Inhibitor>>,
 ) {
     let mut previous_idle_info: Option<IdleInfo> = None;
     let mut last_state_write = Utc::now();
+    let media_player_pauser = MediaPlayerPauser::spawn();
+    let mut break_events = actor.subscribe_break_events();
+    let sound_cues = SoundCues::new(
+        SoundPlayer,
+        SoundCues::<SoundPlayer>::get_sounds_folder().ok(),
+    );
 
     loop {
-        {
-            let idle_info = idle_monitor_ref
-                .lock()
-                .expect("Idle monitor unlock failed")
-                .refresh_idle_info();
+        if let Some(changed_config) = config_watcher.as_mut().and_then(ConfigWatcher::poll) {
+            match changed_config {
+                Ok(config) => {
+                    actor.apply_config(config);
+                }
+                Err(error) => println!("Ignoring changes to settings: {}", error),
+            }
+        }
+
+        actor.set_idle_inhibited(!idle_inhibitors.borrow().is_empty());
+        let idle_info = actor.refresh_idle_info();
+        let mode_changed = previous_idle_info.is_some_and(|previous| {
+            discriminant(&previous.last_mode_state) != discriminant(&idle_info.last_mode_state)
+        });
 
-            if last_state_write
+        // Also write on mode changes, so a restart mid-break doesn't lose the break.
+        if mode_changed
+            || last_state_write
                 .checked_add_signed(TimeDelta::seconds(15))
                 .unwrap()
                 < Utc::now()
-            {
-                let persistable_state = idle_monitor_ref
-                    .lock()
-                    .expect("Idle monitor unlock failed")
-                    .export_persistable_state();
-                if persistable_state.save_to_disk().is_err() {
-                    println!("Tried to write timer state to disk, but failed");
-                }
-                last_state_write = Utc::now();
+        {
+            let persistable_state = actor.idle_monitor().export_persistable_state();
+            if let Err(error) = persistable_state.save_to_disk() {
+                println!("Tried to write timer state to disk, but failed: {}", error);
             }
-            idle_info_sender
-                .send(idle_info)
-                .expect("Could not send idle info");
+            last_state_write = Utc::now();
+        }
 
-            match idle_info.last_mode_state {
-                ModeState::Normal { .. } => {
-                    if let Some(unpacked_value) = previous_idle_info {
-                        match unpacked_value.last_mode_state {
-                            ModeState::Break {
-                                progress_towards_finish: _,
-                                idle_state,
-                            } if matches!(idle_state, DebouncedIdleState::Idle { .. }) => {
-                                // Silently fail if audio isn't available. Make sure we only play
-                                // the sound when the user isn't skipping/postponing a break.
-                                play_break_end_sound();
-                            }
-                            _ => {}
-                        }
+        // Whether a break ran its course can't be told from the state, a skip or postpone
+        // can happen while the user is idle too. Whoever ended the break reports how.
+        while let Ok(break_event) = break_events.try_recv() {
+            match break_event.kind {
+                BreakEventKind::BreakStarted => {
+                    if actor.config().pause_media_during_breaks {
+                        media_player_pauser.break_started();
+                    }
+                    if actor.config().lock_screen_during_breaks {
+                        lock_screen();
                     }
                 }
-                ModeState::PreBreak { .. } => {}
-                ModeState::Break { .. } => {}
-            };
-
-            previous_idle_info = Some(idle_info);
+                BreakEventKind::BreakEnded(BreakOutcome::Completed) => {
+                    media_player_pauser.break_completed();
+                }
+                BreakEventKind::BreakEnded(_) => media_player_pauser.break_abandoned(),
+                _ => {}
+            }
+            sound_cues.play_break_event_cues(break_event.kind, &actor.config().sounds);
         }
+        if let Some(previous) = &previous_idle_info {
+            sound_cues.play_cues(previous, &idle_info, &actor.config().sounds);
+        }
+
+        previous_idle_info = Some(idle_info);
 
-        sleep(StdDuration::from_millis(250));
+        // Commands from the GUI and DBus are served while we wait for the next tick.
+        if !actor.handle_requests_until(Instant::now() + StdDuration::from_millis(250)) {
+            break;
+        }
     }
 }
 
@@ -125,9 +139,19 @@ enum WidgetApiCommand {
     Overtime,
     #[value(help = "Values are 'active', 'snoozed' or 'muted'.")]
     PresenceMode,
+    #[value(help = "Name of the active settings profile.")]
+    Profile,
+}
+
+#[derive(Clone, Subcommand)]
+enum ProfileOperation {
+    #[command(about = "Switch to the named profile.")]
+    Use { name: String },
+    #[command(about = "List all profiles. The active one is marked with an asterisk.")]
+    List,
 }
 
-#[derive(Clone, Copy, Subcommand)]
+#[derive(Clone, Subcommand)]
 enum Operation {
     #[command(about = "Stop prompting for breaks for the specified amount of minutes.")]
     SnoozeFor { minutes: i64 },
@@ -137,13 +161,31 @@ enum Operation {
     Mute,
     #[command(about = "Start a break right now.")]
     Break,
+    #[command(about = "Skip the current break. Not allowed in strict mode.")]
+    Skip,
+    #[command(about = "Postpone the current break by some minutes. Limited in strict mode.")]
+    Postpone { minutes: i64 },
     #[command(about = "When reading mode is active, timer won't reset during idle activity.")]
     SetReadingMode {
         #[arg(action = ArgAction::Set)]
         value: bool,
     },
+    #[command(about = "In strict mode, breaks can't be skipped and only postponed a few times.")]
+    SetStrictMode {
+        #[arg(action = ArgAction::Set)]
+        value: bool,
+        #[arg(long, help = "Confirmation phrase, required to leave strict mode.")]
+        confirmation: Option<String>,
+    },
     #[command(about = "Status data for desktop widgets that source data from terminal commands.")]
     WidgetApi { command: WidgetApiCommand },
+    #[command(about = "Manage settings profiles, as defined in config.toml.")]
+    Profile {
+        #[command(subcommand)]
+        operation: ProfileOperation,
+    },
+    #[command(about = "Live status and break controls in the terminal, for use over SSH.")]
+    Tui,
 }
 
 #[derive(Parser)]
@@ -158,11 +200,6 @@ struct Cli {
 
 #[tokio::main]
 async fn main() -> zbus::Result<()> {
-    #[cfg(target_os = "linux")]
-    if libnotify::init(APP_ID).is_err() {
-        println!("Warning: could not initialize push notifications");
-    }
-
     let cli = Cli::parse();
 
     match cli.operation {
@@ -174,10 +211,6 @@ async fn main() -> zbus::Result<()> {
             let proxy = DBusAppProxy::new(&connection)
                 .await
                 .expect("Could not open DBus proxy");
-            let raw_widget_info = proxy.get_widget_info().await.expect("DBus call failed");
-            let widget_info: WidgetInfo =
-                serde_json::from_str(&raw_widget_info).expect("Could not load widget info");
-
             match operation {
                 Operation::SnoozeFor { minutes } => {
                     proxy
@@ -194,16 +227,60 @@ async fn main() -> zbus::Result<()> {
                 Operation::Break => {
                     proxy.trigger_break().await.expect("Break failed");
                 }
+                Operation::Skip => {
+                    if let Err(error) = proxy.skip_break().await {
+                        println!("Could not skip break: {}", error);
+                        process::exit(1);
+                    }
+                }
+                Operation::Postpone { minutes } => {
+                    if let Err(error) = proxy.postpone_break(minutes).await {
+                        println!("Could not postpone break: {}", error);
+                        process::exit(1);
+                    }
+                }
                 Operation::SetReadingMode { value } => {
                     proxy.set_reading_mode(value).await.expect("Set failed");
                 }
+                Operation::SetStrictMode {
+                    value,
+                    confirmation,
+                } => {
+                    let confirmation = confirmation.unwrap_or_default();
+                    if let Err(error) = proxy.set_strict_mode(value, &confirmation).await {
+                        println!("Could not change strict mode: {}", error);
+                        process::exit(1);
+                    }
+                }
+                Operation::Profile {
+                    operation: ProfileOperation::Use { name },
+                } => {
+                    if let Err(error) = proxy.use_profile(&name).await {
+                        println!("Could not switch profile: {}", error);
+                        process::exit(1);
+                    }
+                }
+                Operation::Profile {
+                    operation: ProfileOperation::List,
+                } => {
+                    let widget_info = get_widget_info(&proxy).await;
+                    for profile in widget_info.profiles {
+                        let marker = if profile == widget_info.active_profile {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        println!("{} {}", marker, profile);
+                    }
+                }
+                Operation::Tui => {
+                    if let Err(error) = tui::run_tui(&proxy).await {
+                        println!("Terminal UI stopped: {}", error);
+                        process::exit(1);
+                    }
+                }
                 Operation::WidgetApi { command } => {
-                    let raw_widget_info = proxy
-                        .get_widget_info()
-                        .await
-                        .expect("Could not retrieve widget info");
-                    let widget_info: WidgetInfo = serde_json::from_str(&raw_widget_info)
-                        .expect("Could not parse widget info");
+                    let widget_info = get_widget_info(&proxy).await;
 
                     match command {
                         WidgetApiCommand::TimeToBreak => {
@@ -226,6 +303,9 @@ async fn main() -> zbus::Result<()> {
                                 }
                             );
                         }
+                        WidgetApiCommand::Profile => {
+                            print!("{}", widget_info.active_profile);
+                        }
                     }
                 }
             }
@@ -234,41 +314,205 @@ async fn main() -> zbus::Result<()> {
     Ok(())
 }
 
+async fn get_widget_info(proxy: &DBusAppProxy<'_>) -> WidgetInfo {
+    let raw_widget_info = proxy
+        .get_widget_info()
+        .await
+        .expect("Could not retrieve widget info");
+    serde_json::from_str(&raw_widget_info).expect("Could not parse widget info")
+}
+
 async fn start_gui(hide: bool) {
     let instance = SingleInstance::new(APP_ID).expect("Initializing single instance object failed");
     if instance.is_single() {
-        let persistable_state = PersistableState::load_from_disk().ok();
-        if persistable_state.is_none() {
-            println!("Could not read settings and timer state from disk. Loading defaults.");
+        if let Err(error) = migrate_legacy_state_file() {
+            println!("Could not migrate settings from older version: {}", error);
         }
-        let idle_monitor = IdleMonitor::new(IdleChecker, Clock, persistable_state);
-        let idle_monitor_arc = Arc::new(Mutex::new(idle_monitor));
-        let idle_monitor_arc2 = idle_monitor_arc.clone();
-        let idle_monitor_arc3 = idle_monitor_arc.clone();
+        let config = match Config::load_from_disk() {
+            Ok(config) => config,
+            Err(ConfigError::NotFound(_)) => {
+                // Give the user a file to edit
+                let config = Config::default();
+                if let Err(error) = config.save_to_disk() {
+                    println!("Could not write default settings: {}", error);
+                }
+                config
+            }
+            Err(error) => {
+                println!("Could not read settings ({}). Loading defaults.", error);
+                Config::default()
+            }
+        };
+        let config_path = Config::get_config_filename().ok();
+        let config_watcher = config_path.clone().map(ConfigWatcher::new);
+        let persistable_state = match PersistableState::load_from_disk() {
+            Ok(persistable_state) => Some(persistable_state),
+            Err(error) => {
+                println!(
+                    "Could not read timer state from disk ({}). Starting fresh.",
+                    error
+                );
+                None
+            }
+        };
+        let idle_checker: Box<dyn AbstractIdleChecker + Send> =
+            match config.activitywatch.use_afk_watcher {
+                true => Box::new(ActivityWatchIdleChecker::new(
+                    watch_afk_state(ActivityWatchClient::new(&config.activitywatch.url)),
+                    IdleChecker,
+                )),
+                false => Box::new(IdleChecker),
+            };
+        let mut idle_monitor = IdleMonitor::new(
+            idle_checker,
+            Clock,
+            &config.active_profile(),
+            persistable_state,
+        );
 
-        let (idle_info_sender, idle_info_receiver) =
-            channel(idle_monitor_arc.lock().unwrap().refresh_idle_info());
+        let policy = match BreakPolicy::get_policy_filename()
+            .map_err(PolicyError::Config)
+            .and_then(|path| BreakPolicy::load_from_path(&path))
+        {
+            Ok(policy) => policy,
+            Err(error) => {
+                println!("Could not load your break policy ({}).", error);
+                BreakPolicy::default()
+            }
+        };
+
+        let mqtt_settings = config.mqtt.clone();
+        let http_settings = config.http.clone();
+        let metrics_settings = config.metrics.clone();
+        let activitywatch_settings = config.activitywatch.clone();
+        let (idle_info_sender, idle_info_receiver) = channel(idle_monitor.refresh_idle_info());
+        let (config_sender, config_receiver) = channel(config.clone());
+        let (actor, idle_monitor_handle) = IdleMonitorActor::new(
+            idle_monitor,
+            idle_info_sender,
+            config,
+            config_path,
+            config_sender,
+            BreakHistory::new(BreakHistory::get_history_filename().ok()),
+            policy,
+        );
+
+        let user_exercises = match ExerciseLibrary::get_exercises_filename()
+            .and_then(|path| ExerciseLibrary::load_user_exercises(&path))
+        {
+            Ok(user_exercises) => user_exercises,
+            Err(error) => {
+                println!("Could not read your own exercises ({}).", error);
+                Vec::new()
+            }
+        };
 
         let (show_main_window_sender, show_main_window_recv) = channel(!hide);
+        let idle_inhibitors_receiver = watch_idle_inhibitors();
 
-        thread::spawn(move || monitor_idle_forever(idle_monitor_arc, idle_info_sender));
+        let idle_inhibitors_receiver_ref = idle_inhibitors_receiver.clone();
+        let break_warning_receiver = actor.subscribe_break_warnings();
+        let gui_break_warning_receiver = actor.subscribe_break_warnings();
+        let hook_break_event_receiver = actor.subscribe_break_events();
+        let mqtt_break_event_receiver = actor.subscribe_break_events();
+        let http_break_event_receiver = actor.subscribe_break_events();
+        let metrics_break_event_receiver = actor.subscribe_break_events();
+        thread::spawn(move || {
+            monitor_idle_forever(actor, config_watcher, idle_inhibitors_receiver_ref)
+        });
+        let hook_config_receiver = config_receiver.clone();
+        thread::spawn(move || {
+            run_hooks_forever(
+                hook_break_event_receiver,
+                hook_config_receiver,
+                get_hooks_folder().ok(),
+            )
+        });
+        if mqtt_settings.enabled {
+            let mqtt_idle_info_receiver = idle_info_receiver.clone();
+            let mqtt_config_receiver = config_receiver.clone();
+            let mqtt_idle_monitor_handle = idle_monitor_handle.clone();
+            thread::spawn(move || {
+                run_mqtt_forever(
+                    mqtt_settings,
+                    mqtt_idle_info_receiver,
+                    mqtt_config_receiver,
+                    mqtt_break_event_receiver,
+                    mqtt_idle_monitor_handle,
+                )
+            });
+        }
+        if activitywatch_settings.enabled {
+            let activitywatch_idle_info_receiver = idle_info_receiver.clone();
+            thread::spawn(move || {
+                send_heartbeats_forever(
+                    ActivityWatchClient::new(&activitywatch_settings.url),
+                    activitywatch_idle_info_receiver,
+                )
+            });
+        }
+        let (metrics_sender, metrics_receiver) = channel(String::new());
+        if http_settings.enabled || metrics_settings.textfile.is_some() {
+            let metrics_idle_info_receiver = idle_info_receiver.clone();
+            thread::spawn(move || {
+                run_metrics_forever(
+                    metrics_settings.textfile,
+                    metrics_settings.interval_secs,
+                    metrics_idle_info_receiver,
+                    metrics_break_event_receiver,
+                    metrics_sender,
+                )
+            });
+        }
+        if http_settings.enabled {
+            let http_idle_info_receiver = idle_info_receiver.clone();
+            let http_config_receiver = config_receiver.clone();
+            let http_idle_monitor_handle = idle_monitor_handle.clone();
+            thread::spawn(move || {
+                if let Err(error) = run_http_server(
+                    http_settings,
+                    http_idle_info_receiver,
+                    http_config_receiver,
+                    http_break_event_receiver,
+                    metrics_receiver,
+                    http_idle_monitor_handle,
+                ) {
+                    println!("Couldn't run HTTP server ({}).", error);
+                }
+            });
+        }
         let idle_info_receiver_ref = idle_info_receiver.clone();
+        let config_receiver_ref = config_receiver.clone();
+        let idle_monitor_handle_ref = idle_monitor_handle.clone();
         thread::spawn(move || {
             match run_server(
                 idle_info_receiver_ref,
+                config_receiver_ref,
+                break_warning_receiver,
                 show_main_window_sender,
-                idle_monitor_arc3,
+                idle_monitor_handle_ref,
             ) {
                 Ok(()) => {}
                 Err(_) => println!("Couldn't run DBus server."),
             }
         });
 
+        let notifier_idle_monitor_handle = idle_monitor_handle.clone();
+        let notifier = Notifier::spawn(move |action| {
+            notifier_idle_monitor_handle.send(action.command(Utc::now()))
+        });
+
         relm4_icons::initialize_icons(icon_names::GRESOURCE_BYTES, icon_names::RESOURCE_PREFIX);
         let app = RelmApp::new(APP_ID);
+        relm4::set_global_css(BREAK_OVERLAY_CSS);
         app.with_args(vec![]).run::<MainWindow>(MainWindowInit {
-            idle_monitor_arc: idle_monitor_arc2,
+            idle_monitor: idle_monitor_handle,
             last_idle_info: idle_info_receiver,
+            config: config_receiver,
+            idle_inhibitors: idle_inhibitors_receiver,
+            exercise_library: ExerciseLibrary::new(user_exercises),
+            break_warnings: gui_break_warning_receiver,
+            notifier,
             show_main_window: show_main_window_recv,
         });
 
@@ -0,0 +1,503 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde_json::{Value, json};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch::Receiver;
+use tokio::time::{MissedTickBehavior, interval, sleep};
+
+use crate::backend::break_events::BreakEvent;
+use crate::backend::config::{Config, Mqtt};
+use crate::backend::idle_monitoring::{IdleInfo, ModeState, PresenceMode};
+use crate::backend::monitor_actor::{IdleMonitorCommand, IdleMonitorHandle};
+use crate::dbus::get_widget_info;
+
+const STATE_TOPIC: &str = "state";
+const EVENT_TOPIC: &str = "event";
+const AVAILABILITY_TOPIC: &str = "availability";
+const MUTE_COMMAND_TOPIC: &str = "mute/set";
+const READING_MODE_COMMAND_TOPIC: &str = "reading_mode/set";
+const COMMAND_TOPIC: &str = "command";
+
+const DEFAULT_SNOOZE_MINUTES: i64 = 60;
+// Timers count down every second, which is too chatty for a broker. In between changes of mode
+// or presence the state goes out this often, and next_break_at lets dashboards count down.
+const STATE_INTERVAL: StdDuration = StdDuration::from_secs(30);
+
+fn topic(settings: &Mqtt, suffix: &str) -> String {
+    format!("{}/{}", settings.topic_prefix.trim_end_matches('/'), suffix)
+}
+
+// Home Assistant wants object ids without slashes or dashes
+fn node_id(settings: &Mqtt) -> String {
+    settings
+        .topic_prefix
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "ON" } else { "OFF" }
+}
+
+// Retained, so a dashboard shows the current state as soon as it subscribes.
+pub fn state_payload(idle_info: &IdleInfo, config: &Config) -> Value {
+    json!({
+        "mode": idle_info.mode_name(),
+        "presence": idle_info.presence_name(),
+        "secs_until_break": idle_info.secs_until_break(),
+        "next_break_at": next_break_at(idle_info).map(|timestamp| timestamp.to_rfc3339()),
+        "overrun_secs": idle_info.overrun.num_seconds(),
+        "muted": on_off(idle_info.is_muted()),
+        "reading_mode": on_off(idle_info.reading_mode),
+        "widget_info": get_widget_info(idle_info, config),
+    })
+}
+
+// Only while the timer runs. It stands still while the user is away.
+fn next_break_at(idle_info: &IdleInfo) -> Option<DateTime<Utc>> {
+    match idle_info.last_mode_state {
+        ModeState::Normal { .. } => {
+            Some(idle_info.last_checked + Duration::seconds(idle_info.secs_until_break()))
+        }
+        ModeState::PreBreak { .. } | ModeState::Break { .. } => None,
+    }
+}
+
+// Changes that are published right away rather than with the next interval
+fn state_key(idle_info: &IdleInfo) -> (&'static str, PresenceMode, bool, bool) {
+    let user_active = match idle_info.last_mode_state {
+        ModeState::Normal { idle_state, .. } | ModeState::Break { idle_state, .. } => {
+            idle_state.is_user_active()
+        }
+        ModeState::PreBreak { .. } => true,
+    };
+    (
+        idle_info.mode_name(),
+        idle_info.presence_mode,
+        idle_info.reading_mode,
+        user_active,
+    )
+}
+
+pub fn event_payload(event: &BreakEvent) -> Value {
+    json!({
+        "event": event.kind.name(),
+        "mode": event.idle_info.mode_name(),
+        "timestamp": event.idle_info.last_checked.to_rfc3339(),
+    })
+}
+
+// Home Assistant discovery: the entities to create and where their configs go.
+pub fn discovery_messages(settings: &Mqtt) -> Vec<(String, Value)> {
+    let node_id = node_id(settings);
+    let state_topic = topic(settings, STATE_TOPIC);
+    let device = json!({
+        "identifiers": [node_id],
+        "name": "Stretch Break",
+        "manufacturer": "Stretch Break",
+    });
+    let entity = |component: &str, object_id: &str, name: &str, mut extra: Value| {
+        let mut config = json!({
+            "name": name,
+            "unique_id": format!("{}_{}", node_id, object_id),
+            "availability_topic": topic(settings, AVAILABILITY_TOPIC),
+            "device": device,
+        });
+        config
+            .as_object_mut()
+            .unwrap()
+            .append(extra.as_object_mut().unwrap());
+        (
+            format!(
+                "{}/{}/{}/{}/config",
+                settings.discovery_prefix, component, node_id, object_id
+            ),
+            config,
+        )
+    };
+    vec![
+        entity(
+            "sensor",
+            "time_to_break",
+            "Time to break",
+            json!({
+                "state_topic": state_topic,
+                "value_template": "{{ value_json.secs_until_break }}",
+                "device_class": "duration",
+                "unit_of_measurement": "s",
+            }),
+        ),
+        entity(
+            "sensor",
+            "next_break",
+            "Next break",
+            json!({
+                "state_topic": state_topic,
+                "value_template": "{{ value_json.next_break_at }}",
+                "device_class": "timestamp",
+            }),
+        ),
+        entity(
+            "sensor",
+            "overrun",
+            "Overrun",
+            json!({
+                "state_topic": state_topic,
+                "value_template": "{{ value_json.overrun_secs }}",
+                "device_class": "duration",
+                "unit_of_measurement": "s",
+            }),
+        ),
+        entity(
+            "sensor",
+            "mode",
+            "Mode",
+            json!({
+                "state_topic": state_topic,
+                "value_template": "{{ value_json.mode }}",
+                "device_class": "enum",
+                "options": ["normal", "pre_break", "break"],
+            }),
+        ),
+        entity(
+            "switch",
+            "mute",
+            "Mute",
+            json!({
+                "state_topic": state_topic,
+                "value_template": "{{ value_json.muted }}",
+                "command_topic": topic(settings, MUTE_COMMAND_TOPIC),
+                "icon": "mdi:bell-off",
+            }),
+        ),
+        entity(
+            "switch",
+            "reading_mode",
+            "Reading mode",
+            json!({
+                "state_topic": state_topic,
+                "value_template": "{{ value_json.reading_mode }}",
+                "command_topic": topic(settings, READING_MODE_COMMAND_TOPIC),
+                "icon": "mdi:book-open-variant",
+            }),
+        ),
+        entity(
+            "button",
+            "start_break",
+            "Start break",
+            json!({
+                "command_topic": topic(settings, COMMAND_TOPIC),
+                "payload_press": "start_break",
+                "icon": "mdi:coffee",
+            }),
+        ),
+    ]
+}
+
+// Commands accepted from the broker. The command topic takes start_break, skip_break,
+// postpone_break and snooze, the last two optionally followed by a number of minutes.
+pub fn parse_command(
+    settings: &Mqtt,
+    config: &Config,
+    topic_name: &str,
+    payload: &str,
+    now: DateTime<Utc>,
+) -> Option<IdleMonitorCommand> {
+    let payload = payload.trim();
+    if topic_name == topic(settings, MUTE_COMMAND_TOPIC) {
+        return match payload {
+            "ON" => Some(IdleMonitorCommand::Mute),
+            "OFF" => Some(IdleMonitorCommand::Unmute),
+            _ => None,
+        };
+    }
+    if topic_name == topic(settings, READING_MODE_COMMAND_TOPIC) {
+        return match payload {
+            "ON" => Some(IdleMonitorCommand::SetReadingMode(true)),
+            "OFF" => Some(IdleMonitorCommand::SetReadingMode(false)),
+            _ => None,
+        };
+    }
+    if topic_name != topic(settings, COMMAND_TOPIC) {
+        return None;
+    }
+    let mut words = payload.split_whitespace();
+    let command = words.next()?;
+    let minutes = match words.next() {
+        Some(minutes) => Some(minutes.parse::<i64>().ok().filter(|minutes| *minutes > 0)?),
+        None => None,
+    };
+    match command {
+        "start_break" => Some(IdleMonitorCommand::TriggerBreak),
+        "skip_break" => Some(IdleMonitorCommand::SkipBreak),
+        "postpone_break" => {
+            let minutes = minutes.or(config.postpone_minutes.first().copied())?;
+            Some(IdleMonitorCommand::PostponeBreak(Duration::minutes(
+                minutes,
+            )))
+        }
+        "snooze" => Some(IdleMonitorCommand::Snooze(
+            now + Duration::minutes(minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES)),
+        )),
+        _ => None,
+    }
+}
+
+fn try_publish(client: &AsyncClient, topic_name: String, retain: bool, payload: String) {
+    if let Err(error) = client.try_publish(topic_name.clone(), QoS::AtLeastOnce, retain, payload) {
+        println!("Could not publish to {}: {}", topic_name, error);
+    }
+}
+
+// Runs until the app quits. The broker may come and go; we keep reconnecting and announce
+// ourselves again each time.
+#[tokio::main]
+pub async fn run_mqtt_forever(
+    settings: Mqtt,
+    mut idle_info_recv: Receiver<IdleInfo>,
+    mut config_recv: Receiver<Config>,
+    mut break_events: broadcast::Receiver<BreakEvent>,
+    idle_monitor: IdleMonitorHandle,
+) {
+    let mut options = MqttOptions::new(node_id(&settings), &settings.host, settings.port);
+    options
+        .set_keep_alive(StdDuration::from_secs(30))
+        .set_last_will(LastWill::new(
+            topic(&settings, AVAILABILITY_TOPIC),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+    if let Some(username) = &settings.username {
+        options.set_credentials(username, settings.password.clone().unwrap_or_default());
+    }
+    let (client, mut event_loop) = AsyncClient::new(options, 64);
+    let mut state_timer = interval(STATE_INTERVAL);
+    state_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // None when the state is due regardless of what changed
+    let mut last_state_key = None;
+
+    loop {
+        tokio::select! {
+            event = event_loop.poll() => match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    println!("Connected to MQTT broker {}", settings.host);
+                    for (topic_name, config) in discovery_messages(&settings) {
+                        try_publish(&client, topic_name, true, config.to_string());
+                    }
+                    try_publish(
+                        &client,
+                        topic(&settings, AVAILABILITY_TOPIC),
+                        true,
+                        String::from("online"),
+                    );
+                    for suffix in [MUTE_COMMAND_TOPIC, READING_MODE_COMMAND_TOPIC, COMMAND_TOPIC] {
+                        if let Err(error) =
+                            client.try_subscribe(topic(&settings, suffix), QoS::AtLeastOnce)
+                        {
+                            println!("Could not subscribe to MQTT commands: {}", error);
+                        }
+                    }
+                    last_state_key = None;
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let payload = String::from_utf8_lossy(&publish.payload);
+                    match parse_command(
+                        &settings,
+                        &config_recv.borrow(),
+                        &publish.topic,
+                        &payload,
+                        Utc::now(),
+                    ) {
+                        Some(command) => idle_monitor.send(command),
+                        None => println!("Ignoring MQTT message {:?} on {}", payload, publish.topic),
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    println!("MQTT connection to {} failed: {}", settings.host, error);
+                    sleep(StdDuration::from_secs(5)).await;
+                    continue;
+                }
+            },
+            changed = idle_info_recv.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+            changed = config_recv.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                last_state_key = None;
+            }
+            _ = state_timer.tick() => last_state_key = None,
+            event = break_events.recv() => match event {
+                Ok(event) => try_publish(
+                    &client,
+                    topic(&settings, EVENT_TOPIC),
+                    false,
+                    event_payload(&event).to_string(),
+                ),
+                Err(RecvError::Lagged(skipped)) => println!("MQTT missed {} events", skipped),
+                Err(RecvError::Closed) => return,
+            },
+        }
+
+        let idle_info = *idle_info_recv.borrow_and_update();
+        let key = state_key(&idle_info);
+        if last_state_key != Some(key) {
+            let state = state_payload(&idle_info, &config_recv.borrow()).to_string();
+            try_publish(&client, topic(&settings, STATE_TOPIC), true, state);
+            last_state_key = Some(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::backend::break_events::BreakEventKind;
+    use crate::backend::idle_monitoring::{DebouncedIdleState, ModeState, PresenceMode};
+
+    fn make_idle_info() -> IdleInfo {
+        let now = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        IdleInfo {
+            reading_mode: true,
+            presence_mode: PresenceMode::Muted,
+            overrun: Duration::seconds(12),
+            ..IdleInfo::for_tests(now)
+        }
+    }
+
+    fn make_settings() -> Mqtt {
+        Mqtt {
+            topic_prefix: String::from("stretch-break/desk"),
+            ..Mqtt::default()
+        }
+    }
+
+    #[test]
+    fn state_is_readable_by_home_assistant() {
+        let state = state_payload(&make_idle_info(), &Config::default());
+        assert_eq!(state["mode"], "normal");
+        assert_eq!(state["secs_until_break"], 300);
+        assert_eq!(state["next_break_at"], "2025-02-03T12:05:00+00:00");
+        assert_eq!(state["overrun_secs"], 12);
+        assert_eq!(state["muted"], "ON");
+        assert_eq!(state["reading_mode"], "ON");
+        assert_eq!(state["widget_info"]["normal_timer_value"], "5:00");
+
+        let event = event_payload(&BreakEvent {
+            kind: BreakEventKind::BreakStarted,
+            idle_info: make_idle_info(),
+        });
+        assert_eq!(event["event"], "break_start");
+    }
+
+    #[test]
+    fn countdown_alone_waits_for_the_interval() {
+        let idle_info = make_idle_info();
+        let a_second_later = IdleInfo {
+            last_checked: idle_info.last_checked + Duration::seconds(1),
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(901),
+                progress_towards_reset: Duration::zero(),
+                idle_state: DebouncedIdleState::Active {
+                    active_since: idle_info.last_checked,
+                },
+            },
+            ..idle_info
+        };
+        assert_eq!(state_key(&idle_info), state_key(&a_second_later));
+
+        let gone_idle = IdleInfo {
+            last_mode_state: ModeState::Normal {
+                progress_towards_break: Duration::seconds(900),
+                progress_towards_reset: Duration::seconds(1),
+                idle_state: DebouncedIdleState::Idle {
+                    idle_since: idle_info.last_checked,
+                },
+            },
+            ..a_second_later
+        };
+        assert_ne!(state_key(&a_second_later), state_key(&gone_idle));
+        let unmuted = IdleInfo {
+            presence_mode: PresenceMode::Active,
+            ..a_second_later
+        };
+        assert_ne!(state_key(&a_second_later), state_key(&unmuted));
+    }
+
+    #[test]
+    fn discovery_announces_sensors_switches_and_a_button() {
+        let messages = discovery_messages(&make_settings());
+        let topics: Vec<&str> = messages
+            .iter()
+            .map(|(topic_name, _)| topic_name.as_str())
+            .collect();
+        assert_eq!(
+            topics,
+            vec![
+                "homeassistant/sensor/stretch_break_desk/time_to_break/config",
+                "homeassistant/sensor/stretch_break_desk/next_break/config",
+                "homeassistant/sensor/stretch_break_desk/overrun/config",
+                "homeassistant/sensor/stretch_break_desk/mode/config",
+                "homeassistant/switch/stretch_break_desk/mute/config",
+                "homeassistant/switch/stretch_break_desk/reading_mode/config",
+                "homeassistant/button/stretch_break_desk/start_break/config",
+            ]
+        );
+        let mute = &messages[4].1;
+        assert_eq!(mute["unique_id"], "stretch_break_desk_mute");
+        assert_eq!(mute["state_topic"], "stretch-break/desk/state");
+        assert_eq!(mute["command_topic"], "stretch-break/desk/mute/set");
+        assert_eq!(
+            mute["availability_topic"],
+            "stretch-break/desk/availability"
+        );
+        assert_eq!(mute["device"]["identifiers"][0], "stretch_break_desk");
+    }
+
+    #[test]
+    fn commands_come_back_from_the_broker() {
+        let settings = make_settings();
+        let config = Config::default();
+        let now = Utc.with_ymd_and_hms(2025, 2, 3, 12, 0, 0).unwrap();
+        let parse = |topic_name: &str, payload: &str| {
+            parse_command(&settings, &config, topic_name, payload, now)
+        };
+
+        assert_eq!(
+            parse("stretch-break/desk/mute/set", "ON"),
+            Some(IdleMonitorCommand::Mute)
+        );
+        assert_eq!(
+            parse("stretch-break/desk/reading_mode/set", "OFF"),
+            Some(IdleMonitorCommand::SetReadingMode(false))
+        );
+        assert_eq!(
+            parse("stretch-break/desk/command", "start_break"),
+            Some(IdleMonitorCommand::TriggerBreak)
+        );
+        assert_eq!(
+            parse("stretch-break/desk/command", "postpone_break"),
+            Some(IdleMonitorCommand::PostponeBreak(Duration::minutes(
+                config.postpone_minutes[0]
+            )))
+        );
+        assert_eq!(
+            parse("stretch-break/desk/command", "snooze 30"),
+            Some(IdleMonitorCommand::Snooze(now + Duration::minutes(30)))
+        );
+        assert_eq!(parse("stretch-break/desk/command", "snooze -5"), None);
+        assert_eq!(parse("stretch-break/desk/mute/set", "maybe"), None);
+        assert_eq!(parse("stretch-break/other/command", "skip_break"), None);
+    }
+}
@@ -0,0 +1,383 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration as StdDuration, SystemTime};
+
+use chrono::{DateTime, Duration, Local, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::backend::config::Config;
+use crate::backend::history::{BreakHistory, BreakOutcome, HistoryEntry};
+use crate::backend::idle_monitoring::{DebouncedIdleState, IdleInfo, ModeState, PresenceMode};
+use crate::dbus::{DBusAppProxy, WidgetInfo};
+use crate::frontend::formatting::format_postpone_duration;
+
+const REFRESH_INTERVAL: StdDuration = StdDuration::from_millis(500);
+const SNOOZE_MINUTES: i64 = 60;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct DayStats {
+    completed: usize,
+    skipped: usize,
+    postponed: usize,
+    postponed_for_meetings: usize,
+}
+
+fn count_today(entries: &[HistoryEntry], now: DateTime<Local>) -> DayStats {
+    let today = now.date_naive();
+    entries
+        .iter()
+        .filter(|entry| entry.timestamp.with_timezone(&Local).date_naive() == today)
+        .fold(DayStats::default(), |mut stats, entry| {
+            match entry.outcome {
+                BreakOutcome::Completed => stats.completed += 1,
+                BreakOutcome::Skipped => stats.skipped += 1,
+                BreakOutcome::Postponed { .. } => stats.postponed += 1,
+                BreakOutcome::PostponedForMeeting { .. } => stats.postponed_for_meetings += 1,
+            }
+            stats
+        })
+}
+
+// What the screen shows, as last heard from the running app
+struct TuiState {
+    widget_info: WidgetInfo,
+    idle_info: IdleInfo,
+    stats: DayStats,
+    // The outcome of the last key press, such as a refused skip
+    message: String,
+}
+
+fn describe_idle_state(idle_state: DebouncedIdleState) -> &'static str {
+    match idle_state {
+        DebouncedIdleState::Active { .. } => "at the computer",
+        DebouncedIdleState::ActiveGoingToIdle { .. } => "going idle",
+        DebouncedIdleState::Idle { .. } => "away",
+        DebouncedIdleState::IdleGoingToActive { .. } => "coming back",
+    }
+}
+
+fn or_dash(value: &str) -> &str {
+    if value.is_empty() { "-" } else { value }
+}
+
+fn status_lines(state: &TuiState) -> Vec<Line<'_>> {
+    let widget_info = &state.widget_info;
+    let idle_info = &state.idle_info;
+    let (mode, idle_state) = match idle_info.last_mode_state {
+        ModeState::Normal { idle_state, .. } => ("Working", describe_idle_state(idle_state)),
+        ModeState::PreBreak { .. } => ("Break coming up", "waiting for you to let go"),
+        ModeState::Break { idle_state, .. } => ("On break", describe_idle_state(idle_state)),
+    };
+    let presence = match (idle_info.presence_mode, &widget_info.snoozed_until_time) {
+        (PresenceMode::Active, _) => String::from("active"),
+        (PresenceMode::SnoozedUntil(_), Some(time)) => format!("snoozed until {}", time),
+        (PresenceMode::SnoozedUntil(_), None) => String::from("snoozed"),
+        (PresenceMode::Muted, _) => String::from("muted"),
+    };
+    let countdown_label = match idle_info.last_mode_state {
+        ModeState::Break { .. } => "Break left",
+        _ => "Resets in",
+    };
+    let row = |label: &'static str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<16}", label), Style::new().bold()),
+            Span::raw(value),
+        ])
+    };
+    vec![
+        row("Mode", String::from(mode)),
+        row(
+            "Next break in",
+            or_dash(&widget_info.normal_timer_value).to_string(),
+        ),
+        row(
+            countdown_label,
+            or_dash(&widget_info.countdown_to_reset_value).to_string(),
+        ),
+        row("Overrun", or_dash(&widget_info.overrun_value).to_string()),
+        row("You are", String::from(idle_state)),
+        row("Breaks", presence),
+        row(
+            "Reading mode",
+            String::from(if widget_info.reading_mode {
+                "on"
+            } else {
+                "off"
+            }),
+        ),
+        row("Profile", widget_info.active_profile.clone()),
+        row(
+            "Today",
+            format!(
+                "{} completed, {} skipped, {} postponed, {} for meetings",
+                state.stats.completed,
+                state.stats.skipped,
+                state.stats.postponed,
+                state.stats.postponed_for_meetings
+            ),
+        ),
+    ]
+}
+
+fn render(frame: &mut Frame, state: &TuiState) {
+    let [status_area, message_area, keys_area] = Layout::vertical([
+        Constraint::Min(11),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    frame.render_widget(
+        Paragraph::new(status_lines(state)).block(Block::bordered().title(" Stretch Break ")),
+        status_area,
+    );
+    frame.render_widget(
+        Paragraph::new(state.message.as_str()).italic(),
+        message_area,
+    );
+    frame.render_widget(
+        Paragraph::new(
+            "b break  s skip  p postpone  z snooze  m mute/unmute  r reading mode  q quit",
+        )
+        .dim(),
+        keys_area,
+    );
+}
+
+async fn fetch(proxy: &DBusAppProxy<'_>) -> zbus::Result<(WidgetInfo, IdleInfo)> {
+    let widget_info = serde_json::from_str(&proxy.get_widget_info().await?)
+        .map_err(|error| zbus::Error::Failure(format!("Could not parse widget info: {}", error)))?;
+    let idle_info = serde_json::from_str(&proxy.get_idle_info().await?)
+        .map_err(|error| zbus::Error::Failure(format!("Could not parse idle info: {}", error)))?;
+    Ok((widget_info, idle_info))
+}
+
+// A day back always reaches the start of today, count_today drops whatever is older.
+fn load_recent_days(path: Option<&Path>) -> Vec<HistoryEntry> {
+    path.map(|path| BreakHistory::load_since(path, Utc::now() - Duration::days(1)))
+        .unwrap_or_default()
+}
+
+fn modified_time(path: Option<&Path>) -> Option<SystemTime> {
+    fs::metadata(path?)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+async fn handle_key(
+    proxy: &DBusAppProxy<'_>,
+    state: &TuiState,
+    code: KeyCode,
+    postpone_minutes: i64,
+) -> zbus::Result<String> {
+    Ok(match code {
+        KeyCode::Char('b') => {
+            proxy.trigger_break().await?;
+            String::from("Break started")
+        }
+        KeyCode::Char('s') => {
+            proxy.skip_break().await?;
+            String::from("Break skipped")
+        }
+        // The app lets a postpone without a due break pass without a word
+        KeyCode::Char('p')
+            if matches!(state.idle_info.last_mode_state, ModeState::Normal { .. }) =>
+        {
+            String::from("No break to postpone yet")
+        }
+        KeyCode::Char('p') => {
+            proxy.postpone_break(postpone_minutes).await?;
+            // Strict mode may have granted less than was asked for
+            let (_, idle_info) = fetch(proxy).await?;
+            let postponed_by = idle_info
+                .last_postpone
+                .unwrap_or(Duration::minutes(postpone_minutes));
+            format!(
+                "Break postponed by {}",
+                format_postpone_duration(&postponed_by)
+            )
+        }
+        KeyCode::Char('z') => {
+            proxy.snooze_for_minutes(SNOOZE_MINUTES).await?;
+            format!("Snoozed for {} minutes", SNOOZE_MINUTES)
+        }
+        KeyCode::Char('m') if state.idle_info.is_muted() => {
+            proxy.unmute().await?;
+            String::from("Breaks are back on")
+        }
+        KeyCode::Char('m') => {
+            proxy.mute().await?;
+            String::from("Muted")
+        }
+        KeyCode::Char('r') => {
+            let reading_mode = !state.widget_info.reading_mode;
+            proxy.set_reading_mode(reading_mode).await?;
+            String::from(if reading_mode {
+                "Reading mode on"
+            } else {
+                "Reading mode off"
+            })
+        }
+        _ => String::new(),
+    })
+}
+
+async fn run(terminal: &mut DefaultTerminal, proxy: &DBusAppProxy<'_>) -> zbus::Result<()> {
+    // Read from disk like the app does, as the running app doesn't share these over D-Bus
+    let postpone_minutes = Config::load_from_disk()
+        .ok()
+        .and_then(|config| config.postpone_minutes.first().copied())
+        .unwrap_or(5);
+    let history_path = BreakHistory::get_history_filename().ok();
+    let mut history_modified = modified_time(history_path.as_deref());
+    let mut history = load_recent_days(history_path.as_deref());
+    let mut message = String::new();
+    loop {
+        let (widget_info, idle_info) = fetch(proxy).await?;
+        // Only read the history again once the app has written to it
+        let modified = modified_time(history_path.as_deref());
+        if modified != history_modified {
+            history_modified = modified;
+            history = load_recent_days(history_path.as_deref());
+        }
+        let state = TuiState {
+            widget_info,
+            idle_info,
+            stats: count_today(&history, Utc::now().with_timezone(&Local)),
+            message: message.clone(),
+        };
+        terminal.draw(|frame| render(frame, &state))?;
+
+        if !event::poll(REFRESH_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+            return Ok(());
+        }
+        message = match handle_key(proxy, &state, key.code, postpone_minutes).await {
+            Ok(message) => message,
+            Err(zbus::Error::MethodError(_, Some(description), _)) => description,
+            Err(error) => return Err(error),
+        };
+    }
+}
+
+// Runs until the user quits, leaving the terminal as it was.
+pub async fn run_tui(proxy: &DBusAppProxy<'_>) -> zbus::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, proxy).await;
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn counts_only_todays_breaks() {
+        let now = Local.with_ymd_and_hms(2025, 2, 3, 15, 0, 0).unwrap();
+        let entry = |hours_ago: i64, outcome| HistoryEntry {
+            timestamp: (now - Duration::hours(hours_ago)).to_utc(),
+            outcome,
+        };
+        let entries = [
+            entry(20, BreakOutcome::Completed),
+            entry(3, BreakOutcome::Completed),
+            entry(2, BreakOutcome::Skipped),
+            entry(
+                1,
+                BreakOutcome::Postponed {
+                    postponed_for_secs: 300,
+                },
+            ),
+            entry(
+                0,
+                BreakOutcome::PostponedForMeeting {
+                    postponed_for_secs: 1800,
+                },
+            ),
+            entry(0, BreakOutcome::Completed),
+        ];
+        assert_eq!(
+            count_today(&entries, now),
+            DayStats {
+                completed: 2,
+                skipped: 1,
+                postponed: 1,
+                postponed_for_meetings: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn shows_timer_idle_state_and_stats() {
+        let now = Utc::now();
+        let state = TuiState {
+            widget_info: WidgetInfo {
+                normal_timer_value: String::from("19:29"),
+                countdown_to_reset_value: String::new(),
+                overrun_value: String::new(),
+                presence_mode: PresenceMode::Active,
+                snoozed_until_time: None,
+                reading_mode: true,
+                active_profile: String::from("coding"),
+                profiles: vec![String::from("coding")],
+            },
+            idle_info: IdleInfo {
+                last_mode_state: ModeState::Normal {
+                    progress_towards_break: Duration::seconds(31),
+                    progress_towards_reset: Duration::zero(),
+                    idle_state: DebouncedIdleState::ActiveGoingToIdle {
+                        active_since: now,
+                        transitioning_since: now,
+                    },
+                },
+                reading_mode: true,
+                ..IdleInfo::for_tests(now)
+            },
+            stats: DayStats {
+                completed: 4,
+                skipped: 1,
+                postponed: 0,
+                postponed_for_meetings: 2,
+            },
+            message: String::from("Reading mode on"),
+        };
+        let mut terminal = Terminal::new(TestBackend::new(80, 14)).unwrap();
+        terminal.draw(|frame| render(frame, &state)).unwrap();
+
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for expected in [
+            "Next break in   19:29",
+            "Resets in       -",
+            "You are         going idle",
+            "Reading mode    on",
+            "Today           4 completed, 1 skipped, 0 postponed, 2 for meetings",
+            "Reading mode on",
+            "q quit",
+        ] {
+            assert!(screen.contains(expected), "missing {:?}", expected);
+        }
+    }
+}